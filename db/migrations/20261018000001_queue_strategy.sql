@@ -0,0 +1,7 @@
+ALTER TABLE jams ADD COLUMN queue_strategy varchar NOT NULL DEFAULT 'most_votes';
+-- the user whose song was played last, used for round robin
+ALTER TABLE jams ADD COLUMN last_user_id char(24) REFERENCES users (id) ON DELETE SET NULL;
+
+-- in milliseconds
+ALTER TABLE songs ADD COLUMN added_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM now()) * 1000)::BIGINT;
+ALTER TABLE users ADD COLUMN joined_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM now()) * 1000)::BIGINT;
@@ -0,0 +1,4 @@
+-- in milliseconds, null if none of the user's songs were played yet
+ALTER TABLE users ADD COLUMN last_played_at BIGINT;
+
+ALTER TABLE jams ADD COLUMN fairness boolean NOT NULL DEFAULT false;
@@ -0,0 +1,19 @@
+CREATE TABLE played_songs (
+  id char(24) UNIQUE PRIMARY KEY NOT NULL,
+  jam_id varchar(6) NOT NULL REFERENCES jams (id) ON DELETE CASCADE,
+  -- null if the song was a recommendation or the user left the jam
+  user_id char(24) REFERENCES users (id) ON DELETE SET NULL,
+  -- kept so the history still shows who added the song after they left
+  user_name varchar(50),
+  spotify_id varchar NOT NULL,
+  name varchar NOT NULL,
+  album varchar NOT NULL,
+  duration int NOT NULL,
+  artists varchar[] NOT NULL,
+  image_url varchar NOT NULL,
+  votes int NOT NULL,
+  -- in milliseconds
+  played_at BIGINT NOT NULL
+);
+
+CREATE INDEX played_songs_jam_id ON played_songs (jam_id, played_at);
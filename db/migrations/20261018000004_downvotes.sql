@@ -0,0 +1,2 @@
+-- 1 for an upvote, -1 for a downvote
+ALTER TABLE votes ADD COLUMN value smallint NOT NULL DEFAULT 1 CHECK (value IN (-1, 1));
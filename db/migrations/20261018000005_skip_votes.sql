@@ -0,0 +1,8 @@
+-- the share of the users that has to vote to skip the current song
+ALTER TABLE jams ADD COLUMN skip_threshold real NOT NULL DEFAULT 0.5;
+
+-- the votes to skip the current song, cleared when the song changes
+CREATE TABLE skip_votes (
+  user_id char(24) UNIQUE PRIMARY KEY NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  jam_id varchar(6) NOT NULL REFERENCES jams (id) ON DELETE CASCADE
+);
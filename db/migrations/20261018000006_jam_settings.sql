@@ -0,0 +1,7 @@
+-- the settings a host can change while the jam is running
+ALTER TABLE jams ADD COLUMN voting boolean NOT NULL DEFAULT true;
+ALTER TABLE jams ADD COLUMN downvotes boolean NOT NULL DEFAULT true;
+ALTER TABLE jams ADD COLUMN allow_explicit boolean NOT NULL DEFAULT true;
+-- in milliseconds, null if there is no limit
+ALTER TABLE jams ADD COLUMN max_song_duration integer CHECK (max_song_duration > 0);
+ALTER TABLE jams ADD COLUMN join_approval boolean NOT NULL DEFAULT false;
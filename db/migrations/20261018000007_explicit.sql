@@ -0,0 +1,2 @@
+ALTER TABLE songs ADD COLUMN explicit boolean NOT NULL DEFAULT false;
+ALTER TABLE played_songs ADD COLUMN explicit boolean NOT NULL DEFAULT false;
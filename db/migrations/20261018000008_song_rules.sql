@@ -0,0 +1,4 @@
+-- songs by these artists can't be added
+ALTER TABLE jams ADD COLUMN blocked_artists varchar[] NOT NULL DEFAULT '{}';
+-- if not empty, only songs with one of these genres can be added
+ALTER TABLE jams ADD COLUMN allowed_genres varchar[] NOT NULL DEFAULT '{}';
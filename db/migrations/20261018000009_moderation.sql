@@ -0,0 +1,4 @@
+-- if on, the songs the users add have to be approved by the host before they are in the queue
+ALTER TABLE jams ADD COLUMN moderated boolean NOT NULL DEFAULT false;
+
+ALTER TABLE songs ADD COLUMN status varchar(10) NOT NULL DEFAULT 'approved' CHECK (status IN ('pending', 'approved'));
@@ -0,0 +1,8 @@
+-- the songs the host adds are stored with the jam's own user like the current song,
+-- so the current song is told apart by its status
+ALTER TABLE songs DROP CONSTRAINT songs_status_check;
+ALTER TABLE songs ADD CONSTRAINT songs_status_check CHECK (status IN ('pending', 'approved', 'playing'));
+UPDATE songs SET status = 'playing' WHERE user_id IN (SELECT id FROM users WHERE id = jam_id);
+
+-- pinned songs are played before the rest of the queue
+ALTER TABLE songs ADD COLUMN pinned boolean NOT NULL DEFAULT false;
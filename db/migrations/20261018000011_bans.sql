@@ -0,0 +1,13 @@
+-- the token from the device cookie the user joined with, null for the jam itself
+ALTER TABLE users ADD COLUMN device_id char(24);
+
+CREATE TABLE banned_users (
+  id char(24) UNIQUE PRIMARY KEY NOT NULL,
+  jam_id varchar(6) NOT NULL REFERENCES jams (id) ON DELETE CASCADE,
+  device_id char(24) NOT NULL,
+  -- kept so the host knows who they are unbanning
+  name varchar(50) NOT NULL,
+  -- in milliseconds
+  banned_at BIGINT NOT NULL,
+  UNIQUE (jam_id, device_id)
+);
@@ -0,0 +1,2 @@
+-- with join approval on, new users wait until the host admits them
+ALTER TABLE users ADD COLUMN status varchar(10) NOT NULL DEFAULT 'admitted' CHECK (status IN ('waiting', 'admitted'));
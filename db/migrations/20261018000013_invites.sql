@@ -0,0 +1,15 @@
+-- a jam with a passcode can only be joined with it or with an invite link
+ALTER TABLE jams ADD COLUMN passcode varchar(12);
+
+CREATE TABLE invites (
+  id char(24) UNIQUE PRIMARY KEY NOT NULL,
+  jam_id varchar(6) NOT NULL REFERENCES jams (id) ON DELETE CASCADE,
+  -- the signed id that goes into the link
+  token varchar NOT NULL,
+  -- in milliseconds, null if the invite never expires
+  expires_at BIGINT,
+  -- null if the invite can be used any number of times
+  max_uses int,
+  uses int NOT NULL DEFAULT 0,
+  created_at BIGINT NOT NULL
+);
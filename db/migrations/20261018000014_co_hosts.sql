@@ -0,0 +1,2 @@
+-- a user with any permissions is a co-host, the values are the ones of `Permission::as_str`
+ALTER TABLE users ADD COLUMN permissions varchar[] NOT NULL DEFAULT '{}';
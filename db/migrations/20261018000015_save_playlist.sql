@@ -0,0 +1,2 @@
+-- if on, the songs that were played are saved as a playlist on the host's spotify account when the jam is deleted
+ALTER TABLE jams ADD COLUMN save_playlist boolean NOT NULL DEFAULT false;
@@ -82,6 +82,8 @@ pub fn SongListTest() -> impl IntoView {
             votes: 0,
             have_you_voted: None,
         },
+        explicit: false,
+        pinned: false,
     };
     let songs = {
         let mut songs = Vec::new();
@@ -98,8 +100,11 @@ pub fn SongListTest() -> impl IntoView {
     let max_song_count = Signal::derive(|| 10);
     let song_list_action = SongListAction::Vote {
         add_vote: Callback::new(|id| log!("add vote with id:{}", id)),
+        add_downvote: Callback::new(|id| log!("add downvote with id:{}", id)),
         remove_vote: Callback::new(|id| log!("remove vote with id:{}", id)),
         remove_song: Callback::new(|id| log!("remove song with id:{}", id)),
+        voting: Signal::derive(|| true),
+        downvotes: Signal::derive(|| true),
     };
     view! { <SongList songs votes max_song_count song_list_action /> }
 }
@@ -114,46 +119,55 @@ pub fn UserBartTest() -> impl IntoView {
             id: "tb0k2ujdagg6bvvqeqlx2qgq".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "coe7474an5pkiptmjls2bq0w".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kakamaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "bl0m5ktr6bs51hnbmkp8bs0c".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kakamakanaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "tb0k2ujdagg6bvvqeqlx2qgq".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "coe7474an5pkiptmjls2bq0w".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kakamaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "bl0m5ktr6bs51hnbmkp8bs0c".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kakamakanaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "tb0k2ujdagg6bvvqeqlx2qgq".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "coe7474an5pkiptmjls2bq0w".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kakamaka".to_string(),
+            permissions: vec![],
         },
         User {
             id: "bl0m5ktr6bs51hnbmkp8bs0c".to_string(),
             jam_id: "niggaa".to_string(),
             name: "kakamakanaka".to_string(),
+            permissions: vec![],
         },
     ];
     let (users, set_users) = signal(Some(users));
@@ -201,6 +215,8 @@ fn SearchTest() -> impl IntoView {
             votes: 0,
             have_you_voted: None,
         },
+        explicit: false,
+        pinned: false,
     };
 
     let songs = {
@@ -249,6 +265,8 @@ fn UserPlayerTest() -> impl IntoView {
             votes: 0,
             have_you_voted: None,
         },
+        explicit: false,
+        pinned: false,
     }));
     let position = Signal::derive(|| 0.7);
 
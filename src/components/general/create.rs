@@ -15,35 +15,50 @@ async fn redirect_to_spotify_oauth() -> Result<(), ServerFnError> {
     let query = query!("INSERT INTO hosts(id) VALUES ($1)", &host_id);
     let pool = app_state.db.pool;
     query.execute(&pool).await?;
-    redirect(
-        format!(
-            "https://accounts.spotify.com/authorize?response_type=code&client_id={}&scope={}&redirect_uri={}/create-host&state={}&show_dialog=true"
-            ,app_state.spotify_credentials.id
-            ,"user-read-playback-state user-modify-playback-state user-read-currently-playing streaming user-read-private user-read-email user-read-recently-played user-top-read"
-            ,app_state.site_url
-            ,host_id
-        ).as_str()
-    );
+    // the host id is signed, so only the one who was sent here can create a session for it
+    let state = crate::model::oauth_state(&host_id, &app_state.secret_key);
+    if !app_state.music_provider.is_spotify() {
+        // there is no spotify account to connect, the host can be created right away
+        redirect(format!("/create-host?state={}", state).as_str());
+        return Ok(());
+    }
+    redirect(&crate::model::spotify_authorize_url(
+        &app_state.spotify_credentials,
+        &app_state.site_url,
+        &state,
+    ));
     Ok(())
 }
 
 #[server]
 async fn create_jam(
     name: String,
-    host_id: String,
     max_song_count: i16,
+    queue_strategy: QueueStrategy,
+    fairness: bool,
+    skip_threshold: f32,
 ) -> Result<JamId, ServerFnError> {
-    use crate::model::{create_jam, AppState, Error};
+    use crate::model::{create_jam, get_session_token, verify_session, AppState, Error};
     let app_state = expect_context::<AppState>();
+    // the host has no jam yet, so only the session is checked and not what the id belongs to
+    let headers: http::HeaderMap = leptos_axum::extract().await?;
+    let host_id = verify_session(get_session_token(&headers, None)?, &app_state.secret_key)?;
     let mut transaction = app_state.db.pool.begin().await?;
-    let credentials = app_state.spotify_credentials;
+
+    let settings = JamSettings {
+        queue_strategy,
+        fairness,
+        skip_threshold,
+        ..Default::default()
+    };
 
     let jam_id = match create_jam(
         &name,
         &host_id,
         max_song_count,
+        &settings,
         &mut transaction,
-        credentials.clone(),
+        &app_state.music_provider,
     )
     .await
     {
@@ -65,23 +80,36 @@ async fn create_jam(
 pub fn CreateIsland() -> impl IntoView {
     use gloo::storage::{errors::StorageError, LocalStorage, Storage};
 
-    let (host_id, set_host_id) = signal(None::<String>);
+    // the host session, none if spotify is not connected yet
+    let (session, set_session) = signal(None::<Session>);
 
     let (name, set_name) = signal(String::from(""));
     let (max_song_count, set_max_song_count) = signal::<i16>(1);
+    let (queue_strategy, set_queue_strategy) = signal(QueueStrategy::default());
+    let (fairness, set_fairness) = signal(false);
+    let (skip_percentage, set_skip_percentage) = signal::<u8>(50);
 
     let (error_message, set_error_message) =
         signal(String::from("there is no error lol, this is a bug"));
     let (show_dialog, set_show_dialog) = signal(false);
 
-    Effect::new(move |_| match LocalStorage::get::<String>("host_id") {
-        Ok(id) => set_host_id(Some(id)),
-        Err(StorageError::KeyNotFound(_)) => (),
-        Err(e) => {
-            set_error_message(format!("Error getting host id from local storage: {}", e));
-            set_show_dialog(true);
-        }
-    });
+    Effect::new(
+        move |_| match LocalStorage::get::<Session>("host_session") {
+            // with an expired session spotify has to be connected again
+            Ok(session) if session.has_expired(js_sys::Date::now() as i64) => {
+                LocalStorage::delete("host_session");
+            }
+            Ok(session) => set_session(Some(session)),
+            Err(StorageError::KeyNotFound(_)) => (),
+            Err(e) => {
+                set_error_message(format!(
+                    "Error getting host session from local storage: {}",
+                    e
+                ));
+                set_show_dialog(true);
+            }
+        },
+    );
 
     let redirect_to_oauth = move || {
         spawn_local(async move {
@@ -95,11 +123,22 @@ pub fn CreateIsland() -> impl IntoView {
     let create = Action::new(move |_: &()| {
         let name = name.get_untracked();
         let max_song_count = max_song_count();
+        let queue_strategy = queue_strategy.get_untracked();
+        let fairness = fairness.get_untracked();
+        let skip_threshold = skip_percentage.get_untracked() as f32 / 100.0;
         async move {
-            match host_id.get_untracked() {
-                Some(host_id) => {
+            match session.get_untracked() {
+                Some(_) => {
                     if !name.is_empty() {
-                        match create_jam(name, host_id, max_song_count).await {
+                        match create_jam(
+                            name,
+                            max_song_count,
+                            queue_strategy,
+                            fairness,
+                            skip_threshold,
+                        )
+                        .await
+                        {
                             Ok(jam_id) => {
                                 let navigate = use_navigate();
                                 navigate(
@@ -130,7 +169,7 @@ pub fn CreateIsland() -> impl IntoView {
         </Modal>
         <div class="big-space-island" id="create-island">
             {move || {
-                if host_id.with(Option::is_some) {
+                if session.with(Option::is_some) {
                     Either::Left(
                         view! {
                             <div class="input-with-label">
@@ -161,6 +200,59 @@ pub fn CreateIsland() -> impl IntoView {
                                         min=1
                                     />
                                 </div>
+                                <div class="input-with-label">
+                                    <label for="create-jam-queue-strategy">"Queue Order"</label>
+                                    <select
+                                        on:change=move |ev| {
+                                            if let Ok(strategy) = event_target_value(&ev).parse() {
+                                                set_queue_strategy(strategy);
+                                            }
+                                        }
+                                        class="text-input"
+                                        id="create-jam-queue-strategy"
+                                    >
+                                        {QueueStrategy::ALL
+                                            .into_iter()
+                                            .map(|strategy| {
+                                                view! {
+                                                    <option
+                                                        value=strategy.as_str()
+                                                        selected=move || queue_strategy() == strategy
+                                                    >
+                                                        {strategy.display_name()}
+                                                    </option>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </select>
+                                </div>
+                                <div class="input-with-label">
+                                    <label for="create-jam-skip">"Votes Needed To Skip (%)"</label>
+                                    <input
+                                        type="number"
+                                        prop:value=skip_percentage
+                                        on:input=move |ev| set_skip_percentage(
+                                            event_target_value(&ev).parse().unwrap_or(50).min(100),
+                                        )
+
+                                        placeholder="ex. 50"
+                                        class="text-input"
+                                        id="create-jam-skip"
+                                        min=1
+                                        max=100
+                                    />
+                                </div>
+                                <div class="checkbox-with-label">
+                                    <input
+                                        type="checkbox"
+                                        prop:checked=fairness
+                                        on:change=move |ev| set_fairness(event_target_checked(&ev))
+                                        id="create-jam-fairness"
+                                    />
+                                    <label for="create-jam-fairness">
+                                        "Let everyone have a turn before the same person is played again"
+                                    </label>
+                                </div>
                             </div>
 
                             <button
@@ -0,0 +1,51 @@
+use crate::components::general::millis_to_min_sec;
+use crate::model::types::*;
+use leptos::{either::Either, prelude::*};
+
+/// the recently played songs of the jam, the most recent first
+#[component]
+pub fn History(#[prop(into)] history: Signal<Option<Vec<PlayedSong>>>) -> impl IntoView {
+    view! {
+        <div class="history">
+            <div class="header">"Recently played"</div>
+            {move || {
+                if history.with(|h| h.as_ref().map(Vec::is_empty).unwrap_or(true)) {
+                    Either::Left(view! { <div class="no-songs">"Nothing was played yet"</div> })
+                } else {
+                    Either::Right(())
+                }
+            }}
+            <div class="songs">
+                <For
+                    each=move || history().unwrap_or_default().into_iter()
+                    key=|played| played.song.id.clone()
+                    children=move |played| {
+                        view! {
+                            <div class="played-song" title=played.song.name.clone()>
+                                <img
+                                    src=played.song.image_url.clone()
+                                    alt=format!("This is the album cover of {}", played.song.name)
+                                />
+                                <div class="info-text">
+                                    <div class="title">{played.song.name.clone()}</div>
+                                    <div class="small-info">
+                                        {played.song.artists.join(", ")} <span class="bullet-point">
+                                            "•"
+                                        </span> {millis_to_min_sec(played.song.duration)}
+                                    </div>
+                                    <div class="small-info">
+                                        {match played.user_name {
+                                            Some(name) => format!("added by {}", name),
+                                            None => "recommended".to_string(),
+                                        }} <span class="bullet-point">"•"</span>
+                                        {format!("{} votes", played.song.votes.votes)}
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    }
+                />
+            </div>
+        </div>
+    }
+}
@@ -4,11 +4,30 @@ use leptos::{either::EitherOf3, prelude::*, task::spawn_local};
 use leptos_router::{hooks::use_navigate, NavigateOptions};
 use leptos::either::Either;
 
+/// an error means the jam exists but the passcode is missing or wrong
 #[server]
-async fn does_jam_exist(jam_code: String) -> Result<bool, ServerFnError> {
-    use crate::model::{self, AppState};
+async fn does_jam_exist(
+    jam_code: String,
+    passcode: Option<String>,
+) -> Result<bool, ServerFnError> {
+    use crate::model::{self, AppState, JoinCredentials};
     let app_state = expect_context::<AppState>();
-    Ok(model::dose_jam_exist(&jam_code, &app_state.db.pool).await?)
+    let mut transaction = app_state.db.pool.begin().await?;
+    if !model::dose_jam_exist(&jam_code, &mut *transaction).await? {
+        return Ok(false);
+    }
+    let credentials = JoinCredentials {
+        passcode,
+        invite: None,
+    };
+    model::check_join_access(
+        &jam_code,
+        &credentials,
+        &app_state.secret_key,
+        &mut transaction,
+    )
+    .await?;
+    Ok(true)
 }
 
 #[derive(Debug, Clone)]
@@ -21,14 +40,17 @@ enum State {
 #[component]
 pub fn JoinIsland() -> impl IntoView {
     let (jam_code, set_jam_code) = signal(String::from(""));
+    let (passcode, set_passcode) = signal(String::from(""));
     let (state, set_state) = signal(State::None);
 
     let on_click = move |_| {
         set_state(State::Loading);
         spawn_local(async move {
-            let res = does_jam_exist(jam_code.get_untracked()).await;
+            let passcode = Some(passcode.get_untracked()).filter(|p| !p.is_empty());
+            let res = does_jam_exist(jam_code.get_untracked(), passcode.clone()).await;
 
             match res {
+                Err(ServerFnError::ServerError(message)) => set_state(State::Error(message)),
                 Err(e) => set_state(State::Error(format!(
                     "Error checking if jam exists: {:#?}",
                     e
@@ -40,10 +62,15 @@ pub fn JoinIsland() -> impl IntoView {
                     set_state(State::None);
                     if !jam_code.with_untracked(String::is_empty) {
                         let navigate = use_navigate();
-                        navigate(
-                            format!("/create-user/{}", jam_code.get_untracked()).as_str(),
-                            NavigateOptions::default(),
-                        );
+                        let path = match passcode {
+                            Some(passcode) => format!(
+                                "/create-user/{}?passcode={}",
+                                jam_code.get_untracked(),
+                                passcode
+                            ),
+                            None => format!("/create-user/{}", jam_code.get_untracked()),
+                        };
+                        navigate(path.as_str(), NavigateOptions::default());
                     }
                 }
             }
@@ -81,6 +108,18 @@ pub fn JoinIsland() -> impl IntoView {
                     class:glass-element-err=move || jam_code.with(String::is_empty)
                 />
             </div>
+            <div class="input-with-label">
+                <label for="join-passcode-input">"Passcode (if the jam has one)"</label>
+                <input
+                    type="text"
+                    maxlength=12
+                    prop:value=passcode
+                    on:input=move |ev| set_passcode(event_target_value(&ev))
+                    placeholder="ex. party42"
+                    class="text-input"
+                    id="join-passcode-input"
+                />
+            </div>
             <button on:click=on_click class="button">
                 {move||match state() {
                     State::Loading => Either::Left(view!{<SpinnyLoading/>}),
@@ -1,4 +1,5 @@
 pub mod create;
+pub mod history;
 pub mod join;
 pub mod modal;
 pub mod share;
@@ -10,6 +11,8 @@ pub mod player;
 #[allow(unused_imports)]
 pub use create::*;
 #[allow(unused_imports)]
+pub use history::*;
+#[allow(unused_imports)]
 pub use join::*;
 #[allow(unused_imports)]
 pub use modal::*;
@@ -19,8 +19,12 @@ async fn save_to_clipboard(text: &str) {
     }
 }
 
+/// Shows the link to join the jam as a qr code, with an invite token the link is the invite link
 #[component]
-pub fn Share(#[prop(into)] jam_id: Signal<String>) -> impl IntoView {
+pub fn Share(
+    #[prop(into)] jam_id: Signal<String>,
+    #[prop(optional, into)] invite: Option<Signal<Option<String>>>,
+) -> impl IntoView {
     let (base_url, set_base_url) = signal(String::new());
 
     let (clone_jam_id, set_jam_id) = signal(String::new());
@@ -51,12 +55,23 @@ pub fn Share(#[prop(into)] jam_id: Signal<String>) -> impl IntoView {
         }
     }
 
+    let invite = move || invite.and_then(|invite| invite.get());
+
+    let link = Signal::derive(move || {
+        let link = jam_id.with(|id| base_url.with(|url| format!("{}/create-user/{}", url, id)));
+        match invite() {
+            Some(token) => format!("{}?invite={}", link, token),
+            None => link,
+        }
+    });
+
     let qr = Signal::derive(move || {
-        match QrCode::with_version(
-            jam_id.with(move |id| base_url.with(move |url| format!("{}/create-user/{}", url, id))),
-            Version::Normal(10),
-            EcLevel::Q,
-        ) {
+        // the invite links are too long for the fixed version, so the smallest one that fits is used
+        let qr = match invite() {
+            Some(_) => QrCode::with_error_correction_level(link.get(), EcLevel::Q),
+            None => QrCode::with_version(link.get(), Version::Normal(10), EcLevel::Q),
+        };
+        match qr {
             Ok(qr) => qr
                 .render()
                 .quiet_zone(false)
@@ -78,10 +93,13 @@ pub fn Share(#[prop(into)] jam_id: Signal<String>) -> impl IntoView {
             <button
                 class="button"
                 on:click=move |_| {
-                    let id = jam_id.read_untracked();
-                    log!("copying to clipboard: {}", id);
+                    // with an invite the code alone is not enough to join, so the whole link is copied
+                    let text = match invite() {
+                        Some(_) => link.get_untracked(),
+                        None => jam_id.get_untracked(),
+                    };
                     task::spawn_local(async move {
-                        save_to_clipboard(&jam_id.get_untracked()).await
+                        save_to_clipboard(&text).await
                     });
                 }
             >
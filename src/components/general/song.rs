@@ -12,12 +12,17 @@ use leptos::{
 pub enum SongAction {
     Vote {
         add_vote: Callback<String>,
+        add_downvote: Callback<String>,
         remove_vote: Callback<String>,
         vote: Signal<Vote>,
+        voting: Signal<bool>,
+        downvotes: Signal<bool>,
     },
     Remove {
         remove: Callback<String>,
         vote: Signal<Vote>,
+        /// only the host can pin songs, with the song id and if it should be pinned
+        pin: Option<Callback<(String, bool)>>,
     },
     Add(Callback<String>),
 }
@@ -67,27 +72,41 @@ pub fn Song(#[prop(optional_no_strip)] song: Option<Song>, song_type: SongAction
                 title=song.name
                 class:voted=move || {
                     if let SongAction::Vote { vote, .. } = song_type {
-                        vote().have_you_voted.unwrap_or(false)
+                        vote().have_you_voted == Some(YourVote::Up)
+                    } else {
+                        false
+                    }
+                }
+
+                class:downvoted=move || {
+                    if let SongAction::Vote { vote, .. } = song_type {
+                        vote().have_you_voted == Some(YourVote::Down)
                     } else {
                         false
                     }
                 }
 
                 class:remove=song_type.is_remove()
+                class:pinned=song.pinned
                 on:click={
                     let spotify_song_id = song.spotify_id.clone();
                     let song_id = song.id.clone().unwrap_or_default();
                     move |_| {
                         match song_type {
-                            SongAction::Vote { add_vote, remove_vote, vote } => {
-                                if let Some(vote) = vote().have_you_voted {
-                                    if vote {
+                            SongAction::Vote { add_vote, remove_vote, vote, voting, .. } => {
+                                if !voting.get_untracked() {
+                                    return;
+                                }
+                                match vote().have_you_voted {
+                                    Some(YourVote::Up) => {
                                         log!("Removing vote");
                                         remove_vote.run(song_id.clone())
-                                    } else {
+                                    }
+                                    Some(YourVote::NotVoted | YourVote::Down) => {
                                         log!("Adding vote");
                                         add_vote.run(song_id.clone())
                                     }
+                                    None => {}
                                 }
                             }
                             SongAction::Remove { remove, .. } => remove.run(song_id.clone()),
@@ -164,14 +183,59 @@ pub fn Song(#[prop(optional_no_strip)] song: Option<Song>, song_type: SongAction
                             </div>
                             <span class="bullet-point">"•"</span>
                             <span class="song-duration">{millis_to_min_sec(song.duration)}</span>
+                            {if song.explicit {
+                                Either::Left(
+                                    view! {
+                                        <span class="explicit" title="explicit">
+                                            "E"
+                                        </span>
+                                    },
+                                )
+                            } else {
+                                Either::Right(())
+                            }}
                         </div>
                     </div>
                 </div>
 
                 <div class="action">
                     {match song_type {
-                        SongAction::Vote { vote, .. } => {
-                            EitherOf3::A(view! { <div class="votes">{move || vote().votes}</div> })
+                        SongAction::Vote { vote, add_downvote, remove_vote, voting, downvotes, .. } => {
+                            let song_id = song.id.clone().unwrap_or_default();
+                            EitherOf3::A(
+                                view! {
+                                    <div class="votes">{move || vote().votes}</div>
+                                    <button
+                                        class="downvote"
+                                        title="downvote"
+                                        style:display=move || {
+                                            match voting() && downvotes() {
+                                                true => "",
+                                                false => "none",
+                                            }
+                                        }
+                                        on:click=move |ev| {
+                                            ev.stop_propagation();
+                                            match vote().have_you_voted {
+                                                Some(YourVote::Down) => {
+                                                    log!("Removing downvote");
+                                                    remove_vote.run(song_id.clone())
+                                                }
+                                                Some(YourVote::NotVoted | YourVote::Up) => {
+                                                    log!("Adding downvote");
+                                                    add_downvote.run(song_id.clone())
+                                                }
+                                                None => {}
+                                            }
+                                        }
+                                    >
+                                        <svg
+                                            viewBox=icondata::BsHandThumbsDown.view_box
+                                            inner_html=icondata::BsHandThumbsDown.data
+                                        ></svg>
+                                    </button>
+                                },
+                            )
                         }
                         SongAction::Add(_) => {
                             EitherOf3::B(
@@ -184,8 +248,32 @@ pub fn Song(#[prop(optional_no_strip)] song: Option<Song>, song_type: SongAction
                                 },
                             )
                         }
-                        SongAction::Remove { vote, .. } => {
-                            EitherOf3::C(view! { {move || vote().votes} })
+                        SongAction::Remove { vote, pin, .. } => {
+                            let song_id = song.id.clone().unwrap_or_default();
+                            let pinned = song.pinned;
+                            let pin_button = pin
+                                .map(|pin| {
+                                    let icon = match pinned {
+                                        true => icondata::BsPinAngleFill,
+                                        false => icondata::BsPinAngle,
+                                    };
+                                    view! {
+                                        <button
+                                            class="pin"
+                                            title=match pinned {
+                                                true => "unpin",
+                                                false => "play next",
+                                            }
+                                            on:click=move |ev| {
+                                                ev.stop_propagation();
+                                                pin.run((song_id.clone(), !pinned));
+                                            }
+                                        >
+                                            <svg viewBox=icon.view_box inner_html=icon.data></svg>
+                                        </button>
+                                    }
+                                });
+                            EitherOf3::C(view! { {pin_button} {move || vote().votes} })
                         }
                     }}
 
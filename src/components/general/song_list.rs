@@ -6,10 +6,18 @@ use leptos::{either::Either, logging::log, prelude::*};
 pub enum SongListAction {
     Vote {
         add_vote: Callback<String>,
+        add_downvote: Callback<String>,
         remove_vote: Callback<String>,
         remove_song: Callback<String>,
+        /// if off, the songs can't be voted on
+        voting: Signal<bool>,
+        downvotes: Signal<bool>,
+    },
+    Remove {
+        remove: Callback<String>,
+        /// pins the song to be played next, with the song id and if it should be pinned
+        pin: Callback<(String, bool)>,
     },
-    Remove(Callback<String>),
     Add(Callback<String>),
 }
 
@@ -18,7 +26,7 @@ impl SongListAction {
         matches!(self, SongListAction::Vote { .. })
     }
     pub fn is_remove(&self) -> bool {
-        matches!(self, SongListAction::Remove(_))
+        matches!(self, SongListAction::Remove { .. })
     }
     pub fn is_add(&self) -> bool {
         matches!(self, SongListAction::Add(_))
@@ -53,8 +61,9 @@ pub fn SongList(
                     song
                 })
                 .collect::<Vec<_>>();
-            if let SongListAction::Remove(_) = song_list_action {
-                songs.sort_by_key(|song| song.votes.votes);
+            if let SongListAction::Remove { .. } = song_list_action {
+                // the pinned songs are played next, so they stay on top
+                songs.sort_by_key(|song| (song.pinned, song.votes.votes));
             }
             let songs = songs.into_iter().rev().collect::<Vec<_>>();
             Some(songs)
@@ -185,17 +194,28 @@ pub fn SongList(
                                                     log!("votes: {:#?}, song name:{}", votes(), name);
                                                 });
                                                 let song_action = match song_list_action {
-                                                    SongListAction::Vote { add_vote, remove_vote, .. } => {
+                                                    SongListAction::Vote {
+                                                        add_vote,
+                                                        add_downvote,
+                                                        remove_vote,
+                                                        voting,
+                                                        downvotes,
+                                                        ..
+                                                    } => {
                                                         SongAction::Vote {
                                                             add_vote,
+                                                            add_downvote,
                                                             remove_vote,
                                                             vote: votes.into(),
+                                                            voting,
+                                                            downvotes,
                                                         }
                                                     }
-                                                    SongListAction::Remove(cb) => {
+                                                    SongListAction::Remove { remove, pin } => {
                                                         SongAction::Remove {
-                                                            remove: cb,
+                                                            remove,
                                                             vote: votes.into(),
+                                                            pin: Some(pin),
                                                         }
                                                     }
                                                     SongListAction::Add(cb) => SongAction::Add(cb),
@@ -260,6 +280,7 @@ pub fn SongList(
                                                     let song_action = SongAction::Remove {
                                                         remove: remove_song,
                                                         vote: votes.into(),
+                                                        pin: None,
                                                     };
                                                     Either::Left(
                                                         view! { <Song song=Some(song) song_type=song_action /> },
@@ -7,6 +7,14 @@ use std::rc::Rc;
 pub fn UsersBar(
     #[prop(into)] users: Signal<Option<Vec<User>>>,
     #[prop(optional)] kick_user: Option<Callback<String>>,
+    /// only the host can ban, the banned users are listed under the bar so they can be unbanned
+    #[prop(optional)]
+    ban_user: Option<Callback<String>>,
+    #[prop(optional, into)] banned: Option<Signal<Vec<BannedUser>>>,
+    #[prop(optional)] unban_user: Option<Callback<String>>,
+    /// only the host can make users co-hosts, the users are listed under the bar with what they are allowed to do
+    #[prop(optional)]
+    set_permissions: Option<Callback<(String, Vec<Permission>)>>,
     close: Callback<()>,
 ) -> impl IntoView {
     view! {
@@ -45,11 +53,15 @@ pub fn UsersBar(
                 }}
                 <For
                     each=move || users().unwrap_or_default()
-                    key=|user| user.id.clone()
+                    key=|user| (user.id.clone(), user.permissions.clone())
                     children=move |user| {
                         let user_id = Rc::new(user.id);
+                        let class = match user.permissions.is_empty() {
+                            true => "user",
+                            false => "user co-host",
+                        };
                         view! {
-                            <div title=user.name.clone() class="user">
+                            <div title=user.name.clone() class=class>
                                 <img
                                     src=format!("/uploads/{}.webp", user_id)
                                     alt=format!(
@@ -77,6 +89,30 @@ pub fn UsersBar(
                                 } else {
                                     Either::Right(())
                                 }}
+                                {if let Some(ban_user) = ban_user {
+                                    Either::Left(
+                                        view! {
+                                            <button
+                                                class="ban"
+                                                title=format!("ban {}", user.name)
+                                                on:click={
+                                                    let user_id = Rc::clone(&user_id);
+                                                    move |_| {
+                                                        log!("banning user {}", user_id);
+                                                        ban_user.run((*user_id).clone());
+                                                    }
+                                                }
+                                            >
+                                                <svg
+                                                    viewBox=icondata::BsSlashCircle.view_box
+                                                    inner_html=icondata::BsSlashCircle.data
+                                                ></svg>
+                                            </button>
+                                        },
+                                    )
+                                } else {
+                                    Either::Right(())
+                                }}
 
                             </div>
                         }
@@ -85,5 +121,98 @@ pub fn UsersBar(
 
             </div>
         </div>
+        {banned
+            .map(|banned| {
+                view! {
+                    <Show when=move || banned.with(|banned| !banned.is_empty())>
+                        <details class="banned-users">
+                            <summary>
+                                {move || format!("Banned users ({})", banned.with(Vec::len))}
+                            </summary>
+                            <For
+                                each=move || banned.get()
+                                key=|user| user.id.clone()
+                                children=move |user| {
+                                    view! {
+                                        <div class="banned-user">
+                                            <span>{user.name}</span>
+                                            <button on:click=move |_| {
+                                                if let Some(unban_user) = unban_user {
+                                                    unban_user.run(user.id.clone());
+                                                }
+                                            }>"Unban"</button>
+                                        </div>
+                                    }
+                                }
+                            />
+                        </details>
+                    </Show>
+                }
+            })}
+        {set_permissions
+            .map(|set_permissions| {
+                view! {
+                    <Show when=move || users.with(|users| users.as_ref().is_some_and(|u| !u.is_empty()))>
+                        <details class="co-hosts">
+                            <summary>
+                                {move || {
+                                    let co_hosts = users
+                                        .with(|users| {
+                                            users
+                                                .iter()
+                                                .flatten()
+                                                .filter(|user| !user.permissions.is_empty())
+                                                .count()
+                                        });
+                                    format!("Co-hosts ({})", co_hosts)
+                                }}
+                            </summary>
+                            <For
+                                each=move || users().unwrap_or_default()
+                                key=|user| (user.id.clone(), user.permissions.clone())
+                                children=move |user| {
+                                    let permissions = Rc::new(user.permissions);
+                                    let user_id = Rc::new(user.id);
+                                    view! {
+                                        <div class="co-host">
+                                            <span>{user.name}</span>
+                                            {Permission::ALL
+                                                .into_iter()
+                                                .map(|permission| {
+                                                    let id = format!(
+                                                        "permission-{}-{}",
+                                                        user_id,
+                                                        permission.as_str(),
+                                                    );
+                                                    let user_id = Rc::clone(&user_id);
+                                                    let permissions = Rc::clone(&permissions);
+                                                    view! {
+                                                        <div class="checkbox-with-label">
+                                                            <input
+                                                                type="checkbox"
+                                                                checked=permissions.contains(&permission)
+                                                                on:change=move |ev| {
+                                                                    let mut permissions = (*permissions).clone();
+                                                                    permissions.retain(|p| *p != permission);
+                                                                    if event_target_checked(&ev) {
+                                                                        permissions.push(permission);
+                                                                    }
+                                                                    set_permissions.run(((*user_id).clone(), permissions));
+                                                                }
+                                                                id=id.clone()
+                                                            />
+                                                            <label for=id>{permission.display_name()}</label>
+                                                        </div>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </div>
+                                    }
+                                }
+                            />
+                        </details>
+                    </Show>
+                }
+            })}
     }
 }
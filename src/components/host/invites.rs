@@ -0,0 +1,101 @@
+use crate::model::types::*;
+use leptos::prelude::*;
+
+/// the invite can still be used to join
+pub fn is_invite_usable(invite: &Invite) -> bool {
+    let expired = invite
+        .expires_at
+        .is_some_and(|expires_at| (expires_at as f64) < js_sys::Date::now());
+    let used_up = invite
+        .max_uses
+        .is_some_and(|max_uses| invite.uses >= max_uses);
+    !expired && !used_up
+}
+
+fn describe_invite(invite: &Invite) -> String {
+    let uses = match invite.max_uses {
+        Some(max_uses) => format!("{}/{} uses", invite.uses, max_uses),
+        None => format!("{} uses", invite.uses),
+    };
+    let expires = match invite.expires_at {
+        Some(expires_at) => {
+            let hours_left = (expires_at as f64 - js_sys::Date::now()) / (60.0 * 60.0 * 1000.0);
+            match hours_left > 0.0 {
+                true => format!("expires in {}h", hours_left.ceil()),
+                false => "expired".to_string(),
+            }
+        }
+        None => "never expires".to_string(),
+    };
+    format!("{}, {}", uses, expires)
+}
+
+/// Lets the host hand out invite links that expire, the newest usable one is shown in the share qr code
+#[component]
+pub fn Invites(
+    #[prop(into)] invites: Signal<Vec<Invite>>,
+    /// the hours and the uses, none for no limit
+    #[prop(into)]
+    create: Callback<(Option<u32>, Option<u32>)>,
+    #[prop(into)] delete: Callback<String>,
+) -> impl IntoView {
+    let (hours, set_hours) = signal(String::new());
+    let (uses, set_uses) = signal(String::new());
+
+    let on_create = move |_| {
+        let hours = hours.with_untracked(|h| h.parse::<u32>().ok());
+        let uses = uses.with_untracked(|u| u.parse::<u32>().ok());
+        create.run((hours, uses));
+    };
+
+    view! {
+        <details class="invites">
+            <summary>"Invite Links"</summary>
+            <div class="create-invite">
+                <input
+                    type="number"
+                    prop:value=hours
+                    on:input=move |ev| set_hours(event_target_value(&ev))
+                    placeholder="hours"
+                    title="hours until the link expires, empty for never"
+                    class="text-input"
+                    min=1
+                />
+                <input
+                    type="number"
+                    prop:value=uses
+                    on:input=move |ev| set_uses(event_target_value(&ev))
+                    placeholder="uses"
+                    title="how many people can join with the link, empty for no limit"
+                    class="text-input"
+                    min=1
+                />
+                <button class="create" on:click=on_create>
+                    "Create"
+                </button>
+            </div>
+            <For
+                each=move || invites.get().into_iter()
+                key=|invite| (invite.id.clone(), invite.uses)
+                children=move |invite| {
+                    let invite_id = invite.id.clone();
+                    view! {
+                        <div class="invite" class:unusable=!is_invite_usable(&invite)>
+                            <div class="description">{describe_invite(&invite)}</div>
+                            <button
+                                class="delete"
+                                title="delete"
+                                on:click=move |_| delete.run(invite_id.clone())
+                            >
+                                <svg
+                                    viewBox=icondata::IoClose.view_box
+                                    inner_html=icondata::IoClose.data
+                                ></svg>
+                            </button>
+                        </div>
+                    }
+                }
+            />
+        </details>
+    }
+}
@@ -0,0 +1,60 @@
+use crate::model::types::*;
+use leptos::{either::Either, prelude::*};
+
+/// the people in the waiting room of a jam with join approval, the host admits or rejects them
+#[component]
+pub fn JoinRequests(
+    #[prop(into)] join_requests: Signal<Vec<User>>,
+    #[prop(into)] admit: Callback<String>,
+    #[prop(into)] reject: Callback<String>,
+) -> impl IntoView {
+    move || {
+        if join_requests.with(Vec::is_empty) {
+            return Either::Right(());
+        }
+        Either::Left(view! {
+            <div class="join-requests">
+                <div class="header">"Waiting to join"</div>
+                <For
+                    each=move || join_requests.get().into_iter()
+                    key=|user| user.id.clone()
+                    children=move |user| {
+                        let user_id = user.id.clone();
+                        view! {
+                            <div class="join-request" title=user.name.clone()>
+                                <img
+                                    src=format!("/uploads/{}.webp", user.id)
+                                    alt=format!("This is the profile picture of {}", user.name)
+                                />
+                                <div class="name">{user.name.clone()}</div>
+                                <button
+                                    class="admit"
+                                    title="admit"
+                                    on:click={
+                                        let user_id = user_id.clone();
+                                        move |_| admit.run(user_id.clone())
+                                    }
+                                >
+                                    <svg
+                                        viewBox=icondata::BsCheckLg.view_box
+                                        inner_html=icondata::BsCheckLg.data
+                                    ></svg>
+                                </button>
+                                <button
+                                    class="reject"
+                                    title="reject"
+                                    on:click=move |_| reject.run(user_id.clone())
+                                >
+                                    <svg
+                                        viewBox=icondata::IoClose.view_box
+                                        inner_html=icondata::IoClose.data
+                                    ></svg>
+                                </button>
+                            </div>
+                        }
+                    }
+                />
+            </div>
+        })
+    }
+}
@@ -0,0 +1,111 @@
+use crate::components::general;
+use crate::model::types::Song;
+use leptos::{either::*, html, prelude::*};
+
+/// the position is sent to the server when it moved this much, `timeupdate` fires about 4 times a second
+/// and every position is a write to the database and an update to the whole jam
+const POSITION_STEP: f32 = 0.01;
+
+/// Plays songs from the server's local library with an audio element,
+/// the server tells the host which song should be playing
+#[component]
+pub fn LocalPlayer(
+    #[prop(into)] current_song: ReadSignal<Option<Song>>,
+    #[prop(into)] set_song_position: Callback<f32>,
+) -> impl IntoView {
+    let audio_ref = NodeRef::<html::Audio>::new();
+    let (playing, set_playing) = signal(false);
+    let (position_percentage, set_position_percentage) = signal(0.0);
+    let sent_percentage = StoredValue::new(0.0_f32);
+
+    let src = move || {
+        current_song
+            .with(|s| {
+                s.as_ref()
+                    .map(|s| format!("/library/{}/audio", s.spotify_id))
+            })
+            .unwrap_or_default()
+    };
+
+    let on_time_update = move |_| {
+        if let Some(audio) = audio_ref.get_untracked() {
+            let duration = audio.duration();
+            if duration.is_finite() && duration > 0.0 {
+                let percentage = (audio.current_time() / duration) as f32;
+                set_position_percentage(percentage);
+                if (percentage - sent_percentage.get_value()).abs() >= POSITION_STEP {
+                    sent_percentage.set_value(percentage);
+                    set_song_position.run(percentage);
+                }
+            }
+        }
+    };
+
+    let on_ended = move |_| {
+        set_position_percentage(1.0);
+        sent_percentage.set_value(1.0);
+        set_song_position.run(1.0);
+    };
+
+    let toggle_play = move || {
+        if let Some(audio) = audio_ref.get_untracked() {
+            if audio.paused() {
+                let _ = audio.play();
+            } else {
+                let _ = audio.pause();
+            }
+        }
+    };
+
+    view! {
+        <audio
+            node_ref=audio_ref
+            src=src
+            autoplay=true
+            on:timeupdate=on_time_update
+            on:ended=on_ended
+            on:play=move |_| set_playing(true)
+            on:pause=move |_| set_playing(false)
+        ></audio>
+        <general::Player current_song position=position_percentage>
+            <button
+                on:click=move |_| {
+                    toggle_play();
+                }
+
+                class="play-pause"
+                title=move || match playing() {
+                    true => "pause",
+                    false => "play",
+                }
+            >
+
+                {move || match playing() {
+                    true => {
+                        Either::Left(
+                            view! {
+                                <svg
+                                    viewBox=icondata::FaPauseSolid.view_box
+                                    inner_html=icondata::FaPauseSolid.data
+                                    class="pause"
+                                ></svg>
+                            },
+                        )
+                    }
+                    false => {
+                        Either::Right(
+                            view! {
+                                <svg
+                                    viewBox=icondata::BsPlayFill.view_box
+                                    inner_html=icondata::BsPlayFill.data
+                                    class="play"
+                                ></svg>
+                            },
+                        )
+                    }
+                }}
+
+            </button>
+        </general::Player>
+    }
+}
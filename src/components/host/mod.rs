@@ -1,2 +1,12 @@
 mod player;
-pub use player::*;
\ No newline at end of file
+pub use player::*;
+mod local_player;
+pub use local_player::*;
+mod settings;
+pub use settings::*;
+mod pending_songs;
+pub use pending_songs::*;
+mod join_requests;
+pub use join_requests::*;
+mod invites;
+pub use invites::*;
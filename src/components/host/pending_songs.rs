@@ -0,0 +1,68 @@
+use crate::components::general::millis_to_min_sec;
+use crate::model::types::*;
+use leptos::{either::Either, prelude::*};
+
+/// the songs the users added to a moderated jam, waiting for the host to approve or reject them
+#[component]
+pub fn PendingSongs(
+    #[prop(into)] pending: Signal<Option<Vec<Song>>>,
+    #[prop(into)] approve: Callback<String>,
+    #[prop(into)] reject: Callback<String>,
+) -> impl IntoView {
+    move || {
+        if pending.with(|p| p.as_ref().map(Vec::is_empty).unwrap_or(true)) {
+            return Either::Right(());
+        }
+        Either::Left(view! {
+            <div class="pending-songs">
+                <div class="header">"Waiting for approval"</div>
+                <For
+                    each=move || pending().unwrap_or_default().into_iter()
+                    key=|song| song.id.clone()
+                    children=move |song| {
+                        let song_id = song.id.clone().unwrap_or_default();
+                        view! {
+                            <div class="pending-song" title=song.name.clone()>
+                                <img
+                                    src=song.image_url.clone()
+                                    alt=format!("This is the album cover of {}", song.name)
+                                />
+                                <div class="info-text">
+                                    <div class="title">{song.name.clone()}</div>
+                                    <div class="small-info">
+                                        {song.artists.join(", ")} <span class="bullet-point">
+                                            "•"
+                                        </span> {millis_to_min_sec(song.duration)}
+                                    </div>
+                                </div>
+                                <button
+                                    class="approve"
+                                    title="approve"
+                                    on:click={
+                                        let song_id = song_id.clone();
+                                        move |_| approve.run(song_id.clone())
+                                    }
+                                >
+                                    <svg
+                                        viewBox=icondata::BsCheckLg.view_box
+                                        inner_html=icondata::BsCheckLg.data
+                                    ></svg>
+                                </button>
+                                <button
+                                    class="reject"
+                                    title="reject"
+                                    on:click=move |_| reject.run(song_id.clone())
+                                >
+                                    <svg
+                                        viewBox=icondata::IoClose.view_box
+                                        inner_html=icondata::IoClose.data
+                                    ></svg>
+                                </button>
+                            </div>
+                        }
+                    }
+                />
+            </div>
+        })
+    }
+}
@@ -15,7 +15,9 @@ use crate::model;
 
 #[component]
 pub fn Player(
-    #[prop(into)] host_id: Signal<Option<String>>,
+    /// the host session, its token is sent as a cookie
+    #[prop(into)]
+    session: Signal<Option<model::Session>>,
     #[prop(into)] set_song_position: Callback<f32>,
 ) -> impl IntoView {
     let (error_message, set_error_message) = signal(String::new());
@@ -43,19 +45,21 @@ pub fn Player(
                 votes: 0,
                 have_you_voted: None,
             },
+            explicit: false,
+            pinned: false,
         }));
     };
 
     let switch_device = move |device_id: String| {
         spawn_local(async move {
-            if let Some(host_id) = host_id.try_get_untracked() {
-                if let Some(host_id) = host_id {
-                    if let Err(e) = change_playback_device(device_id, host_id).await {
+            if let Some(session) = session.try_get_untracked() {
+                if session.is_some() {
+                    if let Err(e) = change_playback_device(device_id).await {
                         set_error_message(format!("Error switching device: {:?}", e));
                     }
                 } else {
                     use leptos_router::NavigateOptions;
-                    set_error_message("host id is empty, act".into());
+                    set_error_message("there is no host session".into());
                     use_navigate()("/", NavigateOptions::default());
                 }
             }
@@ -76,7 +80,6 @@ pub fn Player(
     };
 
     let get_token = {
-        let host_id = host_id.clone();
         let set_error_message = set_error_message.clone();
         async move || {
             use gloo::timers::future::sleep;
@@ -85,13 +88,15 @@ pub fn Player(
             let mut res = None;
             while token.is_err() {
                 token = {
-                    if let Some(host_id) = host_id.get_untracked() {
-                        get_access_token(host_id).await
+                    if session.with_untracked(Option::is_some) {
+                        get_access_token().await
                     } else {
                         use leptos_router::NavigateOptions;
                         use_navigate()("/", NavigateOptions::default());
-                        error!("host id is empty");
-                        Err(ServerFnError::Request("host id is empty".to_string()))
+                        error!("there is no host session");
+                        Err(ServerFnError::Request(
+                            "there is no host session".to_string(),
+                        ))
                     }
                 };
                 match &token {
@@ -118,7 +123,7 @@ pub fn Player(
 
     Effect::new(move || {
         spawn_local(async move {
-            if sp::player_ready() && !host_id.with(Option::is_some) {
+            if sp::player_ready() && !session.with(Option::is_some) {
                 return;
             }
             let token = get_token().await;
@@ -152,7 +157,7 @@ pub fn Player(
         });
     });
 
-    let is_loaded = Memo::new(move |_| player_is_connected() && host_id.with(Option::is_some));
+    let is_loaded = Memo::new(move |_| player_is_connected() && session.with(Option::is_some));
 
     Effect::new(move |_| {
         log!("player is connected:{}", is_loaded());
@@ -257,19 +262,25 @@ pub fn Player(
 }
 
 #[server]
-async fn change_playback_device(
-    device_id: String,
-    host_id: String,
-) -> Result<(), ServerFnError<String>> {
+async fn change_playback_device(device_id: String) -> Result<(), ServerFnError<String>> {
     use crate::model::*;
     let app_state = expect_context::<AppState>();
+    let headers: http::HeaderMap = leptos_axum::extract()
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("error reading the headers: {}", e)))?;
     let mut transaction =
         app_state.db.pool.begin().await.map_err(|e| {
             ServerFnError::ServerError(format!("error starting transaction: {}", e))
         })?;
-    let credentials = app_state.spotify_credentials;
 
-    let jam_id = match model::check_id_type(&host_id, &mut transaction).await {
+    let jam_id = match check_request_session(
+        &headers,
+        None,
+        &app_state.secret_key,
+        &mut transaction,
+    )
+    .await
+    {
         Ok(id) => match id.id {
             IdType::Host(_) => id.jam_id,
             _ => {
@@ -282,8 +293,10 @@ async fn change_playback_device(
         Err(e) => return Err(ServerFnError::ServerError(e.to_string())),
     };
 
-    if let Err(e) =
-        model::switch_playback_to_device(&device_id, &jam_id, &mut transaction, credentials).await
+    if let Err(e) = app_state
+        .music_provider
+        .switch_playback_to_device(&device_id, &jam_id, &mut transaction)
+        .await
     {
         return Err(ServerFnError::ServerError(e.to_string()));
     };
@@ -297,17 +310,19 @@ async fn change_playback_device(
 }
 
 #[server]
-async fn get_access_token(host_id: String) -> Result<rspotify::Token, ServerFnError<String>> {
+async fn get_access_token() -> Result<rspotify::Token, ServerFnError<String>> {
     use crate::model::*;
 
     let app_state = expect_context::<AppState>();
+    let headers: http::HeaderMap = leptos_axum::extract()
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("error reading the headers: {}", e)))?;
     let mut transaction =
         app_state.db.pool.begin().await.map_err(|e| {
             ServerFnError::ServerError(format!("error starting transaction: {}", e))
         })?;
+    let id = check_request_session(&headers, None, &app_state.secret_key, &mut transaction).await;
     let credentials = app_state.spotify_credentials;
-
-    let id = check_id_type(&host_id, &mut transaction).await;
     let id = match id {
         Ok(id) => id,
         Err(e) => return Err(ServerFnError::ServerError(e.to_string())),
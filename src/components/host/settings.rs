@@ -0,0 +1,262 @@
+use crate::components::general::Modal;
+use crate::model::types::*;
+use leptos::prelude::*;
+
+/// sends the settings with the edit applied, the server broadcasts them back so the form stays in sync
+fn change(
+    settings: Signal<JamSettings>,
+    update_settings: Callback<JamSettings>,
+    edit: impl FnOnce(&mut JamSettings),
+) {
+    let mut settings = settings.get_untracked();
+    edit(&mut settings);
+    update_settings.run(settings);
+}
+
+/// the lists are edited as comma separated text
+fn parse_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Lets the host change the settings of the jam, every change is applied right away
+#[component]
+pub fn Settings(
+    #[prop(into)] visible: Signal<bool>,
+    #[prop(into)] settings: Signal<JamSettings>,
+    #[prop(into)] update_settings: Callback<JamSettings>,
+    #[prop(into)] close: Callback<()>,
+    /// the playlist can only be saved to a spotify account
+    #[prop(into, optional)]
+    spotify: Signal<bool>,
+) -> impl IntoView {
+    view! {
+        <Modal visible>
+            <div class="settings">
+                <div class="checkbox-with-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || settings.with(|s| s.voting)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            change(settings, update_settings, |s| s.voting = checked);
+                        }
+                        id="settings-voting"
+                    />
+                    <label for="settings-voting">"Let the users vote on songs"</label>
+                </div>
+                <div class="checkbox-with-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || settings.with(|s| s.downvotes)
+                        prop:disabled=move || settings.with(|s| !s.voting)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            change(settings, update_settings, |s| s.downvotes = checked);
+                        }
+                        id="settings-downvotes"
+                    />
+                    <label for="settings-downvotes">"Let the users downvote songs"</label>
+                </div>
+                <div class="checkbox-with-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || settings.with(|s| s.allow_explicit)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            change(settings, update_settings, |s| s.allow_explicit = checked);
+                        }
+                        id="settings-explicit"
+                    />
+                    <label for="settings-explicit">"Allow explicit songs"</label>
+                </div>
+                <div class="checkbox-with-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || settings.with(|s| s.fairness)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            change(settings, update_settings, |s| s.fairness = checked);
+                        }
+                        id="settings-fairness"
+                    />
+                    <label for="settings-fairness">
+                        "Let everyone have a turn before the same person is played again"
+                    </label>
+                </div>
+                <div class="checkbox-with-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || settings.with(|s| s.join_approval)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            change(settings, update_settings, |s| s.join_approval = checked);
+                        }
+                        id="settings-join-approval"
+                    />
+                    <label for="settings-join-approval">"Approve new users before they join"</label>
+                </div>
+                <div class="checkbox-with-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || settings.with(|s| s.moderated)
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            change(settings, update_settings, |s| s.moderated = checked);
+                        }
+                        id="settings-moderated"
+                    />
+                    <label for="settings-moderated">"Approve songs before they are queued"</label>
+                </div>
+                <div class="input-with-label">
+                    <label for="settings-passcode">"Passcode (empty for none)"</label>
+                    <input
+                        type="text"
+                        maxlength=12
+                        prop:value=move || settings.with(|s| s.passcode.clone().unwrap_or_default())
+                        on:change=move |ev| {
+                            let passcode = Some(event_target_value(&ev).trim().to_string())
+                                .filter(|p| !p.is_empty());
+                            change(settings, update_settings, |s| s.passcode = passcode);
+                        }
+                        placeholder="ex. party42"
+                        class="text-input"
+                        id="settings-passcode"
+                    />
+                </div>
+                <div class="input-with-label">
+                    <label for="settings-queue-strategy">"Queue Order"</label>
+                    <select
+                        on:change=move |ev| {
+                            if let Ok(strategy) = event_target_value(&ev).parse() {
+                                change(settings, update_settings, |s| s.queue_strategy = strategy);
+                            }
+                        }
+                        class="text-input"
+                        id="settings-queue-strategy"
+                    >
+                        {QueueStrategy::ALL
+                            .into_iter()
+                            .map(|strategy| {
+                                view! {
+                                    <option
+                                        value=strategy.as_str()
+                                        selected=move || {
+                                            settings.with(|s| s.queue_strategy == strategy)
+                                        }
+                                    >
+                                        {strategy.display_name()}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </div>
+                <div class="input-with-label">
+                    <label for="settings-max-duration">"Max Song Length (minutes, empty for no limit)"</label>
+                    <input
+                        type="number"
+                        prop:value=move || {
+                            settings
+                                .with(|s| {
+                                    s.max_song_duration
+                                        .map(|d| (d / 60_000).to_string())
+                                        .unwrap_or_default()
+                                })
+                        }
+                        on:change=move |ev| {
+                            let minutes = event_target_value(&ev).parse::<u32>().ok();
+                            change(
+                                settings,
+                                update_settings,
+                                |s| {
+                                    s.max_song_duration = minutes
+                                        .filter(|m| *m > 0)
+                                        .map(|m| m * 60_000);
+                                },
+                            );
+                        }
+                        placeholder="ex. 6"
+                        class="text-input"
+                        id="settings-max-duration"
+                        min=1
+                    />
+                </div>
+                <div class="input-with-label">
+                    <label for="settings-blocked-artists">"Blocked Artists (comma separated)"</label>
+                    <input
+                        type="text"
+                        prop:value=move || settings.with(|s| s.blocked_artists.join(", "))
+                        on:change=move |ev| {
+                            let artists = parse_list(&event_target_value(&ev));
+                            change(settings, update_settings, |s| s.blocked_artists = artists);
+                        }
+                        placeholder="ex. Rick Astley"
+                        class="text-input"
+                        id="settings-blocked-artists"
+                    />
+                </div>
+                <div class="input-with-label">
+                    <label for="settings-allowed-genres">
+                        "Allowed Genres (comma separated, empty for all)"
+                    </label>
+                    <input
+                        type="text"
+                        prop:value=move || settings.with(|s| s.allowed_genres.join(", "))
+                        on:change=move |ev| {
+                            let genres = parse_list(&event_target_value(&ev));
+                            change(settings, update_settings, |s| s.allowed_genres = genres);
+                        }
+                        placeholder="ex. pop, rock"
+                        class="text-input"
+                        id="settings-allowed-genres"
+                    />
+                </div>
+                <div class="input-with-label">
+                    <label for="settings-skip">"Votes Needed To Skip (%)"</label>
+                    <input
+                        type="number"
+                        prop:value=move || {
+                            settings.with(|s| (s.skip_threshold * 100.0).round() as u8)
+                        }
+                        on:change=move |ev| {
+                            let percentage = event_target_value(&ev)
+                                .parse::<u8>()
+                                .unwrap_or(50)
+                                .min(100);
+                            change(
+                                settings,
+                                update_settings,
+                                |s| s.skip_threshold = percentage as f32 / 100.0,
+                            );
+                        }
+                        placeholder="ex. 50"
+                        class="text-input"
+                        id="settings-skip"
+                        min=1
+                        max=100
+                    />
+                </div>
+                <Show when=move || spotify.get()>
+                    <div class="checkbox-with-label">
+                        <input
+                            type="checkbox"
+                            prop:checked=move || settings.with(|s| s.save_playlist)
+                            on:change=move |ev| {
+                                let checked = event_target_checked(&ev);
+                                change(settings, update_settings, |s| s.save_playlist = checked);
+                            }
+                            id="settings-save-playlist"
+                        />
+                        <label for="settings-save-playlist">
+                            "Save the played songs as a Spotify playlist when the jam ends"
+                        </label>
+                    </div>
+                </Show>
+            </div>
+            <button on:click=move |_| close.run(())>"Close"</button>
+        </Modal>
+    }
+}
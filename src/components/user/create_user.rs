@@ -1,4 +1,5 @@
 use crate::components::general::{set_bg_img, Modal};
+use crate::model::types::{JoinCredentials, Session};
 use gloo::{
     events::EventListener,
     storage::{LocalStorage, Storage},
@@ -15,33 +16,64 @@ async fn create_user(
     jam_id: String,
     name: String,
     pfp_url: String,
-) -> Result<String, ServerFnError> {
-    use crate::model::notify;
+    credentials: JoinCredentials,
+) -> Result<Session, ServerFnError> {
+    use crate::model::{create_session, get_cookie, notify, session_cookie};
     use crate::model::{functions::create_user as create_user_fn, types::AppState};
+    use http::{header, HeaderMap, HeaderValue};
+    use leptos_axum::{extract, ResponseOptions};
+
+    // the device is remembered with a cookie, so someone who was banned can't join again with a new name
+    let headers: HeaderMap = extract().await?;
+    let device_id = get_cookie(&headers, "device_id")
+        .filter(|id| id.len() == 24 && id.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(str::to_string);
+    let device_id = match device_id {
+        Some(device_id) => device_id,
+        None => {
+            let device_id = cuid2::create_id();
+            let cookie = format!(
+                "device_id={}; Path=/; Max-Age=31536000; HttpOnly; SameSite=Lax",
+                device_id
+            );
+            if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+                expect_context::<ResponseOptions>().append_header(header::SET_COOKIE, cookie);
+            }
+            device_id
+        }
+    };
 
     let app_state = expect_context::<AppState>();
     let mut transaction = app_state.db.pool.begin().await?;
-    let res = match create_user_fn(
+    let (user_id, changed) = match create_user_fn(
         &jam_id,
         &pfp_url,
         &name,
-        &mut *transaction,
+        &device_id,
+        &credentials,
+        &app_state.secret_key,
+        &mut transaction,
         &app_state.leptos_options.site_root,
     )
     .await
     {
-        Ok(user_id) => {
-            notify(user_id.1, vec![], &jam_id, &mut transaction).await?;
-            Ok(user_id.0)
-        }
-        Err(e) => Err(ServerFnError::ServerError(e.into())),
+        Ok(created) => created,
+        // nothing of the failed join is kept, dropping the transaction rolls it back
+        Err(e) => return Err(ServerFnError::ServerError(e.into())),
     };
+    notify(changed, &jam_id, &mut transaction).await?;
     transaction.commit().await?;
-    res
+
+    let session = create_session(&user_id);
+    let cookie = session_cookie(&session, Some(&jam_id), &app_state.secret_key);
+    if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+        expect_context::<ResponseOptions>().append_header(header::SET_COOKIE, cookie);
+    }
+    Ok(session)
 }
 
 #[component]
-pub fn CreateUser(jam_id: String) -> impl IntoView {
+pub fn CreateUser(jam_id: String, credentials: JoinCredentials) -> impl IntoView {
     let jam_id = Rc::new(jam_id);
 
     let (error_message, set_error_message) = signal(String::new());
@@ -217,15 +249,19 @@ pub fn CreateUser(jam_id: String) -> impl IntoView {
         let jam_id = Rc::clone(&jam_id);
         Effect::new(move |_| {
             let jam_id: &str = &jam_id;
-            let user_id: String = LocalStorage::get(jam_id).unwrap_or_default();
-            if user_id.is_empty() {
-                camera();
-            } else if user_id == "kicked" {
+            // the jam is either the session of the user, or "kicked" if they were kicked
+            let session = LocalStorage::get::<Session>(jam_id)
+                .ok()
+                .filter(|session| !session.has_expired(js_sys::Date::now() as i64));
+            let kicked = LocalStorage::get::<String>(jam_id).is_ok_and(|s| s == "kicked");
+            if kicked {
                 let navigate = use_navigate();
                 navigate("/", NavigateOptions::default());
-            } else {
+            } else if session.is_some() {
                 let navigate = use_navigate();
                 navigate(&format!("/jam/{}", jam_id), NavigateOptions::default());
+            } else {
+                camera();
             }
         });
     }
@@ -245,11 +281,12 @@ pub fn CreateUser(jam_id: String) -> impl IntoView {
             let name = name.get();
             let pfp_url = image_url.get();
             let jam_id = (*jam_id).to_string();
+            let credentials = credentials.clone();
             async move {
                 if name.is_empty() {
                     return Err(ServerFnError::ServerError("Name is empty".into()));
                 }
-                let res=create_user(jam_id, name, pfp_url).await;
+                let res=create_user(jam_id, name, pfp_url, credentials).await;
                 if res.is_ok() {
                     set_camera_request_state(CameraRequestState::Asking);
                     set_image_url(String::new());
@@ -265,11 +302,11 @@ pub fn CreateUser(jam_id: String) -> impl IntoView {
     Effect::new(move |_| {
         if let Some(res) = create_user.value().get() {
             match res {
-                Ok(id) => {
+                Ok(session) => {
                     let jam_id: &str = &jam_id;
-                    if let Err(e) = LocalStorage::set(jam_id, id) {
+                    if let Err(e) = LocalStorage::set(jam_id, session) {
                         set_error_message(format!(
-                            "Error setting user id in local storage: {:?}",
+                            "Error setting user session in local storage: {:?}",
                             e
                         ));
                     }
@@ -11,10 +11,22 @@ async fn main() {
         eprintln!("didn't find env file")
     };
 
-    let spotify_id = std::env::var("SPOTIFY_ID").expect("SPOTIFY_ID must be set");
-    let spotify_secret = std::env::var("SPOTIFY_SECRET").expect("SPOTIFY_SECRET must be set");
+    let music_provider = std::env::var("MUSIC_PROVIDER").unwrap_or("spotify".to_string());
+    let (spotify_id, spotify_secret) = if music_provider == "spotify" {
+        (
+            std::env::var("SPOTIFY_ID").expect("SPOTIFY_ID must be set"),
+            std::env::var("SPOTIFY_SECRET").expect("SPOTIFY_SECRET must be set"),
+        )
+    } else {
+        (
+            std::env::var("SPOTIFY_ID").unwrap_or_default(),
+            std::env::var("SPOTIFY_SECRET").unwrap_or_default(),
+        )
+    };
+    let music_library = std::env::var("MUSIC_LIBRARY").ok();
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let site_url = std::env::var("SITE_URL").expect("SITE_URL must be set");
+    let secret_key = std::env::var("SECRET_KEY").expect("SECRET_KEY must be set");
 
     println!("Loading configuration...");
     let conf = get_configuration(None).unwrap();
@@ -33,6 +45,9 @@ async fn main() {
         spotify_secret,
         db_url,
         site_url,
+        secret_key,
+        music_provider,
+        music_library,
     )
     .await
     .unwrap();
@@ -0,0 +1,101 @@
+use crate::model::types::*;
+
+/// the songs of a jam, what was played and what is still in the queue
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SetList {
+    /// the most recent first
+    pub played: Vec<PlayedSong>,
+    pub queued: Vec<Song>,
+}
+
+pub async fn get_set_list<'e>(
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<SetList, Error> {
+    let played = super::get_history(jam_id, None, &mut **transaction).await?;
+    let id = Id::new(IdType::General, jam_id.to_string());
+    let queued = super::get_songs(transaction, &id).await?;
+    Ok(SetList { played, queued })
+}
+
+impl SetList {
+    /// `uri` turns the provider id of a song into something a player can open, like a spotify uri
+    pub fn to_m3u(&self, uri: impl Fn(&Song) -> String) -> String {
+        let mut m3u = String::from("#EXTM3U\n");
+        for song in self.songs_in_order() {
+            m3u.push_str(&format!(
+                "#EXTINF:{},{} - {}\n{}\n",
+                song.duration / 1000,
+                song.artists.join(", "),
+                song.name,
+                uri(song)
+            ));
+        }
+        m3u
+    }
+
+    /// `user_names` is used for the names of the users who added the queued songs
+    pub fn to_csv(&self, user_names: &std::collections::HashMap<String, String>) -> String {
+        let mut csv = String::from(
+            "status,played_at,name,artists,album,duration_ms,votes,added_by,spotify_id\n",
+        );
+        let played = self.played.iter().rev().map(|played| {
+            (
+                "played",
+                Some(played.played_at),
+                &played.song,
+                played.user_name.clone(),
+            )
+        });
+        let queued = self.queued.iter().map(|song| {
+            (
+                "queued",
+                None,
+                song,
+                song.user_id
+                    .as_ref()
+                    .and_then(|user_id| user_names.get(user_id))
+                    .cloned(),
+            )
+        });
+
+        for (status, played_at, song, added_by) in played.chain(queued) {
+            let row = [
+                status.to_string(),
+                played_at.map(|p| p.to_string()).unwrap_or_default(),
+                song.name.clone(),
+                song.artists.join("; "),
+                song.album.clone(),
+                song.duration.to_string(),
+                song.votes.votes.to_string(),
+                added_by.unwrap_or_default(),
+                song.spotify_id.clone(),
+            ];
+            csv.push_str(
+                &row.iter()
+                    .map(|field| escape_csv(field))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// first the played songs in the order they were played, then the queue
+    fn songs_in_order(&self) -> impl Iterator<Item = &Song> {
+        self.played
+            .iter()
+            .rev()
+            .map(|played| &played.song)
+            .chain(self.queued.iter())
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
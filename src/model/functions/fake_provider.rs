@@ -0,0 +1,243 @@
+use super::MusicProvider;
+use crate::model::types::*;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A music provider that lives completely in memory,
+/// it has a fixed library and remembers what every jam is "playing"
+#[derive(Debug, Clone)]
+pub struct FakeProvider {
+    library: Arc<Vec<Song>>,
+    players: Arc<Mutex<HashMap<String, FakePlayer>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct FakePlayer {
+    current_song: Option<Song>,
+    device_id: Option<String>,
+    /// how often it was asked what it plays
+    polls: usize,
+    /// the spotify ids of the songs it was told to play, in order
+    played: Vec<String>,
+}
+
+impl FakeProvider {
+    /// the songs in the library should have an unique spotify_id, that is what they are looked up by
+    pub fn new(library: Vec<Song>) -> Self {
+        Self {
+            library: Arc::new(library),
+            players: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn library(&self) -> &[Song] {
+        &self.library
+    }
+
+    /// the device the jam's playback was switched to, if it was switched at all
+    pub fn device(&self, jam_id: &str) -> Option<String> {
+        self.players
+            .lock()
+            .unwrap()
+            .get(jam_id)
+            .and_then(|p| p.device_id.clone())
+    }
+
+    /// how often the jam's player was asked what it plays, the background work of the jam does that
+    pub fn polls(&self, jam_id: &str) -> usize {
+        self.players
+            .lock()
+            .unwrap()
+            .get(jam_id)
+            .map(|p| p.polls)
+            .unwrap_or(0)
+    }
+
+    /// the songs the jam's player was told to play, in order
+    pub fn played(&self, jam_id: &str) -> Vec<String> {
+        self.players
+            .lock()
+            .unwrap()
+            .get(jam_id)
+            .map(|p| p.played.clone())
+            .unwrap_or_default()
+    }
+
+    fn find(&self, song_id: &str) -> Result<Song, Error> {
+        self.library
+            .iter()
+            .find(|s| s.spotify_id == song_id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::DoesNotExist(format!("song with id {} is not in the library", song_id))
+            })
+    }
+}
+
+/// id, name, artist, album, duration in milliseconds
+const DEFAULT_LIBRARY: [(&str, &str, &str, &str, u32); 8] = [
+    (
+        "fake-1",
+        "Never Gonna Give You Up",
+        "Rick Astley",
+        "Whenever You Need Somebody",
+        213_000,
+    ),
+    (
+        "fake-2",
+        "Take On Me",
+        "a-ha",
+        "Hunting High and Low",
+        225_000,
+    ),
+    ("fake-3", "Africa", "Toto", "Toto IV", 295_000),
+    ("fake-4", "Dancing Queen", "ABBA", "Arrival", 231_000),
+    ("fake-5", "September", "Earth, Wind & Fire", "I Am", 215_000),
+    (
+        "fake-6",
+        "Mr. Brightside",
+        "The Killers",
+        "Hot Fuss",
+        222_000,
+    ),
+    (
+        "fake-7",
+        "Billie Jean",
+        "Michael Jackson",
+        "Thriller",
+        294_000,
+    ),
+    (
+        "fake-8",
+        "Wonderwall",
+        "Oasis",
+        "(What's the Story) Morning Glory?",
+        258_000,
+    ),
+];
+
+impl Default for FakeProvider {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_LIBRARY
+                .iter()
+                .map(|(id, name, artist, album, duration)| Song {
+                    id: None,
+                    spotify_id: id.to_string(),
+                    user_id: None,
+                    name: name.to_string(),
+                    artists: vec![artist.to_string()],
+                    album: album.to_string(),
+                    duration: *duration,
+                    image_url: String::new(),
+                    votes: Vote {
+                        votes: 0,
+                        have_you_voted: None,
+                    },
+                    explicit: false,
+                    pinned: false,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl MusicProvider for FakeProvider {
+    async fn search<'e>(
+        &self,
+        query: &str,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Vec<Song>, Error> {
+        let query = query.to_lowercase();
+        Ok(self
+            .library
+            .iter()
+            .filter(|s| {
+                s.name.to_lowercase().contains(&query)
+                    || s.album.to_lowercase().contains(&query)
+                    || s.artists.iter().any(|a| a.to_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_song<'e>(
+        &self,
+        song_id: &str,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        self.find(song_id)
+    }
+
+    async fn play_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        let song = self.find(song_id)?;
+        let mut players = self.players.lock().unwrap();
+        let player = players.entry(jam_id.to_string()).or_default();
+        player.played.push(song.spotify_id.clone());
+        player.current_song = Some(song);
+        Ok(())
+    }
+
+    async fn get_current_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        let mut players = self.players.lock().unwrap();
+        let player = players.entry(jam_id.to_string()).or_default();
+        player.polls += 1;
+        Ok(player.current_song.clone())
+    }
+
+    async fn get_next_song_from_player<'e>(
+        &self,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        Ok(None)
+    }
+
+    async fn get_song_recommendation<'e>(
+        &self,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        self.library
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .ok_or_else(|| Error::DoesNotExist("the fake library is empty".to_string()))
+    }
+
+    /// the fake library has no genres
+    async fn get_genres<'e>(
+        &self,
+        _song_ids: &[String],
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<HashMap<String, Vec<String>>, Error> {
+        Ok(HashMap::new())
+    }
+
+    async fn switch_playback_to_device<'e>(
+        &self,
+        device_id: &str,
+        jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        self.players
+            .lock()
+            .unwrap()
+            .entry(jam_id.to_string())
+            .or_default()
+            .device_id = Some(device_id.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::model::types::*;
+
+/// how many songs the history on the pages shows, the exports get all of them
+pub const RECENTLY_PLAYED: i64 = 50;
+
+/// saves the song as played, the votes and the user who added it are taken from the song
+pub async fn add_to_history<'e>(
+    song: &Song,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO played_songs
+            (id, jam_id, user_id, user_name, spotify_id, name, album, duration, artists, image_url, votes, played_at, explicit)
+        VALUES
+            ($1, $2, $3, (SELECT name FROM users WHERE id = $3), $4, $5, $6, $7, $8, $9, $10, $11, $12);",
+        cuid2::create_id(),
+        jam_id,
+        song.user_id,
+        song.spotify_id,
+        song.name,
+        song.album,
+        song.duration as i32,
+        &song.artists,
+        song.image_url,
+        song.votes.votes as i32,
+        chrono::Utc::now().timestamp_millis(),
+        song.explicit
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(real_time::Changed::new().history())
+}
+
+/// the songs played in the jam, the most recent first, none as the limit gets all of them
+pub async fn get_history<'e>(
+    jam_id: &str,
+    limit: Option<i64>,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<PlayedSong>, sqlx::Error> {
+    let history = sqlx::query!(
+        "SELECT id, user_id, user_name, spotify_id, name, album, duration, artists, image_url, votes, played_at, explicit
+        FROM played_songs
+        WHERE jam_id = $1
+        ORDER BY played_at DESC
+        LIMIT $2;",
+        jam_id,
+        limit
+    )
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .map(|song| PlayedSong {
+        song: Song {
+            id: Some(song.id),
+            spotify_id: song.spotify_id,
+            user_id: song.user_id,
+            name: song.name,
+            artists: song.artists,
+            album: song.album,
+            duration: song.duration as u32,
+            image_url: song.image_url,
+            votes: Vote {
+                votes: song.votes as i64,
+                have_you_voted: None,
+            },
+            explicit: song.explicit,
+            pinned: false,
+        },
+        user_name: song.user_name,
+        played_at: song.played_at,
+    })
+    .collect();
+
+    Ok(history)
+}
@@ -0,0 +1,167 @@
+use super::{sign_token, verify_token};
+use crate::model::types::*;
+use real_time::Changed;
+
+/// the invite expires after the hours and can be used the amount of uses, none means no limit,
+/// the token is signed once here so the host can be sent the same link every time
+pub async fn create_invite<'e>(
+    jam_id: &str,
+    hours: Option<u32>,
+    uses: Option<u32>,
+    secret_key: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Changed, Error> {
+    if hours == Some(0) {
+        return Err(Error::InvalidRequest(
+            "An invite must be valid for at least an hour".into(),
+        ));
+    }
+    if uses == Some(0) {
+        return Err(Error::InvalidRequest(
+            "An invite must be usable at least once".into(),
+        ));
+    }
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let invite_id = cuid2::create_id();
+    let token = sign_token("invite", &invite_id, secret_key);
+
+    sqlx::query!(
+        "INSERT INTO invites (id, jam_id, token, expires_at, max_uses, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+        invite_id,
+        jam_id,
+        token,
+        hours.map(|h| now + h as i64 * 60 * 60 * 1000),
+        uses.map(|u| u as i32),
+        now
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(Changed::new().invites())
+}
+
+pub async fn delete_invite<'e>(
+    invite_id: &str,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Changed, Error> {
+    let res = sqlx::query!(
+        "DELETE FROM invites WHERE id = $1 AND jam_id = $2",
+        invite_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "invite with id: {} does not exist, could not delete",
+            invite_id
+        )));
+    }
+
+    Ok(Changed::new().invites())
+}
+
+/// the newest first, expired and used up invites are included so the host can see them
+pub async fn get_invites<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<Invite>, sqlx::Error> {
+    let invites = sqlx::query!(
+        "SELECT id, token, expires_at, max_uses, uses FROM invites WHERE jam_id = $1 ORDER BY created_at DESC",
+        jam_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(invites
+        .into_iter()
+        .map(|invite| Invite {
+            id: invite.id,
+            token: invite.token,
+            expires_at: invite.expires_at,
+            max_uses: invite.max_uses.map(|u| u as u32),
+            uses: invite.uses as u32,
+        })
+        .collect())
+}
+
+/// checks if someone is allowed to join the jam, a valid invite lets them in even without the passcode,
+/// returns the id of the invite if one was used, so it can be counted with `use_invite`
+pub async fn check_join_access<'e>(
+    jam_id: &str,
+    credentials: &JoinCredentials,
+    secret_key: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<Option<String>, Error> {
+    if let Some(token) = &credentials.invite {
+        let invite_id = verify_token(token, "invite", secret_key)
+            .ok_or_else(|| Error::Forbidden("the invite link is not valid".to_string()))?;
+
+        let invite = sqlx::query!(
+            "SELECT expires_at, max_uses, uses FROM invites WHERE id = $1 AND jam_id = $2",
+            invite_id,
+            jam_id.to_lowercase()
+        )
+        .fetch_optional(&mut **transaction)
+        .await?
+        .ok_or_else(|| Error::Forbidden("the invite link was deleted by the host".to_string()))?;
+
+        if invite
+            .expires_at
+            .is_some_and(|expires_at| expires_at < chrono::Utc::now().timestamp_millis())
+        {
+            return Err(Error::Forbidden("the invite link has expired".to_string()));
+        }
+        if invite
+            .max_uses
+            .is_some_and(|max_uses| invite.uses >= max_uses)
+        {
+            return Err(Error::Forbidden("the invite link was used up".to_string()));
+        }
+
+        return Ok(Some(invite_id));
+    }
+
+    let passcode = sqlx::query!(
+        "SELECT passcode FROM jams WHERE id = $1",
+        jam_id.to_lowercase()
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    .ok_or_else(|| {
+        Error::DoesNotExist(format!(
+            "jam with id {} does not exist, could not check access",
+            jam_id
+        ))
+    })?
+    .passcode;
+
+    match (passcode, &credentials.passcode) {
+        (None, _) => Ok(None),
+        (Some(passcode), Some(given)) if passcode == *given => Ok(None),
+        (Some(_), Some(_)) => Err(Error::Forbidden("the passcode is wrong".to_string())),
+        (Some(_), None) => Err(Error::Forbidden("this jam needs a passcode".to_string())),
+    }
+}
+
+/// counts a use of the invite, fails if it was used up since it was checked
+pub async fn use_invite<'e>(
+    invite_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Changed, Error> {
+    let res = sqlx::query!(
+        "UPDATE invites SET uses = uses + 1 WHERE id = $1 AND (max_uses IS NULL OR uses < max_uses)",
+        invite_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::Forbidden("the invite link was used up".to_string()));
+    }
+
+    Ok(Changed::new().invites())
+}
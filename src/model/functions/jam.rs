@@ -1,4 +1,4 @@
-use super::notify;
+use super::{notify, MusicProvider};
 use crate::model::types::*;
 use real_time::Changed;
 
@@ -22,15 +22,118 @@ pub async fn get_jam<'e>(jam_id: &str, executor: impl sqlx::PgExecutor<'e>) -> R
         id: jam.id,
         name: jam.name,
         max_song_count: jam.max_song_count as u8,
+        settings: JamSettings {
+            voting: jam.voting,
+            downvotes: jam.downvotes,
+            allow_explicit: jam.allow_explicit,
+            max_song_duration: jam.max_song_duration.map(|d| d as u32),
+            blocked_artists: jam.blocked_artists,
+            allowed_genres: jam.allowed_genres,
+            queue_strategy: jam.queue_strategy.parse()?,
+            fairness: jam.fairness,
+            join_approval: jam.join_approval,
+            passcode: jam.passcode,
+            moderated: jam.moderated,
+            skip_threshold: jam.skip_threshold,
+            save_playlist: jam.save_playlist,
+        },
     })
 }
 
-pub async fn create_host<'e>(
+pub async fn get_settings<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<JamSettings, Error> {
+    Ok(get_jam(jam_id, executor).await?.settings)
+}
+
+/// the amount of votes needed to skip depends on the settings, so the skip votes are changed too,
+/// if explicit songs are no longer allowed they are removed from the queue,
+/// if the jam is no longer moderated the pending songs are approved,
+/// and without join approval everyone in the waiting room is admitted
+pub async fn update_settings<'e>(
+    jam_id: &str,
+    settings: &JamSettings,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<real_time::Changed, Error> {
+    settings.validate()?;
+
+    let res = sqlx::query!(
+        "UPDATE jams SET voting = $1, downvotes = $2, allow_explicit = $3, max_song_duration = $4, blocked_artists = $5, allowed_genres = $6, queue_strategy = $7, fairness = $8, join_approval = $9, moderated = $10, skip_threshold = $11, passcode = $12, save_playlist = $13 WHERE id = $14",
+        settings.voting,
+        settings.downvotes,
+        settings.allow_explicit,
+        settings.max_song_duration.map(|d| d as i32),
+        &settings.blocked_artists,
+        &settings.allowed_genres,
+        settings.queue_strategy.as_str(),
+        settings.fairness,
+        settings.join_approval,
+        settings.moderated,
+        settings.skip_threshold,
+        settings.passcode,
+        settings.save_playlist,
+        jam_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "jam with id {} does not exist, could not update settings",
+            jam_id
+        )));
+    }
+
+    let mut changed = real_time::Changed::new().settings().skip_votes();
+
+    if !settings.moderated {
+        let res = sqlx::query!(
+            "UPDATE songs SET status = 'approved' WHERE status = 'pending' AND user_id IN (SELECT id FROM users WHERE jam_id = $1)",
+            jam_id
+        )
+        .execute(&mut **transaction)
+        .await?;
+        if res.rows_affected() > 0 {
+            changed = changed.songs().votes().pending();
+        }
+    }
+
+    if !settings.join_approval {
+        let res = sqlx::query!(
+            "UPDATE users SET status = 'admitted' WHERE status = 'waiting' AND jam_id = $1",
+            jam_id
+        )
+        .execute(&mut **transaction)
+        .await?;
+        if res.rows_affected() > 0 {
+            changed = changed.users().join_requests();
+        }
+    }
+
+    if !settings.allow_explicit {
+        // the current song is left playing
+        let res = sqlx::query!(
+            "DELETE FROM songs WHERE explicit AND status <> 'playing' AND user_id IN (SELECT id FROM users WHERE jam_id = $1)",
+            jam_id
+        )
+        .execute(&mut **transaction)
+        .await?;
+        if res.rows_affected() > 0 {
+            return Ok(changed.songs().votes().pending());
+        }
+    }
+
+    Ok(changed)
+}
+
+/// stores the spotify token of the host, a host that connects spotify again gets its old token replaced
+pub async fn create_host(
     code: String,
     host_id: String,
     spotify_credentials: &SpotifyCredentials,
     reqwest_client: &reqwest::Client,
-    executor: impl sqlx::PgExecutor<'e>,
+    pool: &sqlx::PgPool,
     redirect_uri: &str,
 ) -> Result<(), Error> {
     use http::StatusCode;
@@ -75,9 +178,13 @@ pub async fn create_host<'e>(
         &StatusCode::OK | &StatusCode::CREATED => res.text().await,
         _ => {
             eprintln!("Error: {:?}", res);
-            sqlx::query!("DELETE FROM hosts WHERE id = $1", host_id)
-                .execute(executor)
-                .await?;
+            // a host that is connecting spotify again keeps their jam
+            sqlx::query!(
+                "DELETE FROM hosts WHERE id = $1 AND NOT EXISTS(SELECT 1 FROM jams WHERE host_id = $1)",
+                host_id
+            )
+            .execute(pool)
+            .await?;
             return Err(Error::Database(format!(
                 "error while acquiring spotify token, spotify returned not ok response code: {:#?}",
                 res
@@ -109,6 +216,10 @@ pub async fn create_host<'e>(
     let expires_at = now + token.expires_in;
 
     let access_token_id = cuid2::create_id();
+    let mut transaction = pool.begin().await?;
+    sqlx::query!("DELETE FROM access_tokens WHERE host_id = $1", host_id)
+        .execute(&mut *transaction)
+        .await?;
     sqlx::query!(
         "INSERT INTO access_tokens 
             (access_token, expires_at, scope, refresh_token,id, host_id) 
@@ -121,8 +232,9 @@ pub async fn create_host<'e>(
         access_token_id,
         host_id
     )
-    .execute(executor)
+    .execute(&mut *transaction)
     .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
@@ -148,9 +260,12 @@ pub async fn create_jam<'e>(
     name: &str,
     host_id: &str,
     max_song_count: i16,
+    settings: &JamSettings,
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
+    provider: &impl MusicProvider,
 ) -> Result<JamId, Error> {
+    settings.validate()?;
+
     println!("checking if jam exists");
     let jam_exists = sqlx::query!(
         "SELECT EXISTS(SELECT 1 FROM jams WHERE host_id=$1)",
@@ -192,11 +307,24 @@ pub async fn create_jam<'e>(
     println!("trying to insert jam");
 
     sqlx::query!(
-        "INSERT INTO jams (id, max_song_count, host_id, name) VALUES ($1, $2, $3, $4)",
+        "INSERT INTO jams (id, max_song_count, host_id, name, voting, downvotes, allow_explicit, max_song_duration, blocked_artists, allowed_genres, queue_strategy, fairness, join_approval, moderated, skip_threshold, passcode, save_playlist) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)",
         &jam_id,
         &max_song_count,
         host_id,
-        name
+        name,
+        settings.voting,
+        settings.downvotes,
+        settings.allow_explicit,
+        settings.max_song_duration.map(|d| d as i32),
+        &settings.blocked_artists,
+        &settings.allowed_genres,
+        settings.queue_strategy.as_str(),
+        settings.fairness,
+        settings.join_approval,
+        settings.moderated,
+        settings.skip_threshold,
+        settings.passcode,
+        settings.save_playlist
     )
     .execute(&mut **transaction)
     .await?;
@@ -215,34 +343,29 @@ pub async fn create_jam<'e>(
 
     println!("getting next song");
 
-    let song = get_next_song(&jam_id, &mut *transaction, credentials).await?;
+    let song = get_next_song(&jam_id, &mut *transaction, provider).await?;
     println!("trying to set current song");
     let changed = set_current_song(&song, &jam_id, &mut *transaction).await?;
     println!("trying to notify");
-    notify(changed, vec![], &jam_id, &mut *transaction).await?;
+    notify(changed, &jam_id, &mut *transaction).await?;
 
     println!("successfully created jam with id: {}", jam_id);
     Ok(jam_id)
 }
 
+/// only stores the position, the server leading the jam moves on to the next song once it is over,
+/// see `go_to_next_song_if_over`
 pub async fn set_current_song_position(
     jam_id: &str,
     percentage: f32,
-    credentials: SpotifyCredentials,
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
 ) -> Result<real_time::Changed, Error> {
     if !(0.0..=1.0).contains(&percentage) {
         return Err(Error::InvalidRequest(
-            "Percentage must be between 0 and 1".to_string(),
+            "Percentage must be between 0 and 1".into(),
         ));
     }
 
-    if percentage > 0.999 {
-        let changed = go_to_next_song(jam_id, transaction, credentials).await?;
-        println!("new song");
-        return Ok(changed.position());
-    }
-
     let res = sqlx::query!(
         "UPDATE jams SET song_position = $1 WHERE id = $2",
         percentage,
@@ -261,6 +384,28 @@ pub async fn set_current_song_position(
     Ok(real_time::Changed::new().position())
 }
 
+/// the position is reset in the same statement it is checked in, so the song is only skipped once
+pub async fn go_to_next_song_if_over(
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    provider: &impl MusicProvider,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "UPDATE jams SET song_position = 0 WHERE id = $1 AND song_position > 0.999",
+        jam_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Ok(real_time::Changed::new());
+    }
+
+    let changed = go_to_next_song(jam_id, transaction, provider).await?;
+    println!("new song");
+    Ok(changed.position())
+}
+
 pub async fn get_current_song_position<'e>(
     jam_id: &str,
     executor: impl sqlx::PgExecutor<'e>,
@@ -295,11 +440,16 @@ pub async fn get_current_song<'e>(
         pub duration: i32,
         pub artists: Option<Vec<String>>,
         pub image_url: String,
+        pub explicit: bool,
     }
 
-    let song = match sqlx::query_as!(SongDb, "SELECT * FROM songs WHERE user_id=$1", jam_id)
-        .fetch_optional(executor)
-        .await
+    let song = match sqlx::query_as!(
+        SongDb,
+        "SELECT id, spotify_id, user_id, name, album, duration, artists, image_url, explicit FROM songs WHERE user_id=$1 AND status = 'playing'",
+        jam_id
+    )
+    .fetch_optional(executor)
+    .await
     {
         Ok(song) => song,
         Err(sqlx::Error::RowNotFound) => {
@@ -331,6 +481,8 @@ pub async fn get_current_song<'e>(
         album: song.album,
         duration: song.duration as u32,
         image_url: song.image_url,
+        explicit: song.explicit,
+        pinned: false,
     }))
 }
 
@@ -346,14 +498,15 @@ pub async fn set_current_song<'e>(
     // Try to update the song first
     let res = sqlx::query!(
         "UPDATE songs 
-        SET name = $1, album = $2, duration = $3, artists = $4, image_url = $5, spotify_id = $6 
-        WHERE user_id = $7",
+        SET name = $1, album = $2, duration = $3, artists = $4, image_url = $5, spotify_id = $6, explicit = $7 
+        WHERE user_id = $8 AND status = 'playing'",
         song.name,
         song.album,
         song.duration as i32,
         &song.artists,
         song.image_url,
         song.spotify_id,
+        song.explicit,
         jam_id
     )
     .execute(&mut **transaction)
@@ -362,8 +515,8 @@ pub async fn set_current_song<'e>(
     // If no rows were affected, insert the new song
     if res.rows_affected() == 0 {
         sqlx::query!(
-            "INSERT INTO songs (id, name, album, duration, artists, image_url, spotify_id, user_id) 
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            "INSERT INTO songs (id, name, album, duration, artists, image_url, spotify_id, user_id, explicit, status) 
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'playing')",
             song_id,
             song.name,
             song.album,
@@ -371,13 +524,16 @@ pub async fn set_current_song<'e>(
             &song.artists,
             song.image_url,
             song.spotify_id,
-            jam_id
+            jam_id,
+            song.explicit
         )
         .execute(&mut **transaction)
         .await?;
     }
 
-    Ok(real_time::Changed::new().current_song()) // Return success
+    let changed = super::add_to_history(song, jam_id, &mut **transaction).await?;
+
+    Ok(changed.current_song()) // Return success
 }
 
 pub async fn dose_jam_exist<'e>(
@@ -397,47 +553,97 @@ pub async fn dose_jam_exist<'e>(
 pub async fn get_next_song<'e>(
     jam_id: &str,
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
+    provider: &impl MusicProvider,
 ) -> Result<Song, Error> {
     use super::*;
-    let top_song = get_top_song(transaction, jam_id.to_string()).await?;
+    let jam = get_jam(jam_id, &mut **transaction).await?;
+    let top_song = get_top_song(
+        transaction,
+        jam_id.to_string(),
+        jam.settings.queue_strategy,
+        jam.settings.fairness,
+    )
+    .await?;
     if let Some(s) = top_song {
         return Ok(s);
     }
 
-    if let Ok(Some(s)) = get_next_song_from_player(jam_id, transaction, credentials.clone()).await {
-        return Ok(s);
+    // the songs in the queue were checked when they were added, these were not
+    let allowed = |s: &Song| jam.settings.allow_explicit || !s.explicit;
+
+    if let Ok(Some(s)) = provider
+        .get_next_song_from_player(jam_id, transaction)
+        .await
+    {
+        if allowed(&s) {
+            return Ok(s);
+        }
     }
 
-    match get_song_recommendation(credentials.clone(), jam_id, transaction).await {
-        Ok(s) => return Ok(s),
+    match provider.get_song_recommendation(jam_id, transaction).await {
+        Ok(s) if allowed(&s) => return Ok(s),
+        Ok(s) => {
+            println!("skipping explicit recommendation: {}", s.name);
+        }
         Err(e) => {
             eprintln!("error getting song recommendation: {}", e);
         }
     }
 
-    let never_gonna = search("Never gonna give you up", transaction, jam_id, credentials)
+    let never_gonna = provider
+        .search("Never gonna give you up", jam_id, transaction)
         .await?
-        .remove(0);
-    Ok(never_gonna)
+        .into_iter()
+        .next();
+    match never_gonna {
+        Some(s) => Ok(s),
+        None => Err(Error::DoesNotExist(format!(
+            "could not find any song to play next in jam with id: {}",
+            jam_id
+        ))),
+    }
 }
 
 pub async fn go_to_next_song<'e>(
     jam_id: &str,
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
+    provider: &impl MusicProvider,
 ) -> Result<Changed, Error> {
     use super::*;
 
-    let top_song = get_next_song(jam_id, transaction, credentials.clone()).await?;
+    let top_song = get_next_song(jam_id, transaction, provider).await?;
 
     let changed = set_current_song(&top_song, jam_id, transaction).await?;
 
+    // recommendations were never in the queue, so they are nobody's turn
+    if let Some(user_id) = &top_song.user_id {
+        sqlx::query!(
+            "UPDATE jams SET last_user_id = $1 WHERE id = $2",
+            user_id,
+            jam_id
+        )
+        .execute(&mut **transaction)
+        .await?;
+        sqlx::query!(
+            "UPDATE users SET last_played_at = $1 WHERE id = $2",
+            chrono::Utc::now().timestamp_millis(),
+            user_id
+        )
+        .execute(&mut **transaction)
+        .await?;
+    }
+
     let changed = reset_votes(jam_id, &mut **transaction)
         .await?
         .merge_with_other(changed);
 
-    play_song(&top_song.spotify_id, jam_id, transaction, credentials).await?;
+    let changed = reset_skip_votes(jam_id, &mut **transaction)
+        .await?
+        .merge_with_other(changed);
+
+    provider
+        .play_song(&top_song.spotify_id, jam_id, transaction)
+        .await?;
 
     Ok(changed)
 }
@@ -0,0 +1,109 @@
+use super::{
+    dose_jam_exist, get_current_song, go_to_next_song_if_over, notify, MusicProvider, Provider,
+};
+use crate::model::types::*;
+use std::time::Duration;
+
+/// how often the leader checks if the current song is over
+const TICK: Duration = Duration::from_secs(1);
+/// every this many ticks everyone gets everything again and the player is checked
+const CHECKUP_TICKS: u32 = 10;
+
+/// Starts the background work of the jam once a host connects to this server, if it isn't running here already.
+/// It runs on every server a host of the jam is connected to, but only does something on the one leading the jam
+pub fn watch_jam(jam_id: &str, pool: &sqlx::PgPool, provider: &Provider, leader: &Leader) {
+    if leader.host_joined(jam_id) {
+        tokio::spawn(lead_jam(
+            jam_id.to_string(),
+            pool.clone(),
+            provider.clone(),
+            leader.clone(),
+        ));
+    }
+}
+
+async fn lead_jam(jam_id: String, pool: sqlx::PgPool, provider: Provider, leader: Leader) {
+    let mut ticks = 0;
+    while leader.has_hosts(&jam_id) && dose_jam_exist(&jam_id, &pool).await.unwrap_or(true) {
+        if leader.lead(&jam_id).await {
+            if let Err(e) = next_song_if_over(&jam_id, &pool, &provider).await {
+                eprintln!("Error going to the next song in jam {}: {:?}", jam_id, e);
+            }
+            if ticks % CHECKUP_TICKS == 0 {
+                checkup(&jam_id, &pool, &provider).await;
+            }
+            ticks += 1;
+        }
+
+        tokio::time::sleep(TICK).await;
+    }
+
+    leader.resign(&jam_id).await;
+}
+
+async fn next_song_if_over(
+    jam_id: &str,
+    pool: &sqlx::PgPool,
+    provider: &Provider,
+) -> Result<(), Error> {
+    let mut transaction = pool.begin().await?;
+    let changed = go_to_next_song_if_over(jam_id, &mut transaction, provider).await?;
+    notify(changed, jam_id, &mut transaction).await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+async fn checkup(jam_id: &str, pool: &sqlx::PgPool, provider: &Provider) {
+    println!("Occasional notify");
+
+    let mut transaction = match pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            eprintln!("Error starting transaction in occasional notify: {:?}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = notify(real_time::Changed::all(), jam_id, &mut transaction).await {
+        eprintln!("Error notifying all, in occasional notify: {:?}", e);
+    };
+
+    play_the_current_song_if_player_is_not_playing_it(jam_id, &mut transaction, provider)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Error playing current song in occasional notify: {:?}", e);
+        });
+    if let Err(e) = transaction.commit().await {
+        eprintln!("Error committing transaction in occasional notify: {:?}", e);
+    }
+}
+
+async fn play_the_current_song_if_player_is_not_playing_it<'e>(
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    provider: &impl MusicProvider,
+) -> Result<(), Error> {
+    let current_song = get_current_song(jam_id, &mut **transaction).await?;
+    let song = match current_song {
+        Some(song) => song,
+        None => return Ok(()),
+    };
+    let player_current_song = provider
+        .get_current_song_from_player(jam_id, transaction)
+        .await?;
+    if player_current_song
+        .as_ref()
+        .map(|s| s.spotify_id != song.spotify_id)
+        .unwrap_or(true)
+    {
+        println!(
+            "playing song: {:?}, set away from: {}",
+            song.name,
+            player_current_song.map(|s| s.name).unwrap_or_default()
+        );
+        provider
+            .play_song(&song.spotify_id, jam_id, transaction)
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,300 @@
+use super::MusicProvider;
+use crate::model::types::*;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use lofty::{
+    file::{AudioFile, TaggedFileExt},
+    tag::Accessor,
+};
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const SUPPORTED_EXTENSIONS: [&str; 3] = ["mp3", "flac", "ogg"];
+
+/// Plays songs from a directory of audio files, for parties without internet.
+/// The id of a song is its path relative to the library root, encoded in url safe base64,
+/// the audio and the cover art are served by the router under `/library/{id}/..`
+#[derive(Debug, Clone)]
+pub struct LocalProvider {
+    root: PathBuf,
+    library: Arc<Vec<Song>>,
+    /// the genres from the tags of the songs, by song id
+    genres: Arc<HashMap<String, Vec<String>>>,
+    current_songs: Arc<Mutex<HashMap<String, Song>>>,
+}
+
+impl LocalProvider {
+    /// scans the directory recursively, files that have no readable tags are skipped
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let root = root.into();
+        let mut library = Vec::new();
+        let mut genres = HashMap::new();
+        scan_dir(&root, &root, &mut library, &mut genres)?;
+        println!(
+            "loaded {} songs from local library: {}",
+            library.len(),
+            root.display()
+        );
+
+        Ok(Self {
+            root,
+            library: Arc::new(library),
+            genres: Arc::new(genres),
+            current_songs: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// only returns paths of songs that are in the library, so the id can't be used to read other files
+    pub fn path_of(&self, song_id: &str) -> Result<PathBuf, Error> {
+        if !self.library.iter().any(|s| s.spotify_id == song_id) {
+            return Err(Error::DoesNotExist(format!(
+                "song with id {} is not in the local library",
+                song_id
+            )));
+        }
+        let relative = URL_SAFE_NO_PAD
+            .decode(song_id)
+            .map_err(|e| Error::Decode(format!("invalid local song id: {}", e)))?;
+        let relative = String::from_utf8(relative)
+            .map_err(|e| Error::Decode(format!("invalid local song id: {}", e)))?;
+        Ok(self.root.join(relative))
+    }
+
+    /// the embedded cover art of the song and its mime type
+    pub fn cover_of(&self, song_id: &str) -> Result<Option<(Vec<u8>, String)>, Error> {
+        let path = self.path_of(song_id)?;
+        let tagged_file = lofty::read_from_path(&path).map_err(|e| {
+            Error::FileSystem(format!("could not read tags of {}: {}", path.display(), e))
+        })?;
+        let picture = tagged_file
+            .primary_tag()
+            .or_else(|| tagged_file.first_tag())
+            .and_then(|tag| tag.pictures().first().cloned());
+
+        Ok(picture.map(|picture| {
+            let mime_type = picture
+                .mime_type()
+                .map(|m| m.as_str().to_string())
+                .unwrap_or("image/jpeg".to_string());
+            (picture.data().to_vec(), mime_type)
+        }))
+    }
+
+    fn find(&self, song_id: &str) -> Result<Song, Error> {
+        self.library
+            .iter()
+            .find(|s| s.spotify_id == song_id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::DoesNotExist(format!(
+                    "song with id {} is not in the local library",
+                    song_id
+                ))
+            })
+    }
+}
+
+fn scan_dir(
+    dir: &Path,
+    root: &Path,
+    library: &mut Vec<Song>,
+    genres: &mut HashMap<String, Vec<String>>,
+) -> Result<(), Error> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        Error::FileSystem(format!(
+            "could not read library directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            scan_dir(&path, root, library, genres)?;
+            continue;
+        }
+
+        let is_supported = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+            .unwrap_or(false);
+        if !is_supported {
+            continue;
+        }
+
+        match read_song(&path, root) {
+            Ok((song, song_genres)) => {
+                if !song_genres.is_empty() {
+                    genres.insert(song.spotify_id.clone(), song_genres);
+                }
+                library.push(song);
+            }
+            Err(e) => eprintln!("skipping {}: {}", path.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+fn read_song(path: &Path, root: &Path) -> Result<(Song, Vec<String>), Error> {
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|e| Error::FileSystem(e.to_string()))?
+        .to_string_lossy()
+        .to_string();
+    let id = URL_SAFE_NO_PAD.encode(relative);
+
+    let tagged_file = lofty::read_from_path(path)
+        .map_err(|e| Error::FileSystem(format!("could not read tags: {}", e)))?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag());
+
+    let file_name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let genres = tag
+        .and_then(|tag| tag.genre())
+        .map(|g| g.split(';').map(|g| g.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    let (name, artists, album, has_cover) = match tag {
+        Some(tag) => (
+            tag.title().map(|t| t.to_string()).unwrap_or(file_name),
+            tag.artist()
+                .map(|a| a.split(';').map(|a| a.trim().to_string()).collect())
+                .unwrap_or(vec!["Unknown artist".to_string()]),
+            tag.album().map(|a| a.to_string()).unwrap_or_default(),
+            !tag.pictures().is_empty(),
+        ),
+        None => (
+            file_name,
+            vec!["Unknown artist".to_string()],
+            String::new(),
+            false,
+        ),
+    };
+
+    let song = Song {
+        id: None,
+        image_url: if has_cover {
+            format!("/library/{}/cover", id)
+        } else {
+            String::new()
+        },
+        spotify_id: id,
+        user_id: None,
+        name,
+        artists,
+        album,
+        duration: tagged_file.properties().duration().as_millis() as u32,
+        votes: Vote {
+            votes: 0,
+            have_you_voted: None,
+        },
+        // audio tags have no common way to mark a song as explicit
+        explicit: false,
+        pinned: false,
+    };
+
+    Ok((song, genres))
+}
+
+impl MusicProvider for LocalProvider {
+    async fn search<'e>(
+        &self,
+        query: &str,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Vec<Song>, Error> {
+        let query = query.to_lowercase();
+        Ok(self
+            .library
+            .iter()
+            .filter(|s| {
+                s.name.to_lowercase().contains(&query)
+                    || s.album.to_lowercase().contains(&query)
+                    || s.artists.iter().any(|a| a.to_lowercase().contains(&query))
+            })
+            .take(30)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_song<'e>(
+        &self,
+        song_id: &str,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        self.find(song_id)
+    }
+
+    /// the actual playing is done by the host page, this only remembers what it should be playing
+    async fn play_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        let song = self.find(song_id)?;
+        self.current_songs
+            .lock()
+            .unwrap()
+            .insert(jam_id.to_string(), song);
+        Ok(())
+    }
+
+    async fn get_current_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        Ok(self.current_songs.lock().unwrap().get(jam_id).cloned())
+    }
+
+    async fn get_next_song_from_player<'e>(
+        &self,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        Ok(None)
+    }
+
+    async fn get_song_recommendation<'e>(
+        &self,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        self.library
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .ok_or_else(|| Error::DoesNotExist("the local library is empty".to_string()))
+    }
+
+    async fn get_genres<'e>(
+        &self,
+        song_ids: &[String],
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<HashMap<String, Vec<String>>, Error> {
+        Ok(song_ids
+            .iter()
+            .filter_map(|id| self.genres.get(id).map(|g| (id.clone(), g.clone())))
+            .collect())
+    }
+
+    async fn switch_playback_to_device<'e>(
+        &self,
+        _device_id: &str,
+        _jam_id: &str,
+        _transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -1,6 +1,15 @@
 pub mod spotify;
 pub use spotify::*;
 
+mod music_provider;
+pub use music_provider::*;
+
+mod fake_provider;
+pub use fake_provider::*;
+
+mod local_provider;
+pub use local_provider::*;
+
 mod song;
 pub use song::*;
 
@@ -13,5 +22,26 @@ pub use user::*;
 mod real_time;
 pub use real_time::*;
 
+mod leader;
+pub use leader::*;
+
 mod jam;
 pub use jam::*;
+
+mod history;
+pub use history::*;
+
+mod export;
+pub use export::*;
+
+mod skip;
+pub use skip::*;
+
+mod token;
+pub use token::*;
+
+mod invite;
+pub use invite::*;
+
+mod session;
+pub use session::*;
@@ -0,0 +1,223 @@
+use super::{FakeProvider, LocalProvider, SpotifyProvider};
+use crate::model::types::*;
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Everything a jam needs from the service that actually has the music,
+/// the songs returned never have an id or votes, those only exist once they are in the jam
+pub trait MusicProvider: Send + Sync {
+    fn search<'e>(
+        &self,
+        query: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<Vec<Song>, Error>> + Send;
+
+    /// the song_id is the id of the song in the provider, so `Song::spotify_id`
+    fn get_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<Song, Error>> + Send;
+
+    fn play_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn get_current_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<Option<Song>, Error>> + Send;
+
+    fn get_next_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<Option<Song>, Error>> + Send;
+
+    fn get_song_recommendation<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<Song, Error>> + Send;
+
+    /// the genres of the songs by their `Song::spotify_id`, songs with no known genres can be left out
+    fn get_genres<'e>(
+        &self,
+        song_ids: &[String],
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<HashMap<String, Vec<String>>, Error>> + Send;
+
+    fn switch_playback_to_device<'e>(
+        &self,
+        device_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// The provider the server was started with, picked with the `MUSIC_PROVIDER` env
+#[derive(Debug, Clone)]
+pub enum Provider {
+    Spotify(SpotifyProvider),
+    /// an in memory library, for development and testing without spotify
+    Fake(FakeProvider),
+    /// a directory of audio files on the server, set with the `MUSIC_LIBRARY` env
+    Local(LocalProvider),
+}
+
+impl Provider {
+    pub fn new(
+        kind: &str,
+        credentials: SpotifyCredentials,
+        library_path: Option<String>,
+    ) -> Result<Self, Error> {
+        match kind {
+            "spotify" => Ok(Provider::Spotify(SpotifyProvider::new(credentials))),
+            "fake" => Ok(Provider::Fake(FakeProvider::default())),
+            "local" => match library_path {
+                Some(path) => Ok(Provider::Local(LocalProvider::new(path)?)),
+                None => Err(Error::EnvNotFound(
+                    "MUSIC_LIBRARY must be set when using the local music provider".to_string(),
+                )),
+            },
+            _ => Err(Error::InvalidRequest(
+                format!(
+                    "unknown music provider: {}, expected spotify, local or fake",
+                    kind
+                )
+                .into(),
+            )),
+        }
+    }
+
+    pub fn is_spotify(&self) -> bool {
+        matches!(self, Provider::Spotify(_))
+    }
+
+    pub fn kind(&self) -> ProviderKind {
+        match self {
+            Provider::Spotify(_) => ProviderKind::Spotify,
+            Provider::Fake(_) => ProviderKind::Fake,
+            Provider::Local(_) => ProviderKind::Local,
+        }
+    }
+}
+
+impl MusicProvider for Provider {
+    async fn search<'e>(
+        &self,
+        query: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Vec<Song>, Error> {
+        match self {
+            Provider::Spotify(p) => p.search(query, jam_id, transaction).await,
+            Provider::Fake(p) => p.search(query, jam_id, transaction).await,
+            Provider::Local(p) => p.search(query, jam_id, transaction).await,
+        }
+    }
+
+    async fn get_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        match self {
+            Provider::Spotify(p) => p.get_song(song_id, jam_id, transaction).await,
+            Provider::Fake(p) => p.get_song(song_id, jam_id, transaction).await,
+            Provider::Local(p) => p.get_song(song_id, jam_id, transaction).await,
+        }
+    }
+
+    async fn play_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        match self {
+            Provider::Spotify(p) => p.play_song(song_id, jam_id, transaction).await,
+            Provider::Fake(p) => p.play_song(song_id, jam_id, transaction).await,
+            Provider::Local(p) => p.play_song(song_id, jam_id, transaction).await,
+        }
+    }
+
+    async fn get_current_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        match self {
+            Provider::Spotify(p) => p.get_current_song_from_player(jam_id, transaction).await,
+            Provider::Fake(p) => p.get_current_song_from_player(jam_id, transaction).await,
+            Provider::Local(p) => p.get_current_song_from_player(jam_id, transaction).await,
+        }
+    }
+
+    async fn get_next_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        match self {
+            Provider::Spotify(p) => p.get_next_song_from_player(jam_id, transaction).await,
+            Provider::Fake(p) => p.get_next_song_from_player(jam_id, transaction).await,
+            Provider::Local(p) => p.get_next_song_from_player(jam_id, transaction).await,
+        }
+    }
+
+    async fn get_song_recommendation<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        match self {
+            Provider::Spotify(p) => p.get_song_recommendation(jam_id, transaction).await,
+            Provider::Fake(p) => p.get_song_recommendation(jam_id, transaction).await,
+            Provider::Local(p) => p.get_song_recommendation(jam_id, transaction).await,
+        }
+    }
+
+    async fn get_genres<'e>(
+        &self,
+        song_ids: &[String],
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<HashMap<String, Vec<String>>, Error> {
+        match self {
+            Provider::Spotify(p) => p.get_genres(song_ids, jam_id, transaction).await,
+            Provider::Fake(p) => p.get_genres(song_ids, jam_id, transaction).await,
+            Provider::Local(p) => p.get_genres(song_ids, jam_id, transaction).await,
+        }
+    }
+
+    async fn switch_playback_to_device<'e>(
+        &self,
+        device_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        match self {
+            Provider::Spotify(p) => {
+                p.switch_playback_to_device(device_id, jam_id, transaction)
+                    .await
+            }
+            Provider::Fake(p) => {
+                p.switch_playback_to_device(device_id, jam_id, transaction)
+                    .await
+            }
+            Provider::Local(p) => {
+                p.switch_playback_to_device(device_id, jam_id, transaction)
+                    .await
+            }
+        }
+    }
+}
@@ -1,15 +1,23 @@
 use crate::model::types::*;
+use sqlx::postgres::PgListener;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// the Postgres NOTIFY channel the updates of all jams are sent through
+const UPDATES_CHANNEL: &str = "jam_updates";
 
 /// only the jam id is used form the id
 /// some fields such as songs and votes have different outputs depending on the id type
 pub async fn notify<'e>(
     changed: real_time::Changed,
-    errors: Vec<Error>,
     jam_id: &str,
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
 ) -> Result<(), sqlx::Error> {
     if changed.has_changed() {
-        let update = real_time::ChannelUpdate { errors, changed };
+        let update = real_time::ChannelUpdate {
+            jam_id: jam_id.to_string(),
+            changed,
+        };
         let update = match serde_json::to_string(&update) {
             Ok(update) => update,
             Err(e) => {
@@ -18,9 +26,128 @@ pub async fn notify<'e>(
             }
         };
 
-        sqlx::query!("SELECT pg_notify($1,$2)", jam_id, update)
+        sqlx::query!("SELECT pg_notify($1,$2)", UPDATES_CHANNEL, update)
             .execute(&mut **transaction)
             .await?;
     }
     Ok(())
 }
+
+/// Listens for the updates of every jam on a single connection and hands them to the hub.
+/// The updates of this server come through Postgres too, they are only sent once the transaction is committed,
+/// and that is also how the other servers running the app get them.
+/// Every jam has a task that loads its updates, so a slow jam doesn't hold up the others
+pub async fn start_hub(pool: &sqlx::PgPool) -> Result<Hub, Error> {
+    let mut listener = PgListener::connect_with(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+    listener
+        .listen(UPDATES_CHANNEL)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    let hub = Hub::default();
+    tokio::spawn(forward_updates(listener, pool.clone(), hub.clone()));
+    Ok(hub)
+}
+
+async fn forward_updates(mut listener: PgListener, pool: sqlx::PgPool, hub: Hub) {
+    let mut jams = HashMap::new();
+    loop {
+        match listener.try_recv().await {
+            Ok(Some(notification)) => {
+                let update: real_time::ChannelUpdate =
+                    match serde_json::from_str(notification.payload()) {
+                        Ok(update) => update,
+                        Err(e) => {
+                            eprintln!("Error decoding message sent in listen/notify: {:#?}", e);
+                            continue;
+                        }
+                    };
+
+                if hub.has_subscribers(&update.jam_id) {
+                    forward(&mut jams, &pool, &hub, update.jam_id, update.changed);
+                }
+            }
+            // the connection is made again on the next try, the updates sent in between are lost
+            Ok(None) => {
+                eprintln!("pool disconnected on listener, reconnecting...");
+                for jam_id in hub.jam_ids() {
+                    forward(&mut jams, &pool, &hub, jam_id, real_time::Changed::all());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error receiving from listener: {:?}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// hands the update to the task of the jam, one is started if the jam has none
+fn forward(
+    jams: &mut HashMap<String, mpsc::UnboundedSender<real_time::Changed>>,
+    pool: &sqlx::PgPool,
+    hub: &Hub,
+    jam_id: String,
+    changed: real_time::Changed,
+) {
+    // the tasks of the jams the hub forgot have ended
+    jams.retain(|_, sender| !sender.is_closed());
+    let changed = match jams.get(&jam_id) {
+        Some(sender) => match sender.send(changed) {
+            Ok(()) => return,
+            Err(mpsc::error::SendError(changed)) => changed,
+        },
+        None => changed,
+    };
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    // the receiver is alive, it was just made
+    let _ = sender.send(changed);
+    jams.insert(jam_id.clone(), sender);
+    tokio::spawn(publish_jam(pool.clone(), hub.clone(), jam_id, receiver));
+}
+
+/// publishes the updates of one jam in the order they came in,
+/// the ones that came in while an update was loading are loaded together
+async fn publish_jam(
+    pool: sqlx::PgPool,
+    hub: Hub,
+    jam_id: String,
+    mut receiver: mpsc::UnboundedReceiver<real_time::Changed>,
+) {
+    while let Some(mut changed) = receiver.recv().await {
+        while let Ok(more) = receiver.try_recv() {
+            changed = changed.merge_with_other(more);
+        }
+        publish(&pool, &hub, &jam_id, changed).await;
+
+        if !hub.has_subscribers(&jam_id) {
+            // the updates that come after this start a new task, the ones already sent are still published
+            receiver.close();
+            while let Some(changed) = receiver.recv().await {
+                publish(&pool, &hub, &jam_id, changed).await;
+            }
+            break;
+        }
+    }
+}
+
+async fn publish(pool: &sqlx::PgPool, hub: &Hub, jam_id: &str, changed: real_time::Changed) {
+    let mut transaction = match pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            eprintln!("Error starting transaction for update: {:?}", e);
+            return;
+        }
+    };
+
+    let update = real_time::SharedUpdate::from_changed(changed, jam_id, &mut transaction).await;
+
+    if let Err(e) = transaction.commit().await {
+        eprintln!("Error committing transaction for update: {:?}", e);
+    }
+
+    hub.publish(jam_id, update);
+}
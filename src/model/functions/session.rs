@@ -0,0 +1,137 @@
+use super::{check_id_type, sign_token, verify_token};
+use crate::model::types::*;
+
+/// a week, after that the user has to join again and the host has to connect again
+pub const SESSION_DURATION: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// ten minutes, for the host to connect spotify and come back
+pub const OAUTH_STATE_DURATION: i64 = 10 * 60 * 1000;
+
+/// the cookie of the host session
+const HOST_SESSION_COOKIE: &str = "host_session";
+
+/// the session is the id of the user or host with when it expires
+pub fn create_session(id: &str) -> Session {
+    Session {
+        id: id.to_string(),
+        expires_at: chrono::Utc::now().timestamp_millis() + SESSION_DURATION,
+    }
+}
+
+/// the session signed so it can't be changed, it is only handed out in the session cookie
+pub fn session_token(session: &Session, secret_key: &str) -> String {
+    sign_token(
+        "session",
+        &format!("{}:{}", session.id, session.expires_at),
+        secret_key,
+    )
+}
+
+/// a user has a session for each jam they joined, so one browser can host a jam and be a user in another one
+pub fn session_cookie_name(user_of_jam: Option<&str>) -> String {
+    match user_of_jam {
+        Some(jam_id) => format!("user_session_{}", jam_id),
+        None => HOST_SESSION_COOKIE.to_string(),
+    }
+}
+
+/// the `Set-Cookie` header that hands out the session, the token can't be read by the scripts of the page,
+/// and it is not sent with requests that other sites make
+pub fn session_cookie(session: &Session, user_of_jam: Option<&str>, secret_key: &str) -> String {
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+        session_cookie_name(user_of_jam),
+        session_token(session, secret_key),
+        SESSION_DURATION / 1000
+    )
+}
+
+/// the value of a cookie that came with the request
+pub fn get_cookie<'a>(headers: &'a http::HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|cookies| cookies.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(name)?.strip_prefix('='))
+}
+
+/// the session token of the request, from the `Authorization: Bearer` header for clients that are not browsers,
+/// or from the session cookie, the one of the host if `user_of_jam` is none
+pub fn get_session_token<'a>(
+    headers: &'a http::HeaderMap,
+    user_of_jam: Option<&str>,
+) -> Result<&'a str, Error> {
+    let bearer = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| get_cookie(headers, &session_cookie_name(user_of_jam)))
+        .ok_or_else(|| Error::InvalidSession("there is no session".to_string()))
+}
+
+/// returns the id the session was created for, if the token was signed by this server and has not expired
+pub fn verify_session(token: &str, secret_key: &str) -> Result<String, Error> {
+    let data = verify_token(token, "session", secret_key)
+        .ok_or_else(|| Error::InvalidSession("the session token is not valid".to_string()))?;
+
+    let (id, expires_at) = data
+        .rsplit_once(':')
+        .and_then(|(id, expires_at)| Some((id, expires_at.parse::<i64>().ok()?)))
+        .ok_or_else(|| Error::InvalidSession("the session token is not valid".to_string()))?;
+
+    if expires_at < chrono::Utc::now().timestamp_millis() {
+        return Err(Error::InvalidSession("the session has expired".to_string()));
+    }
+
+    Ok(id.to_string())
+}
+
+/// the state sent through the spotify authorization, so only the one who was sent there can get a session for the host
+pub fn oauth_state(host_id: &str, secret_key: &str) -> String {
+    let expires_at = chrono::Utc::now().timestamp_millis() + OAUTH_STATE_DURATION;
+    sign_token("oauth", &format!("{}:{}", host_id, expires_at), secret_key)
+}
+
+/// returns the host id the state was made for, if it was signed by this server and has not expired
+pub fn verify_oauth_state(state: &str, secret_key: &str) -> Result<String, Error> {
+    let (host_id, expires_at) = verify_token(state, "oauth", secret_key)
+        .as_deref()
+        .and_then(|data| data.rsplit_once(':'))
+        .and_then(|(host_id, expires_at)| {
+            Some((host_id.to_string(), expires_at.parse::<i64>().ok()?))
+        })
+        .ok_or_else(|| {
+            Error::InvalidSession("the state of the request is not valid".to_string())
+        })?;
+
+    if expires_at < chrono::Utc::now().timestamp_millis() {
+        return Err(Error::InvalidSession(
+            "the state of the request has expired, try again".to_string(),
+        ));
+    }
+
+    Ok(host_id)
+}
+
+/// verifies the session, then finds out if the id in it belongs to a host or a user, like `check_id_type`
+pub async fn check_session<'e>(
+    token: &str,
+    secret_key: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<Id, Error> {
+    let id = verify_session(token, secret_key)?;
+    check_id_type(&id, transaction).await
+}
+
+/// `check_session` with the session the request was made with, see `get_session_token`
+pub async fn check_request_session<'e>(
+    headers: &http::HeaderMap,
+    user_of_jam: Option<&str>,
+    secret_key: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<Id, Error> {
+    let token = get_session_token(headers, user_of_jam)?;
+    check_session(token, secret_key, transaction).await
+}
@@ -0,0 +1,93 @@
+use super::{get_settings, get_users, go_to_next_song, MusicProvider};
+use crate::model::types::*;
+
+/// skips the current song if enough users voted to skip it
+pub async fn vote_skip<'e>(
+    user_id: &str,
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    provider: &impl MusicProvider,
+) -> Result<real_time::Changed, Error> {
+    let result = sqlx::query!(
+        "INSERT INTO skip_votes (user_id, jam_id) VALUES ($1, $2) ON CONFLICT (user_id) DO NOTHING",
+        user_id,
+        jam_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::Forbidden(
+            "user has already voted to skip this song".to_string(),
+        ));
+    }
+
+    let id = Id::new(IdType::General, jam_id.to_string());
+    let skip_votes = get_skip_votes(transaction, &id).await?;
+    if skip_votes.votes < skip_votes.needed {
+        return Ok(real_time::Changed::new().skip_votes());
+    }
+
+    skip_song(jam_id, transaction, provider).await
+}
+
+/// skips the current song right away, for the host and the co-hosts who are allowed to
+pub async fn skip_song<'e>(
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    provider: &impl MusicProvider,
+) -> Result<real_time::Changed, Error> {
+    println!("skipping song in jam {}", jam_id);
+    let changed = go_to_next_song(jam_id, transaction, provider).await?;
+    sqlx::query!("UPDATE jams SET song_position = 0 WHERE id = $1", jam_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    Ok(changed.position().skip_votes())
+}
+
+pub async fn get_skip_votes<'e>(
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    id: &Id,
+) -> Result<SkipVotes, Error> {
+    let skip_threshold = get_settings(id.jam_id(), &mut **transaction)
+        .await?
+        .skip_threshold;
+    let user_count = get_users(&mut **transaction, id).await?.len();
+
+    let voters = get_skip_voters(id.jam_id(), &mut **transaction).await?;
+
+    Ok(SkipVotes {
+        votes: voters.len() as u32,
+        needed: ((user_count as f32 * skip_threshold).ceil() as u32).max(1),
+        have_you_voted: match &id.id {
+            IdType::User(user_id) => Some(voters.contains(user_id)),
+            IdType::Host(_) | IdType::General => None,
+        },
+    })
+}
+
+/// the ids of the users who voted to skip the current song
+pub async fn get_skip_voters<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let voters = sqlx::query!("SELECT user_id FROM skip_votes WHERE jam_id=$1", jam_id)
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|vote| vote.user_id)
+        .collect();
+    Ok(voters)
+}
+
+pub async fn reset_skip_votes<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, sqlx::Error> {
+    sqlx::query!("DELETE FROM skip_votes WHERE jam_id=$1", jam_id)
+        .execute(executor)
+        .await?;
+
+    Ok(real_time::Changed::new().skip_votes())
+}
@@ -1,39 +1,51 @@
-use crate::model::functions::get_access_token;
+use crate::model::functions::{get_settings, MusicProvider};
 use crate::model::types::*;
 use itertools::Itertools;
-use leptos::logging::*;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
-use rspotify::model::TrackId;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
+/// every vote moves a song this many places forward in the queue, used by `QueueStrategy::FifoWithVoteBoost`
+const VOTE_BOOST: i64 = 2;
+/// with fairness on, the users whose songs were played in the last this many turns are skipped
+const FAIRNESS_COOLDOWN: usize = 2;
+
 pub async fn remove_song<'e>(
     song_id: &str,
     id: &Id,
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
 ) -> Result<real_time::Changed, Error> {
-    // Check if the ID is a user and if the song belongs to the user
-    if let IdType::User(user_id) = &id.id {
-        let song_user = sqlx::query!(
-            "SELECT * FROM songs WHERE id=$1 AND user_id=$2",
-            song_id,
-            user_id
-        )
-        .fetch_optional(&mut **transaction)
-        .await?;
+    // Check if the ID is a user and if the song belongs to the user,
+    // co-hosts who can approve songs can remove any song, like the host
+    match &id.id {
+        IdType::User(user_id) if !id.can(Permission::ApproveSongs) => {
+            let song_user = sqlx::query!(
+                "SELECT * FROM songs WHERE id=$1 AND user_id=$2",
+                song_id,
+                user_id
+            )
+            .fetch_optional(&mut **transaction)
+            .await?;
 
-        // Return error if the song does not belong to the user
-        if song_user.is_none() {
-            return Err(Error::Forbidden(
-                "This song was not added by the user who wants to remove it.".to_string(),
-            ));
+            // Return error if the song does not belong to the user
+            if song_user.is_none() {
+                return Err(Error::Forbidden(
+                    "This song was not added by the user who wants to remove it.".to_string(),
+                ));
+            }
         }
+        _ => {}
     }
 
-    // Proceed to delete the song
-    let res = sqlx::query!("DELETE FROM songs WHERE id=$1;", song_id)
-        .execute(&mut **transaction)
-        .await?;
+    // Proceed to delete the song, only a queued one of this jam
+    let res = sqlx::query!(
+        "DELETE FROM songs WHERE id = $1 AND status <> 'playing' AND user_id IN (SELECT id FROM users WHERE jam_id = $2);",
+        song_id,
+        id.jam_id()
+    )
+    .execute(&mut **transaction)
+    .await?;
     if res.rows_affected() < 1 {
         return Err(Error::DoesNotExist(format!(
             "could not delete song, no song found with id: {}",
@@ -41,26 +53,175 @@ pub async fn remove_song<'e>(
         )));
     }
 
-    Ok(real_time::Changed::new().songs())
+    // the song may still have been waiting for approval
+    Ok(real_time::Changed::new().songs().pending())
 }
 
 pub async fn get_top_song<'e>(
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
     jam_id: String,
+    strategy: QueueStrategy,
+    fairness: bool,
 ) -> Result<Option<Song>, Error> {
-    let id = Id {
-        id: IdType::General,
-        jam_id,
-    };
+    let id = Id::new(IdType::General, jam_id);
 
     let songs = get_songs(transaction, &id).await?;
     if songs.is_empty() {
         return Ok(None);
     }
 
-    let mut songs = songs.into_iter().max_set_by_key(|s| s.votes.votes);
-    songs.shuffle(&mut thread_rng());
-    Ok(songs.into_iter().next())
+    // the host pinned these to be played next, the songs are already sorted by votes
+    if let Some(song) = songs.iter().find(|s| s.pinned) {
+        return Ok(Some(song.clone()));
+    }
+
+    let songs = if fairness {
+        skip_recently_played_users(transaction, id.jam_id(), songs).await?
+    } else {
+        songs
+    };
+
+    let song = match strategy {
+        QueueStrategy::MostVotes => {
+            let mut songs = songs.into_iter().max_set_by_key(|s| s.votes.votes);
+            songs.shuffle(&mut thread_rng());
+            songs.into_iter().next()
+        }
+        QueueStrategy::RoundRobin => {
+            let added_at = get_added_at(transaction, id.jam_id()).await?;
+            let last_user_id =
+                sqlx::query!("SELECT last_user_id FROM jams WHERE id=$1", id.jam_id())
+                    .fetch_one(&mut **transaction)
+                    .await?
+                    .last_user_id;
+
+            let joined_at = sqlx::query!(
+                "SELECT id, joined_at FROM users WHERE jam_id=$1",
+                id.jam_id()
+            )
+            .fetch_all(&mut **transaction)
+            .await?
+            .into_iter()
+            .map(|user| (user.id, user.joined_at))
+            .collect::<HashMap<String, i64>>();
+            // the ids only break ties, the users who were in a jam before the column was added all joined at once
+            let turn = |user_id: &String| {
+                (
+                    joined_at.get(user_id).copied().unwrap_or(i64::MAX),
+                    user_id.clone(),
+                )
+            };
+
+            // the users take turns in the order they joined, starting after the one who was played last
+            let users = songs
+                .iter()
+                .filter_map(|s| s.user_id.clone())
+                .unique()
+                .sorted_by_key(turn)
+                .collect::<Vec<String>>();
+            let next_user = match last_user_id {
+                Some(last_user_id) => {
+                    let last_turn = turn(&last_user_id);
+                    users
+                        .iter()
+                        .find(|user_id| turn(user_id) > last_turn)
+                        .or(users.first())
+                }
+                None => users.first(),
+            }
+            .cloned();
+
+            songs
+                .into_iter()
+                .filter(|s| s.user_id == next_user)
+                .max_by_key(|s| (s.votes.votes, Reverse(added_at_of(&added_at, s))))
+        }
+        QueueStrategy::FifoWithVoteBoost => {
+            let added_at = get_added_at(transaction, id.jam_id()).await?;
+            songs
+                .into_iter()
+                .sorted_by_key(|s| added_at_of(&added_at, s))
+                .enumerate()
+                .min_by_key(|(place, s)| *place as i64 - s.votes.votes as i64 * VOTE_BOOST)
+                .map(|(_, s)| s)
+        }
+        QueueStrategy::WeightedRandom => songs
+            .choose_weighted(&mut thread_rng(), |s| s.votes.votes.max(0) + 1)
+            .ok()
+            .cloned(),
+    };
+
+    Ok(song)
+}
+
+/// leaves out the songs of the users who were played most recently,
+/// at least one user with songs in the queue is always kept
+async fn skip_recently_played_users<'e>(
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    jam_id: &str,
+    songs: Vec<Song>,
+) -> Result<Vec<Song>, sqlx::Error> {
+    let last_played_at = sqlx::query!(
+        "SELECT id, last_played_at FROM users WHERE jam_id=$1",
+        jam_id
+    )
+    .fetch_all(&mut **transaction)
+    .await?
+    .into_iter()
+    .filter_map(|user| Some((user.id, user.last_played_at?)))
+    .collect::<HashMap<String, i64>>();
+
+    let users_in_queue = songs
+        .iter()
+        .filter_map(|s| s.user_id.clone())
+        .unique()
+        .collect::<Vec<String>>();
+    let cooldown = FAIRNESS_COOLDOWN.min(users_in_queue.len().saturating_sub(1));
+
+    let skipped_users = users_in_queue
+        .into_iter()
+        .filter_map(|user_id| {
+            let played_at = last_played_at.get(&user_id).copied()?;
+            Some((user_id, played_at))
+        })
+        .sorted_by_key(|(_, played_at)| Reverse(*played_at))
+        .take(cooldown)
+        .map(|(user_id, _)| user_id)
+        .collect::<Vec<String>>();
+
+    Ok(songs
+        .into_iter()
+        .filter(|s| {
+            s.user_id
+                .as_ref()
+                .map(|user_id| !skipped_users.contains(user_id))
+                .unwrap_or(true)
+        })
+        .collect())
+}
+
+/// when the songs of the jam were added, in milliseconds, keyed by the song id
+async fn get_added_at<'e>(
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    jam_id: &str,
+) -> Result<HashMap<String, i64>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT s.id, s.added_at FROM songs s JOIN users u ON s.user_id = u.id WHERE u.jam_id = $1;",
+        jam_id
+    )
+    .fetch_all(&mut **transaction)
+    .await?
+    .into_iter()
+    .map(|song| (song.id, song.added_at))
+    .collect())
+}
+
+fn added_at_of(added_at: &HashMap<String, i64>, song: &Song) -> i64 {
+    song.id
+        .as_ref()
+        .and_then(|id| added_at.get(id))
+        .copied()
+        .unwrap_or(i64::MAX)
 }
 
 pub async fn get_songs<'e>(
@@ -77,17 +238,19 @@ pub async fn get_songs<'e>(
         pub votes: Option<i64>,
         pub artists: Option<Vec<String>>,
         pub image_url: String,
+        pub explicit: bool,
+        pub pinned: bool,
     }
 
     let vec = sqlx::query_as!(
         SongDb,
-        "SELECT s.id, s.spotify_id ,s.artists, s.image_url, s.user_id, s.name, s.album, s.duration, COUNT(v.id) AS votes
+        "SELECT s.id, s.spotify_id ,s.artists, s.image_url, s.user_id, s.name, s.album, s.duration, s.explicit, s.pinned, COALESCE(SUM(v.value), 0) AS votes
         FROM songs s
         JOIN users u ON s.user_id = u.id
         LEFT JOIN votes v ON s.id = v.song_id
-        WHERE u.jam_id = $1
+        WHERE u.jam_id = $1 AND s.status = 'approved'
         GROUP BY s.id
-        ORDER BY votes DESC, s.id DESC;",
+        ORDER BY s.pinned DESC, votes DESC, s.id DESC;",
         &id.jam_id()
     )
     .fetch_all(&mut **transaction)
@@ -100,26 +263,26 @@ pub async fn get_songs<'e>(
                 (
                     song.id.clone(),
                     Vote {
-                        votes: song.votes.unwrap_or(0) as u64,
+                        votes: song.votes.unwrap_or(0),
                         have_you_voted: None,
                     },
                 )
             })
             .collect(),
         IdType::User(id) => {
-            let votes = sqlx::query!("SELECT song_id FROM votes WHERE user_id=$1", id)
+            let votes = sqlx::query!("SELECT song_id, value FROM votes WHERE user_id=$1", id)
                 .fetch_all(&mut **transaction)
                 .await?
                 .into_iter()
-                .map(|vote| vote.song_id)
-                .collect::<Vec<String>>();
+                .map(|vote| (vote.song_id, YourVote::from_value(vote.value)))
+                .collect::<HashMap<String, YourVote>>();
             vec.iter()
                 .map(|song| {
                     (
                         song.id.clone(),
                         Vote {
-                            votes: song.votes.unwrap_or(0) as u64,
-                            have_you_voted: Some(votes.contains(&song.id)),
+                            votes: song.votes.unwrap_or(0),
+                            have_you_voted: Some(votes.get(&song.id).copied().unwrap_or_default()),
                         },
                     )
                 })
@@ -129,13 +292,12 @@ pub async fn get_songs<'e>(
 
     let songs = vec
         .into_iter()
-        .filter(|song| song.user_id.trim() != id.jam_id())
         .map(|song| Song {
             votes: votes.get(&song.id).cloned().unwrap_or(Vote {
                 votes: 0,
                 have_you_voted: match id.id {
                     IdType::Host(_) | IdType::General => None,
-                    IdType::User(_) => Some(false),
+                    IdType::User(_) => Some(YourVote::NotVoted),
                 },
             }),
             id: Some(song.id),
@@ -160,24 +322,25 @@ pub async fn get_songs<'e>(
             album: song.album,
             duration: song.duration as u32,
             image_url: song.image_url,
+            explicit: song.explicit,
+            pinned: song.pinned,
         })
         .collect::<Vec<_>>();
 
     Ok(songs)
 }
 
+/// the host adds songs with the jam id as the user id, those don't count towards a limit and skip moderation
 pub async fn add_song<'e>(
     spotify_song_id: &str,
     user_id: &str,
     jam_id: &str,
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
+    provider: &impl MusicProvider,
 ) -> Result<real_time::Changed, Error> {
-    use rspotify::prelude::*;
-    use rspotify::AuthCodeSpotify;
     println!("adding song, with id: {}", spotify_song_id);
 
-    let does_song_exist = sqlx::query!("SELECT EXISTS(SELECT 1 FROM songs WHERE spotify_id=$1 AND user_id IN (SELECT id FROM users WHERE jam_id=$2) AND user_id <> $2)", spotify_song_id, jam_id)
+    let does_song_exist = sqlx::query!("SELECT EXISTS(SELECT 1 FROM songs WHERE spotify_id=$1 AND user_id IN (SELECT id FROM users WHERE jam_id=$2) AND status <> 'playing')", spotify_song_id, jam_id)
         .fetch_one(&mut **transaction)
         .await?;
 
@@ -185,46 +348,234 @@ pub async fn add_song<'e>(
         return Err(Error::SongAlreadyInJam);
     }
 
-    let amount_of_songs = sqlx::query!("SELECT COUNT(*) FROM songs WHERE user_id=$1", user_id)
-        .fetch_one(&mut **transaction)
-        .await?
-        .count
-        .unwrap_or(0);
+    let is_host = user_id == jam_id;
 
-    let max_amount_of_songs = sqlx::query!("SELECT max_song_count FROM jams WHERE id=$1", jam_id)
-        .fetch_one(&mut **transaction)
-        .await?
-        .max_song_count;
+    if !is_host {
+        let amount_of_songs = sqlx::query!("SELECT COUNT(*) FROM songs WHERE user_id=$1", user_id)
+            .fetch_one(&mut **transaction)
+            .await?
+            .count
+            .unwrap_or(0);
+
+        let max_amount_of_songs =
+            sqlx::query!("SELECT max_song_count FROM jams WHERE id=$1", jam_id)
+                .fetch_one(&mut **transaction)
+                .await?
+                .max_song_count;
+
+        if amount_of_songs as i16 >= max_amount_of_songs {
+            return Err(Error::UserHasTooTheMaxSongAmount);
+        }
+    }
+
+    let song = provider
+        .get_song(spotify_song_id, jam_id, transaction)
+        .await?;
+
+    let settings = get_settings(jam_id, &mut **transaction).await?;
 
-    if amount_of_songs as i16 >= max_amount_of_songs {
-        return Err(Error::UserHasTooTheMaxSongAmount);
+    if song.explicit && !settings.allow_explicit {
+        return Err(Error::ExplicitSongNotAllowed);
     }
 
-    let token = get_access_token(transaction, jam_id, credentials).await?;
-    let client = AuthCodeSpotify::from_token(token);
-    let track_id = TrackId::from_id(spotify_song_id)?;
-    let song = client.track(track_id, None).await?;
+    let genres = if settings.allowed_genres.is_empty() {
+        Vec::new()
+    } else {
+        provider
+            .get_genres(&[song.spotify_id.clone()], jam_id, transaction)
+            .await?
+            .remove(&song.spotify_id)
+            .unwrap_or_default()
+    };
+    settings
+        .check_song(&song, &genres)
+        .map_err(Error::InvalidRequest)?;
+
+    let moderated = settings.moderated && !is_host;
+    let status = match moderated {
+        true => "pending",
+        false => "approved",
+    };
 
     sqlx::query!(
         "INSERT INTO songs 
-            (id, user_id, name, album, duration, image_url, artists, spotify_id) 
+            (id, user_id, name, album, duration, image_url, artists, spotify_id, explicit, status) 
         VALUES 
-            ($1, $2, $3, $4, $5, $6, $7, $8);",
+            ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);",
         cuid2::create_id(),
         user_id,
         song.name,
-        song.album.name,
-        song.duration.num_milliseconds() as i32,
-        song.album.images[0].url,
-        &song
-            .artists
-            .into_iter()
-            .map(|a| a.name)
-            .collect::<Vec<String>>(),
+        song.album,
+        song.duration as i32,
+        song.image_url,
+        &song.artists,
         spotify_song_id,
+        song.explicit,
+        status,
     )
     .execute(&mut **transaction)
     .await?;
 
+    if moderated {
+        return Ok(real_time::Changed::new().pending());
+    }
+
     Ok(real_time::Changed::new().songs())
 }
+
+/// the songs that wait for the host to approve them, the oldest first
+pub async fn get_pending_songs<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<Song>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT s.id, s.spotify_id, s.user_id, s.name, s.album, s.duration, s.artists, s.image_url, s.explicit
+        FROM songs s
+        JOIN users u ON s.user_id = u.id
+        WHERE u.jam_id = $1 AND s.status = 'pending'
+        ORDER BY s.added_at ASC;",
+        jam_id
+    )
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .map(|song| Song {
+        id: Some(song.id),
+        spotify_id: song.spotify_id,
+        user_id: Some(song.user_id),
+        name: song.name,
+        artists: song.artists,
+        album: song.album,
+        duration: song.duration as u32,
+        image_url: song.image_url,
+        votes: Vote {
+            votes: 0,
+            have_you_voted: None,
+        },
+        explicit: song.explicit,
+        pinned: false,
+    })
+    .collect())
+}
+
+pub async fn approve_song<'e>(
+    song_id: &str,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "UPDATE songs SET status = 'approved' WHERE id = $1 AND status = 'pending' AND user_id IN (SELECT id FROM users WHERE jam_id = $2)",
+        song_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "could not approve song, no pending song found with id: {}",
+            song_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().pending().songs().votes())
+}
+
+/// a pinned song is played next, before the songs with more votes
+pub async fn pin_song<'e>(
+    song_id: &str,
+    jam_id: &str,
+    pinned: bool,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "UPDATE songs SET pinned = $1 WHERE id = $2 AND status = 'approved' AND user_id IN (SELECT id FROM users WHERE jam_id = $3)",
+        pinned,
+        song_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "could not pin song, no song found in the queue with id: {}",
+            song_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().songs())
+}
+
+/// the rejected song is removed, so the user can add another song instead
+pub async fn reject_song<'e>(
+    song_id: &str,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "DELETE FROM songs WHERE id = $1 AND status = 'pending' AND user_id IN (SELECT id FROM users WHERE jam_id = $2)",
+        song_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "could not reject song, no pending song found with id: {}",
+            song_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().pending())
+}
+
+/// searches with the jam's music provider, leaving out the songs that are already in the jam,
+/// and the ones that the rules of the jam don't allow
+pub async fn search<'e>(
+    query: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    jam_id: &str,
+    provider: &impl MusicProvider,
+) -> Result<Vec<Song>, Error> {
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let songs = provider.search(query, jam_id, transaction).await?;
+
+    let songs_in_jam = sqlx::query!(
+        "SELECT spotify_id FROM songs WHERE user_id IN (SELECT id FROM users WHERE jam_id=$1);",
+        jam_id
+    )
+    .fetch_all(&mut **transaction)
+    .await?
+    .into_iter()
+    .map(|song| song.spotify_id)
+    .collect::<Vec<String>>();
+
+    let settings = get_settings(jam_id, &mut **transaction).await?;
+
+    let mut genres = if settings.allowed_genres.is_empty() {
+        HashMap::new()
+    } else {
+        let song_ids = songs
+            .iter()
+            .map(|song| song.spotify_id.clone())
+            .collect::<Vec<_>>();
+        provider.get_genres(&song_ids, jam_id, transaction).await?
+    };
+
+    let songs = songs
+        .into_iter()
+        .filter(|song| !songs_in_jam.contains(&song.spotify_id))
+        .filter(|song| settings.allow_explicit || !song.explicit)
+        .filter(|song| {
+            let genres = genres.remove(&song.spotify_id).unwrap_or_default();
+            settings.check_song(song, &genres).is_ok()
+        })
+        .collect::<Vec<Song>>();
+
+    Ok(songs)
+}
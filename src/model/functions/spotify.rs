@@ -1,31 +1,50 @@
-use std::result;
-
+use super::MusicProvider;
 use crate::model::types::*;
-use leptos::logging::*;
+use itertools::Itertools;
 use rand::{Rng, SeedableRng};
 use rspotify::{
     clients::{BaseClient, OAuthClient},
-    model::{AdditionalType, Id, PlayContextId, PlayableId, SearchResult, TrackId},
+    model::{ArtistId, Id, PlayableId, SearchResult, TrackId},
     AuthCodeSpotify,
 };
+use std::collections::HashMap;
 
-use super::song;
+/// what the host lets the app do with their spotify account
+pub const SPOTIFY_SCOPES: &str = "user-read-playback-state user-modify-playback-state user-read-currently-playing streaming user-read-private user-read-email user-read-recently-played user-top-read playlist-modify-private";
+/// the hosts who connected spotify before playlists could be saved don't have this one
+const PLAYLIST_SCOPE: &str = "playlist-modify-private";
 
-pub async fn switch_playback_to_device<'e>(
-    device_id: &str,
-    jam_id: &str,
-    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
-) -> Result<(), Error> {
-    let token = get_access_token(transaction, jam_id, credentials).await?;
-    let client = AuthCodeSpotify::from_token(token);
-    if let Err(e) = client.transfer_playback(device_id, Some(true)).await {
-        return Err(Error::Spotify(format!(
-            "could not switch playback to device: {}",
-            e
-        )));
-    };
-    Ok(())
+/// where the host is sent to connect spotify, the state is the signed host id, see `create_host`
+pub fn spotify_authorize_url(
+    credentials: &SpotifyCredentials,
+    site_url: &str,
+    state: &str,
+) -> String {
+    format!(
+        "https://accounts.spotify.com/authorize?response_type=code&client_id={}&scope={}&redirect_uri={}/create-host&state={}&show_dialog=true",
+        credentials.id, SPOTIFY_SCOPES, site_url, state
+    )
+}
+
+/// plays the music through the host's spotify account, the access token of the host is stored in the db
+#[derive(Debug, Clone)]
+pub struct SpotifyProvider {
+    pub credentials: SpotifyCredentials,
+}
+
+impl SpotifyProvider {
+    pub fn new(credentials: SpotifyCredentials) -> Self {
+        Self { credentials }
+    }
+
+    async fn client<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<AuthCodeSpotify, Error> {
+        let token = get_access_token(transaction, jam_id, self.credentials.clone()).await?;
+        Ok(AuthCodeSpotify::from_token(token))
+    }
 }
 
 #[allow(dead_code)]
@@ -132,114 +151,249 @@ pub async fn get_access_token<'e>(
     Ok(new_token)
 }
 
-pub async fn get_song_recommendation<'e>(
-    credentials: SpotifyCredentials,
+/// creates a private playlist on the host's account with the songs that were played in the jam,
+/// returns the url of the playlist, or `Error::MissingSpotifyScope` if the host has to connect spotify again
+pub async fn export_history_to_playlist<'e>(
     jam_id: &str,
     transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-) -> Result<Song, Error> {
+    credentials: SpotifyCredentials,
+) -> Result<String, Error> {
+    let history = super::get_history(jam_id, None, &mut **transaction).await?;
+    if history.is_empty() {
+        return Err(Error::DoesNotExist(format!(
+            "no songs were played in jam with id: {}, could not create playlist",
+            jam_id
+        )));
+    }
+    let jam = super::get_jam(jam_id, &mut **transaction).await?;
+
     let token = get_access_token(transaction, jam_id, credentials).await?;
+    if !token.scopes.contains(PLAYLIST_SCOPE) {
+        return Err(Error::MissingSpotifyScope(
+            "the app is not allowed to create playlists on your account".to_string(),
+        ));
+    }
     let client = AuthCodeSpotify::from_token(token);
-    let mut tracks = client
-        .current_user_top_tracks_manual(
-            Some(rspotify::model::TimeRange::MediumTerm),
-            Some(20),
-            Some(rand::prelude::StdRng::from_entropy().gen_range(0..19)),
+    let user = client.current_user().await?;
+    let playlist = client
+        .user_playlist_create(
+            user.id,
+            &jam.name,
+            Some(false),
+            None,
+            Some("The songs played at the jam"),
         )
         .await?;
-    let track = tracks.items.remove(0);
-    Ok(track_to_song(track))
+
+    // the history starts with the most recent song, the playlist should be in the order they were played
+    let track_ids = history
+        .iter()
+        .rev()
+        .map(|played| TrackId::from_id(played.song.spotify_id.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // spotify only accepts 100 songs per request
+    for chunk in track_ids.chunks(100) {
+        client
+            .playlist_add_items(
+                playlist.id.clone(),
+                chunk.iter().cloned().map(PlayableId::Track),
+                None,
+            )
+            .await?;
+    }
+
+    Ok(playlist
+        .external_urls
+        .get("spotify")
+        .cloned()
+        .unwrap_or_default())
 }
 
-pub async fn search<'e>(
-    query: &str,
-    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    jam_id: &str,
-    credentials: SpotifyCredentials,
-) -> Result<Vec<Song>, Error> {
-    use rspotify::prelude::*;
-    use rspotify::AuthCodeSpotify;
+impl MusicProvider for SpotifyProvider {
+    async fn search<'e>(
+        &self,
+        query: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Vec<Song>, Error> {
+        let client = self.client(jam_id, transaction).await?;
+        let result = client
+            .search(
+                query,
+                rspotify::model::SearchType::Track,
+                None,
+                None,
+                Some(30),
+                Some(0),
+            )
+            .await?;
+        let songs = if let SearchResult::Tracks(tracks) = result {
+            tracks
+        } else {
+            return Err(Error::Spotify(
+                "Error in search, returned other then tracks".to_string(),
+            ));
+        };
 
-    if query.is_empty() {
-        return Ok(vec![]);
+        Ok(songs.items.into_iter().map(track_to_song).collect())
     }
 
-    let token = get_access_token(transaction, jam_id, credentials).await?;
-    let client = AuthCodeSpotify::from_token(token);
-    let result = client
-        .search(
-            query,
-            rspotify::model::SearchType::Track,
-            None,
-            None,
-            Some(30),
-            Some(0),
-        )
-        .await?;
-    let songs = if let SearchResult::Tracks(tracks) = result {
-        tracks
-    } else {
-        return Err(Error::Spotify(
-            "Error in search, returned other then tracks".to_string(),
-        ));
-    };
+    async fn get_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        let client = self.client(jam_id, transaction).await?;
+        let track_id = TrackId::from_id(song_id)?;
+        let track = client.track(track_id, None).await?;
+        Ok(track_to_song(track))
+    }
 
-    let songs_in_jam = sqlx::query!(
-        "SELECT id FROM songs WHERE user_id IN (SELECT id FROM users WHERE jam_id=$1);",
-        jam_id
-    )
-    .fetch_all(&mut **transaction)
-    .await?
-    .into_iter()
-    .map(|song| song.id)
-    .collect::<Vec<String>>();
+    async fn play_song<'e>(
+        &self,
+        song_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        let client = self.client(jam_id, transaction).await?;
+        let song_id = match TrackId::from_id(song_id) {
+            Ok(id) => id,
+            Err(e) => {
+                return Err(Error::Spotify(format!(
+                    "could not play song, song id is not correct: {}",
+                    e
+                )))
+            }
+        };
+        if let Err(e) = client
+            .start_uris_playback(vec![PlayableId::Track(song_id)], None, None, None)
+            .await
+        {
+            return Err(Error::Spotify(format!("could not play song: {}", e)));
+        };
+        Ok(())
+    }
 
-    let songs = songs
-        .items
-        .into_iter()
-        .filter(|song| !songs_in_jam.contains(&song.id.as_ref().unwrap().id().to_owned()))
-        .collect::<Vec<_>>();
+    async fn get_current_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        let client = self.client(jam_id, transaction).await?;
+        let current = client.current_playing(None, None::<Vec<_>>).await?;
+        let current = match current {
+            Some(song) => song,
+            None => return Ok(None),
+        };
+        let current = match current.item {
+            Some(rspotify::model::PlayableItem::Track(track)) => track,
+            _ => return Ok(None),
+        };
+        Ok(Some(track_to_song(current)))
+    }
 
-    let songs = songs.into_iter().map(track_to_song).collect::<Vec<Song>>();
+    async fn get_next_song_from_player<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Option<Song>, Error> {
+        let client = self.client(jam_id, transaction).await?;
+        let next = client.current_user_queue().await?.queue.into_iter().next();
+        let next = match next {
+            Some(song) => song,
+            None => return Ok(None),
+        };
+        let next = match next {
+            rspotify::model::PlayableItem::Track(track) => track,
+            _ => return Ok(None),
+        };
+        Ok(Some(track_to_song(next)))
+    }
 
-    Ok(songs)
-}
+    async fn get_song_recommendation<'e>(
+        &self,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<Song, Error> {
+        let client = self.client(jam_id, transaction).await?;
+        let mut tracks = client
+            .current_user_top_tracks_manual(
+                Some(rspotify::model::TimeRange::MediumTerm),
+                Some(20),
+                Some(rand::prelude::StdRng::from_entropy().gen_range(0..19)),
+            )
+            .await?;
+        if tracks.items.is_empty() {
+            return Err(Error::Spotify(
+                "the host has no top tracks to recommend from".to_string(),
+            ));
+        }
+        let track = tracks.items.remove(0);
+        Ok(track_to_song(track))
+    }
 
-pub async fn get_current_song_from_player<'e>(
-    jam_id: &str,
-    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
-) -> Result<Option<Song>, Error> {
-    let token = get_access_token(transaction, jam_id, credentials).await?;
-    let client = AuthCodeSpotify::from_token(token);
-    let current = client.current_playing(None, None::<Vec<_>>).await?;
-    let current = match current {
-        Some(song) => song,
-        None => return Ok(None),
-    };
-    let current = match current.item {
-        Some(rspotify::model::PlayableItem::Track(track)) => track,
-        _ => return Ok(None),
-    };
-    Ok(Some(track_to_song(current)))
-}
+    /// spotify only has genres for artists, so the genres of a song are the genres of its artists
+    async fn get_genres<'e>(
+        &self,
+        song_ids: &[String],
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<HashMap<String, Vec<String>>, Error> {
+        let client = self.client(jam_id, transaction).await?;
 
-pub async fn get_next_song_from_player<'e>(
-    jam_id: &str,
-    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
-) -> Result<Option<Song>, Error> {
-    let token = get_access_token(transaction, jam_id, credentials).await?;
-    let client = AuthCodeSpotify::from_token(token);
-    let current = client.current_user_queue().await?.queue.into_iter().next();
-    let current = match current {
-        Some(song) => song,
-        None => return Ok(None),
-    };
-    let current = match current {
-        rspotify::model::PlayableItem::Track(track) => track,
-        _ => return Ok(None),
-    };
-    Ok(Some(track_to_song(current)))
+        let mut tracks = Vec::new();
+        for chunk in song_ids.chunks(50) {
+            let ids = chunk
+                .iter()
+                .map(|id| TrackId::from_id(id.as_str()))
+                .collect::<Result<Vec<_>, _>>()?;
+            tracks.extend(client.tracks(ids, None).await?);
+        }
+
+        let artist_ids = tracks
+            .iter()
+            .flat_map(|track| track.artists.iter().filter_map(|a| a.id.clone()))
+            .unique()
+            .collect::<Vec<ArtistId>>();
+        let mut artist_genres = HashMap::new();
+        for chunk in artist_ids.chunks(50) {
+            for artist in client.artists(chunk.to_vec()).await? {
+                artist_genres.insert(artist.id, artist.genres);
+            }
+        }
+
+        Ok(tracks
+            .into_iter()
+            .filter_map(|track| {
+                let genres = track
+                    .artists
+                    .iter()
+                    .filter_map(|a| a.id.as_ref().and_then(|id| artist_genres.get(id)))
+                    .flatten()
+                    .unique()
+                    .cloned()
+                    .collect();
+                Some((track.id?.id().to_string(), genres))
+            })
+            .collect())
+    }
+
+    async fn switch_playback_to_device<'e>(
+        &self,
+        device_id: &str,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        let client = self.client(jam_id, transaction).await?;
+        if let Err(e) = client.transfer_playback(device_id, Some(true)).await {
+            return Err(Error::Spotify(format!(
+                "could not switch playback to device: {}",
+                e
+            )));
+        };
+        Ok(())
+    }
 }
 
 pub fn track_to_song(track: rspotify::model::FullTrack) -> Song {
@@ -253,7 +407,7 @@ pub fn track_to_song(track: rspotify::model::FullTrack) -> Song {
         name: track.name,
         artists: track.artists.into_iter().map(|a| a.name).collect(),
         album: track.album.name,
-        duration: track.duration.num_seconds() as u32,
+        duration: track.duration.num_milliseconds() as u32,
         image_url: track
             .album
             .images
@@ -265,31 +419,7 @@ pub fn track_to_song(track: rspotify::model::FullTrack) -> Song {
             votes: 0,
             have_you_voted: None,
         },
+        explicit: track.explicit,
+        pinned: false,
     }
 }
-
-pub async fn play_song<'e>(
-    spotify_song_id: &str,
-    jam_id: &str,
-    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
-) -> Result<(), Error> {
-    let token = get_access_token(transaction, jam_id, credentials).await?;
-    let client = AuthCodeSpotify::from_token(token);
-    let song_id = match TrackId::from_id(spotify_song_id) {
-        Ok(id) => id,
-        Err(e) => {
-            return Err(Error::Spotify(format!(
-                "could not play song, song id is not correct: {}",
-                e
-            )))
-        }
-    };
-    if let Err(e) = client
-        .start_uris_playback(vec![PlayableId::Track(song_id)], None, None, None)
-        .await
-    {
-        return Err(Error::Spotify(format!("could not play song: {}", e)));
-    };
-    Ok(())
-}
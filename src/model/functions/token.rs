@@ -0,0 +1,53 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(data: &str, secret_key: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret_key.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(data.as_bytes());
+    mac
+}
+
+/// signs the data with the secret key of the server, so it can be given to a client
+/// and trusted when it comes back, the data is readable by anyone.
+/// The purpose is signed with it, like "invite", so a token made for one thing is never taken for another
+pub fn sign_token(purpose: &str, data: &str, secret_key: &str) -> String {
+    let data = format!("{}:{}", purpose, data);
+    let signature = mac(&data, secret_key).finalize().into_bytes();
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(data),
+        URL_SAFE_NO_PAD.encode(signature)
+    )
+}
+
+/// returns the data if the token was signed with the secret key for the purpose
+pub fn verify_token(token: &str, purpose: &str, secret_key: &str) -> Option<String> {
+    let (data, signature) = token.split_once('.')?;
+    let data = String::from_utf8(URL_SAFE_NO_PAD.decode(data).ok()?).ok()?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    mac(&data, secret_key).verify_slice(&signature).ok()?;
+    data.strip_prefix(purpose)?
+        .strip_prefix(':')
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_token_is_only_valid_for_its_purpose() {
+        let token = sign_token("invite", "id", "secret");
+        assert_eq!(
+            verify_token(&token, "invite", "secret").as_deref(),
+            Some("id")
+        );
+        assert_eq!(verify_token(&token, "session", "secret"), None);
+        assert_eq!(verify_token(&token, "inv", "secret"), None);
+        assert_eq!(verify_token(&token, "invite", "another secret"), None);
+    }
+}
@@ -2,20 +2,37 @@ use sqlx::Transaction;
 
 use crate::model::types::*;
 
+/// permissions that are not known anymore are left out
+fn parse_permissions(permissions: Vec<String>) -> Vec<Permission> {
+    permissions
+        .iter()
+        .filter_map(|permission| permission.parse().ok())
+        .collect()
+}
+
 ///only the jam is is used from the id
 pub async fn get_users<'e>(
     executor: impl sqlx::PgExecutor<'e>,
     id: &Id,
 ) -> Result<Vec<User>, sqlx::Error> {
-    sqlx::query_as!(User, "SELECT * FROM users WHERE jam_id=$1", id.jam_id())
-        .fetch_all(executor)
-        .await
-        .map(|users| {
-            users
-                .into_iter()
-                .filter(|user| user.id.trim() != id.jam_id())
-                .collect()
-        })
+    sqlx::query!(
+        "SELECT id, jam_id, name, permissions FROM users WHERE jam_id=$1 AND status = 'admitted'",
+        id.jam_id()
+    )
+    .fetch_all(executor)
+    .await
+    .map(|users| {
+        users
+            .into_iter()
+            .filter(|user| user.id.trim() != id.jam_id())
+            .map(|user| User {
+                id: user.id,
+                jam_id: user.jam_id,
+                name: user.name,
+                permissions: parse_permissions(user.permissions),
+            })
+            .collect()
+    })
 }
 
 pub async fn check_id_type<'e>(
@@ -32,25 +49,16 @@ pub async fn check_id_type<'e>(
             .fetch_one(&mut **transaction)
             .await?
             .id;
-        return Ok(Id {
-            id: IdType::Host(id.to_string()),
-            jam_id,
-        });
+        return Ok(Id::new(IdType::Host(id.to_string()), jam_id));
     }
 
-    let user_check = sqlx::query!("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)", id)
-        .fetch_one(&mut **transaction)
+    let user = sqlx::query!("SELECT jam_id, permissions FROM users WHERE id = $1", id)
+        .fetch_optional(&mut **transaction)
         .await?;
 
-    if user_check.exists.unwrap_or(false) {
-        let jam_id = sqlx::query!("SELECT jam_id FROM users WHERE id = $1", id)
-            .fetch_one(&mut **transaction)
-            .await?
-            .jam_id;
-        return Ok(Id {
-            id: IdType::User(id.to_string()),
-            jam_id,
-        });
+    if let Some(user) = user {
+        return Ok(Id::new(IdType::User(id.to_string()), user.jam_id)
+            .with_permissions(parse_permissions(user.permissions)));
     }
 
     Err(Error::DoesNotExist(format!(
@@ -59,17 +67,23 @@ pub async fn check_id_type<'e>(
     )))
 }
 
+/// only the users of the jam can be kicked, the user of the jam itself holds the songs of the host
 pub async fn kick_user<'e>(
     user_id: &str,
+    jam_id: &str,
     executor: impl sqlx::PgExecutor<'e>,
 ) -> Result<real_time::Changed, Error> {
-    let res = sqlx::query!("DELETE FROM users WHERE id=$1;", user_id)
-        .execute(executor)
-        .await?;
+    let res = sqlx::query!(
+        "DELETE FROM users WHERE id = $1 AND jam_id = $2 AND id <> jam_id;",
+        user_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
 
     if res.rows_affected() == 0 {
         return Err(Error::DoesNotExist(format!(
-            "user with id: {} does not exist, could not kick",
+            "user with id: {} does not exist in this jam, could not kick",
             user_id
         )));
     }
@@ -77,20 +91,286 @@ pub async fn kick_user<'e>(
     Ok(real_time::Changed::new().users())
 }
 
-///returns id of the created user
+/// the users in the waiting room, the ones who asked first come first
+pub async fn get_join_requests<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query!(
+        "SELECT id, jam_id, name, permissions FROM users WHERE jam_id = $1 AND status = 'waiting' ORDER BY joined_at ASC",
+        jam_id
+    )
+    .fetch_all(executor)
+    .await
+    .map(|users| {
+        users
+            .into_iter()
+            .map(|user| User {
+                id: user.id,
+                jam_id: user.jam_id,
+                name: user.name,
+                permissions: parse_permissions(user.permissions),
+            })
+            .collect()
+    })
+}
+
+/// the permissions the host gave the user, they are checked again on every request
+/// because the host can change them while the user is connected
+pub async fn get_permissions<'e>(
+    user_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<Permission>, Error> {
+    let permissions = sqlx::query!("SELECT permissions FROM users WHERE id = $1", user_id)
+        .fetch_optional(executor)
+        .await?
+        .map(|user| parse_permissions(user.permissions))
+        .unwrap_or_default();
+    Ok(permissions)
+}
+
+/// the host can change the permissions of a user at any time, so they are loaded again before they are used
+pub async fn refresh_permissions<'e>(
+    id: Id,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Id, Error> {
+    match &id.id {
+        IdType::User(user_id) => {
+            let permissions = get_permissions(user_id, executor).await?;
+            Ok(id.with_permissions(permissions))
+        }
+        _ => Ok(id),
+    }
+}
+
+/// makes the user a co-host with the permissions, no permissions makes them a normal user again
+pub async fn set_permissions<'e>(
+    user_id: &str,
+    jam_id: &str,
+    permissions: &[Permission],
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let permissions = permissions
+        .iter()
+        .map(|permission| permission.as_str().to_string())
+        .collect::<Vec<String>>();
+
+    let res = sqlx::query!(
+        "UPDATE users SET permissions = $1 WHERE id = $2 AND jam_id = $3 AND id <> jam_id",
+        &permissions,
+        user_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "user with id: {} does not exist in this jam, could not set permissions",
+            user_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().users())
+}
+
+/// a user is gone once they are kicked, banned, leave, or are turned away from the waiting room
+pub async fn check_user_exists<'e>(
+    user_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<(), Error> {
+    let exists = sqlx::query!("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)", user_id)
+        .fetch_one(executor)
+        .await?
+        .exists
+        .unwrap_or(false);
+    if !exists {
+        return Err(Error::DoesNotExist(
+            "You are no longer a user of this jam".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn is_user_waiting<'e>(
+    user_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND status = 'waiting')",
+        user_id
+    )
+    .fetch_one(executor)
+    .await?
+    .exists
+    .unwrap_or(false))
+}
+
+/// lets the user out of the waiting room into the jam
+pub async fn admit_user<'e>(
+    user_id: &str,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "UPDATE users SET status = 'admitted' WHERE id = $1 AND jam_id = $2 AND status = 'waiting'",
+        user_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "user with id: {} is not in the waiting room, could not admit",
+            user_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().users().join_requests())
+}
+
+/// the user is removed, their socket sees that they are not in the jam and leaves
+pub async fn reject_user<'e>(
+    user_id: &str,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "DELETE FROM users WHERE id = $1 AND jam_id = $2 AND status = 'waiting'",
+        user_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "user with id: {} is not in the waiting room, could not reject",
+            user_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().users().join_requests())
+}
+
+/// kicks the user and keeps the device they joined from out of the jam
+pub async fn ban_user<'e>(
+    user_id: &str,
+    jam_id: &str,
+    transaction: &mut Transaction<'e, sqlx::Postgres>,
+) -> Result<real_time::Changed, Error> {
+    let user = sqlx::query!(
+        "SELECT name, device_id FROM users WHERE id = $1 AND jam_id = $2 AND id <> jam_id",
+        user_id,
+        jam_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    .ok_or_else(|| {
+        Error::DoesNotExist(format!(
+            "user with id: {} does not exist in this jam, could not ban",
+            user_id
+        ))
+    })?;
+
+    // users who joined before bans existed can't be recognized, they are only kicked
+    if let Some(device_id) = user.device_id {
+        sqlx::query!(
+            "INSERT INTO banned_users (id, jam_id, device_id, name, banned_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (jam_id, device_id) DO NOTHING",
+            cuid2::create_id(),
+            jam_id,
+            device_id,
+            user.name,
+            chrono::Utc::now().timestamp_millis()
+        )
+        .execute(&mut **transaction)
+        .await?;
+    }
+
+    let changed = kick_user(user_id, jam_id, &mut **transaction).await?;
+
+    Ok(changed.banned())
+}
+
+pub async fn unban_user<'e>(
+    ban_id: &str,
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<real_time::Changed, Error> {
+    let res = sqlx::query!(
+        "DELETE FROM banned_users WHERE id = $1 AND jam_id = $2",
+        ban_id,
+        jam_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::DoesNotExist(format!(
+            "ban with id: {} does not exist, could not unban",
+            ban_id
+        )));
+    }
+
+    Ok(real_time::Changed::new().banned())
+}
+
+/// the most recently banned first
+pub async fn get_banned_users<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<BannedUser>, sqlx::Error> {
+    sqlx::query_as!(
+        BannedUser,
+        "SELECT id, name, banned_at FROM banned_users WHERE jam_id = $1 ORDER BY banned_at DESC",
+        jam_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+///returns id of the created user, the device id is the token from the cookie of the device that joins,
+///the credentials are checked against the passcode of the jam and a used invite is counted
 pub async fn create_user<'e>(
     jam_id: &str,
     image_url: &str,
     name: &str,
-    executor: impl sqlx::PgExecutor<'e>,
+    device_id: &str,
+    credentials: &JoinCredentials,
+    secret_key: &str,
+    transaction: &mut Transaction<'e, sqlx::Postgres>,
     root: &str,
 ) -> Result<(String, real_time::Changed), Error> {
     use data_url::DataUrl;
 
     if name.is_empty() {
-        return Err(Error::InvalidRequest("name is empty".to_string()));
+        return Err(Error::InvalidRequest("name is empty".into()));
+    }
+
+    let is_banned = sqlx::query!(
+        "SELECT EXISTS(SELECT 1 FROM banned_users WHERE jam_id = $1 AND device_id = $2)",
+        jam_id.to_lowercase(),
+        device_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .exists
+    .unwrap_or(false);
+
+    if is_banned {
+        return Err(Error::Banned);
     }
 
+    let invite_id = super::check_join_access(jam_id, credentials, secret_key, transaction).await?;
+    // the invite is counted before the user is made, a used up one stops the join
+    let invited = match invite_id {
+        Some(invite_id) => Some(super::use_invite(&invite_id, &mut **transaction).await?),
+        None => None,
+    };
+
     let data_url = match DataUrl::process(image_url) {
         Ok(data_url) => data_url,
         Err(_) => return Err(Error::Decode("invalid data url".to_string())),
@@ -135,14 +415,34 @@ pub async fn create_user<'e>(
         }
     };
 
+    let join_approval = super::get_settings(jam_id, &mut **transaction)
+        .await?
+        .join_approval;
+    let status = match join_approval {
+        true => "waiting",
+        false => "admitted",
+    };
+
     sqlx::query!(
-        "INSERT INTO users(id, jam_id, name) VALUES ($1, $2, $3)",
+        "INSERT INTO users(id, jam_id, name, device_id, status, joined_at) VALUES ($1, $2, $3, $4, $5, $6)",
         user_id,
         jam_id.to_lowercase(),
         name,
+        device_id,
+        status,
+        chrono::Utc::now().timestamp_millis()
     )
-    .execute(executor)
+    .execute(&mut **transaction)
     .await?;
 
-    Ok((user_id, real_time::Changed::new().users()))
+    let mut changed = match join_approval {
+        true => real_time::Changed::new().join_requests(),
+        false => real_time::Changed::new().users(),
+    };
+
+    if let Some(invited) = invited {
+        changed = changed.merge_with_other(invited);
+    }
+
+    Ok((user_id, changed))
 }
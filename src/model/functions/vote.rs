@@ -1,20 +1,110 @@
-use crate::model::{get_current_song, types::*};
-use std::collections::{HashMap, HashSet};
+use crate::model::{get_current_song, get_settings, types::*};
+use std::collections::HashMap;
+
+/// a song is dropped from the queue once its score is this low, or minus half of the users if there are more of them
+const MIN_DROP_SCORE: i64 = 2;
 
 pub async fn add_vote<'e>(
     song_id: &str,
     user_id: &str,
-    executor: impl sqlx::PgExecutor<'e>,
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<real_time::Changed, Error> {
+    if !get_settings(jam_id, &mut **transaction).await?.voting {
+        return Err(Error::Forbidden(
+            "voting is turned off in this jam".to_string(),
+        ));
+    }
+    insert_vote(song_id, user_id, jam_id, 1, transaction).await
+}
+
+/// if the downvote makes the score of the song strongly negative the song is removed from the queue
+pub async fn add_downvote<'e>(
+    song_id: &str,
+    user_id: &str,
+    jam_id: &str,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+) -> Result<real_time::Changed, Error> {
+    let settings = get_settings(jam_id, &mut **transaction).await?;
+    if !settings.voting || !settings.downvotes {
+        return Err(Error::Forbidden(
+            "downvoting is turned off in this jam".to_string(),
+        ));
+    }
+
+    let changed = insert_vote(song_id, user_id, jam_id, -1, transaction).await?;
+
+    let score = sqlx::query!(
+        "SELECT COALESCE(SUM(value), 0) AS score FROM votes WHERE song_id=$1",
+        song_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .score
+    .unwrap_or(0);
+
+    let user_count = sqlx::query!(
+        "SELECT COUNT(*) FROM users WHERE jam_id=$1 AND status = 'admitted'",
+        jam_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .count
+    .unwrap_or(0)
+        // the jam itself has a row in users
+        - 1;
+
+    if score <= -MIN_DROP_SCORE.max(user_count / 2) {
+        println!("dropping song {} with score {}", song_id, score);
+        sqlx::query!(
+            "DELETE FROM songs WHERE id = $1 AND user_id IN (SELECT id FROM users WHERE jam_id = $2);",
+            song_id,
+            jam_id
+        )
+        .execute(&mut **transaction)
+        .await?;
+        return Ok(changed.songs());
+    }
+
+    Ok(changed)
+}
+
+/// a user can change an upvote to a downvote and the other way around, but not vote the same way twice,
+/// and only for the songs of their jam
+async fn insert_vote<'e>(
+    song_id: &str,
+    user_id: &str,
+    jam_id: &str,
+    value: i16,
+    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
 ) -> Result<real_time::Changed, Error> {
+    let in_jam = sqlx::query!(
+        "SELECT EXISTS(SELECT 1 FROM songs WHERE id = $1 AND user_id IN (SELECT id FROM users WHERE jam_id = $2))",
+        song_id,
+        jam_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .exists
+    .unwrap_or(false);
+    if !in_jam {
+        return Err(Error::DoesNotExist(format!(
+            "could not vote, no song in this jam with id: {}",
+            song_id
+        )));
+    }
+
     let result = sqlx::query!(
-        "INSERT INTO votes (song_id, user_id, id)
-         VALUES ($1, $2, $3)
-         ON CONFLICT (song_id, user_id) DO NOTHING",
+        "INSERT INTO votes (song_id, user_id, id, value)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (song_id, user_id) DO UPDATE SET value = EXCLUDED.value
+         WHERE votes.value <> EXCLUDED.value",
         song_id,
         user_id,
-        format!("{}{}", song_id, user_id)
+        format!("{}{}", song_id, user_id),
+        value
     )
-    .execute(executor)
+    .execute(&mut **transaction)
     .await?;
 
     if result.rows_affected() == 0 {
@@ -63,11 +153,11 @@ pub async fn get_votes<'e>(
     // Fetch the vote counts for all songs in the current jam
     let vec = sqlx::query_as!(
         VotesDb,
-        "SELECT s.id AS song_id, COUNT(v.id) AS votes_nr
+        "SELECT s.id AS song_id, COALESCE(SUM(v.value), 0) AS votes_nr
         FROM songs s
         JOIN users u ON s.user_id = u.id
         LEFT JOIN votes v ON s.id = v.song_id
-        WHERE u.jam_id = $1
+        WHERE u.jam_id = $1 AND s.status = 'approved'
         GROUP BY s.id
         ORDER BY votes_nr DESC",
         id.jam_id()
@@ -83,7 +173,7 @@ pub async fn get_votes<'e>(
                 (
                     v.song_id,
                     Vote {
-                        votes: v.votes_nr.unwrap_or(0) as u64,
+                        votes: v.votes_nr.unwrap_or(0),
                         have_you_voted: None,
                     },
                 )
@@ -91,21 +181,22 @@ pub async fn get_votes<'e>(
             .collect(),
         IdType::User(user_id) => {
             // Fetch the songs the user has voted for
-            let user_votes = sqlx::query!("SELECT song_id FROM votes WHERE user_id = $1;", user_id)
-                .fetch_all(&mut **transaction)
-                .await?
-                .into_iter()
-                .map(|vote| vote.song_id)
-                .collect::<HashSet<String>>(); // Collect into a HashSet for efficient lookup
+            let user_votes =
+                sqlx::query!("SELECT song_id, value FROM votes WHERE user_id=$1", user_id)
+                    .fetch_all(&mut **transaction)
+                    .await?
+                    .into_iter()
+                    .map(|vote| (vote.song_id, YourVote::from_value(vote.value)))
+                    .collect::<HashMap<String, YourVote>>(); // Collect into a HashMap for efficient lookup
 
             vec.into_iter()
                 .map(|v| {
-                    let user_voted = user_votes.contains(&v.song_id);
+                    let user_vote = user_votes.get(&v.song_id).copied().unwrap_or_default();
                     (
                         v.song_id,
                         Vote {
-                            votes: v.votes_nr.unwrap_or(0) as u64,
-                            have_you_voted: Some(user_voted),
+                            votes: v.votes_nr.unwrap_or(0),
+                            have_you_voted: Some(user_vote),
                         },
                     )
                 })
@@ -121,6 +212,28 @@ pub async fn get_votes<'e>(
     Ok(votes)
 }
 
+/// the votes of every user in the jam, by user id and then by song id
+pub async fn get_votes_of_users<'e>(
+    jam_id: &str,
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<HashMap<String, HashMap<String, YourVote>>, sqlx::Error> {
+    let votes = sqlx::query!(
+        "SELECT v.user_id, v.song_id, v.value FROM votes v JOIN users u ON v.user_id = u.id WHERE u.jam_id = $1",
+        jam_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut votes_of_users: HashMap<String, HashMap<String, YourVote>> = HashMap::new();
+    for vote in votes {
+        votes_of_users
+            .entry(vote.user_id)
+            .or_default()
+            .insert(vote.song_id, YourVote::from_value(vote.value));
+    }
+    Ok(votes_of_users)
+}
+
 pub async fn reset_votes<'e>(
     jam_id: &str,
     executor: impl sqlx::PgExecutor<'e>,
@@ -1,3 +1,4 @@
+use crate::model::functions::{start_hub, Provider};
 use crate::model::types::*;
 use axum::extract::FromRef;
 use std::error::Error as StdError;
@@ -7,8 +8,15 @@ pub struct AppState {
     pub db: Db,
     pub reqwest_client: reqwest::Client,
     pub spotify_credentials: SpotifyCredentials,
+    pub music_provider: Provider,
     pub leptos_options: leptos::prelude::LeptosOptions,
     pub site_url: String,
+    /// signs the tokens that are handed out, like the invite links and the sessions
+    pub secret_key: String,
+    /// the sockets get the updates of their jam from here
+    pub hub: Hub,
+    /// decides which server runs the background work of a jam
+    pub leader: Leader,
 }
 
 impl AppState {
@@ -18,6 +26,9 @@ impl AppState {
         spotify_secret: String,
         db_url: String,
         site_url: String,
+        secret_key: String,
+        music_provider: String,
+        music_library: Option<String>,
     ) -> Result<Self, Error> {
         
         let reqwest_client = reqwest::Client::new();
@@ -26,17 +37,30 @@ impl AppState {
         let db = Db::new(db_url).await?;
         println!("Connected to database...");
 
+        let hub = start_hub(&db.pool).await?;
+        let leader = Leader::new(&db.pool);
+
         let spotify_credentials = SpotifyCredentials {
             id: spotify_id,
             secret: spotify_secret,
         };
 
+        let music_provider = Provider::new(
+            &music_provider,
+            spotify_credentials.clone(),
+            music_library,
+        )?;
+
         Ok(Self {
             db,
             reqwest_client,
             spotify_credentials,
+            music_provider,
             leptos_options,
             site_url,
+            secret_key,
+            hub,
+            leader,
         })
     }
 }
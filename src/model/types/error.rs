@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, thiserror::Error)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub enum Error {
     #[error("Error from database: {0}")]
     Database(String),
@@ -17,7 +18,7 @@ pub enum Error {
     #[error("There is something missing or something that is not allow with the file system: {0}")]
     FileSystem(String),
     #[error("Your request is incorrect: {0}")]
-    InvalidRequest(String),
+    InvalidRequest(InvalidReason),
     #[error("The host cant create another jam cuz he is in one already, jam id: {jam_id}")]
     HostAlreadyInJam { jam_id: String },
     #[error("This user has reached |insert pronoun here| song limit")]
@@ -26,8 +27,16 @@ pub enum Error {
     EnvNotFound(String),
     #[error("song already in jam")]
     SongAlreadyInJam,
+    #[error("explicit songs are not allowed in this jam")]
+    ExplicitSongNotAllowed,
+    #[error("you were banned from this jam")]
+    Banned,
     #[error("A entry was not found: {0}")]
     DoesNotExist(String),
+    #[error("Your session is not valid, join again: {0}")]
+    InvalidSession(String),
+    #[error("Spotify has to be connected again to allow this: {0}")]
+    MissingSpotifyScope(String),
 }
 
 impl Error {
@@ -45,7 +54,11 @@ impl Error {
             Error::UserHasTooTheMaxSongAmount => 4400,
             Error::EnvNotFound(_) => 4500,
             Error::SongAlreadyInJam => 4400,
+            Error::ExplicitSongNotAllowed => 4400,
+            Error::Banned => 4403,
             Error::DoesNotExist(_) => 4404,
+            Error::InvalidSession(_) => 4401,
+            Error::MissingSpotifyScope(_) => 4403,
         }
     }
 }
@@ -60,18 +73,93 @@ impl From<Error> for String {
             Error::Forbidden(s) => s,
             Error::Spotify(s) => s,
             Error::FileSystem(s) => s,
-            Error::InvalidRequest(s) => s,
+            Error::InvalidRequest(reason) => reason.to_string(),
             Error::HostAlreadyInJam { jam_id } => {
                 format!("Host is already in jam with id: {}", jam_id)
             }
             Error::UserHasTooTheMaxSongAmount => "User has too the max song amount".to_string(),
             Error::EnvNotFound(s) => s,
             Error::SongAlreadyInJam => "Song already in jam".to_string(),
+            Error::ExplicitSongNotAllowed => {
+                "Explicit songs are not allowed in this jam".to_string()
+            }
+            Error::Banned => "You were banned from this jam".to_string(),
             Error::DoesNotExist(s) => s,
+            Error::InvalidSession(s) => s,
+            Error::MissingSpotifyScope(s) => s,
+        }
+    }
+}
+
+/// Why a request was rejected, the rules of the jam that a song broke are kept structured,
+/// so the user page can explain them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub enum InvalidReason {
+    Other(String),
+    /// the durations are in milliseconds
+    SongTooLong {
+        song: String,
+        duration: u32,
+        max_duration: u32,
+    },
+    ArtistBlocked {
+        song: String,
+        artist: String,
+    },
+    /// none of the genres of the song are in the allowed genres of the jam
+    GenreNotAllowed {
+        song: String,
+        allowed_genres: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for InvalidReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let min_sec = |millis: u32| format!("{}:{:02}", millis / 60_000, millis / 1000 % 60);
+        match self {
+            InvalidReason::Other(s) => write!(f, "{}", s),
+            InvalidReason::SongTooLong {
+                song,
+                duration,
+                max_duration,
+            } => write!(
+                f,
+                "{} is {} long, songs can be at most {} long in this jam",
+                song,
+                min_sec(*duration),
+                min_sec(*max_duration)
+            ),
+            InvalidReason::ArtistBlocked { song, artist } => write!(
+                f,
+                "{} can't be added, the host blocked songs by {}",
+                song, artist
+            ),
+            InvalidReason::GenreNotAllowed {
+                song,
+                allowed_genres,
+            } => write!(
+                f,
+                "{} can't be added, only these genres are allowed in this jam: {}",
+                song,
+                allowed_genres.join(", ")
+            ),
         }
     }
 }
 
+impl From<String> for InvalidReason {
+    fn from(s: String) -> Self {
+        InvalidReason::Other(s)
+    }
+}
+
+impl From<&str> for InvalidReason {
+    fn from(s: &str) -> Self {
+        InvalidReason::Other(s.to_string())
+    }
+}
+
 use rspotify::model::idtypes::IdError;
 use rspotify::ClientError;
 
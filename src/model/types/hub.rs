@@ -0,0 +1,244 @@
+use super::real_time::{Delta, SharedUpdate, Update};
+use super::{Song, ToVotes, User, Votes};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// how many updates a socket can fall behind before it misses some, then the client asks for a snapshot
+const CAPACITY: usize = 16;
+/// how many of the last updates of a jam are kept, for the clients that lost their connection to resume from
+const LOG_SIZE: usize = 64;
+/// how long a jam is kept after its last socket left, so a phone that locked its screen can still resume
+const RESUME_WINDOW: Duration = Duration::from_secs(60 * 5);
+
+/// Hands the updates of the jams to the sockets connected to this server,
+/// an update is loaded once for the jam and not once for every socket.
+/// The updates come from Postgres NOTIFY on a single connection, so every server gets them, see `start_hub`.
+/// The last updates of every jam are kept too, so a client that lost its connection only gets what it missed
+#[derive(Debug, Clone, Default)]
+pub struct Hub {
+    jams: Arc<Mutex<HashMap<String, Jam>>>,
+}
+
+#[derive(Debug)]
+struct Jam {
+    sender: broadcast::Sender<Arc<SharedUpdate>>,
+    /// the `Update::seq` of the last update
+    seq: u64,
+    /// the last updates, the oldest first
+    log: VecDeque<Arc<SharedUpdate>>,
+    /// when the last socket of the jam left, none while it has sockets
+    left_at: Option<Instant>,
+    /// the lists as the clients have them, only what changed since is sent
+    songs: Option<Vec<Song>>,
+    votes: Option<Votes>,
+    users: Option<Vec<User>>,
+}
+
+impl Jam {
+    /// The seq starts from the time the jam was started at, in microseconds. A client may resume with the seq
+    /// it got from another server, or from this one before the jam was forgotten, that one is never in this log
+    fn new() -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+            seq: started_at,
+            log: VecDeque::with_capacity(LOG_SIZE),
+            left_at: None,
+            songs: None,
+            votes: None,
+            users: None,
+        }
+    }
+
+    /// Replaces the lists in the update with deltas. The first time a list is loaded it is sent whole,
+    /// there is nothing to compare it to
+    fn replace_lists_with_deltas(&mut self, update: &mut Update) {
+        let mut deltas = Vec::new();
+
+        // the new songs come with their votes
+        let mut votes = update.votes.take();
+        if let Some(songs) = update.songs.take() {
+            if votes.is_none() {
+                votes = songs.clone().to_votes();
+            }
+            match &self.songs {
+                Some(old_songs) => deltas.extend(Delta::songs(old_songs, &songs)),
+                None => update.songs = Some(songs.clone()),
+            }
+            self.songs = Some(songs);
+        }
+        if let Some(votes) = votes {
+            match &self.votes {
+                Some(old_votes) => deltas.extend(Delta::votes(old_votes, &votes)),
+                None => update.votes = Some(votes.clone()),
+            }
+            self.votes = Some(votes);
+        }
+
+        if let Some(users) = update.users.take() {
+            match &self.users {
+                Some(old_users) => deltas.extend(Delta::users(old_users, &users)),
+                None => update.users = Some(users.clone()),
+            }
+            self.users = Some(users);
+        }
+
+        update.deltas.extend(deltas);
+    }
+}
+
+impl Hub {
+    /// the seq of the last update of the jam comes with the receiver
+    pub fn subscribe(&self, jam_id: &str) -> (u64, broadcast::Receiver<Arc<SharedUpdate>>) {
+        let mut jams = self.jams.lock().unwrap();
+        let jam = jams.entry(jam_id.to_string()).or_insert_with(Jam::new);
+        (jam.seq, jam.sender.subscribe())
+    }
+
+    /// the jams whose sockets are all gone are forgotten here once they can't be resumed anymore,
+    /// so an update is only loaded if someone on this server gets it, or may resume from it
+    pub fn has_subscribers(&self, jam_id: &str) -> bool {
+        let now = Instant::now();
+        let mut jams = self.jams.lock().unwrap();
+        jams.retain(|_, jam| {
+            if jam.sender.receiver_count() > 0 {
+                jam.left_at = None;
+                return true;
+            }
+            let left_at = *jam.left_at.get_or_insert(now);
+            now.duration_since(left_at) < RESUME_WINDOW
+        });
+        jams.contains_key(jam_id)
+    }
+
+    pub fn publish(&self, jam_id: &str, mut update: SharedUpdate) {
+        if let Some(jam) = self.jams.lock().unwrap().get_mut(jam_id) {
+            jam.seq += 1;
+            update.update.seq = Some(jam.seq);
+            jam.replace_lists_with_deltas(&mut update.update);
+
+            let update = Arc::new(update);
+            if jam.log.len() == LOG_SIZE {
+                jam.log.pop_front();
+            }
+            jam.log.push_back(update.clone());
+            // it fails if the jam has no sockets, it is only kept for resuming
+            let _ = jam.sender.send(update);
+        }
+    }
+
+    /// the updates after `last_seq` up to `seq`, the oldest first,
+    /// none if some of them are not in the log anymore, or never were
+    pub fn missed(&self, jam_id: &str, last_seq: u64, seq: u64) -> Option<Vec<Arc<SharedUpdate>>> {
+        if last_seq > seq {
+            return None;
+        }
+        let jams = self.jams.lock().unwrap();
+        let missed = jams
+            .get(jam_id)?
+            .log
+            .iter()
+            .filter(|update| {
+                update
+                    .update
+                    .seq
+                    .is_some_and(|update_seq| update_seq > last_seq && update_seq <= seq)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if missed.len() as u64 == seq - last_seq {
+            Some(missed)
+        } else {
+            None
+        }
+    }
+
+    /// the jams that have sockets on this server
+    pub fn jam_ids(&self) -> Vec<String> {
+        self.jams.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared(update: Update) -> SharedUpdate {
+        SharedUpdate {
+            update,
+            votes_of_users: None,
+            skip_voters: None,
+        }
+    }
+
+    fn seqs(missed: Option<Vec<Arc<SharedUpdate>>>) -> Option<Vec<u64>> {
+        missed.map(|missed| {
+            missed
+                .iter()
+                .map(|shared| shared.update.seq.unwrap())
+                .collect()
+        })
+    }
+
+    /// publishes `count` updates to the jam, returns the seq before the first one
+    fn publish(hub: &Hub, count: usize) -> u64 {
+        let (seq, _receiver) = hub.subscribe("jam");
+        for i in 0..count {
+            hub.publish("jam", shared(Update::new().position(i as f32 / 100.0)));
+        }
+        seq
+    }
+
+    #[test]
+    fn a_client_resumes_with_the_updates_it_missed() {
+        let hub = Hub::default();
+        let first = publish(&hub, 3);
+
+        assert_eq!(
+            seqs(hub.missed("jam", first + 1, first + 3)),
+            Some(vec![first + 2, first + 3])
+        );
+        assert_eq!(
+            seqs(hub.missed("jam", first, first + 3)),
+            Some(vec![first + 1, first + 2, first + 3])
+        );
+        // it didn't miss anything
+        assert_eq!(seqs(hub.missed("jam", first + 3, first + 3)), Some(vec![]));
+        // only the updates up to the seq the socket subscribed at, the later ones it gets from the receiver
+        assert_eq!(
+            seqs(hub.missed("jam", first, first + 1)),
+            Some(vec![first + 1])
+        );
+    }
+
+    #[test]
+    fn the_log_keeps_the_last_updates() {
+        let hub = Hub::default();
+        let first = publish(&hub, LOG_SIZE + 1);
+        let last = first + LOG_SIZE as u64 + 1;
+
+        // the first update is gone, a snapshot is sent instead
+        assert_eq!(hub.missed("jam", first, last), None);
+        let missed = seqs(hub.missed("jam", first + 1, last)).unwrap();
+        assert_eq!(missed.len(), LOG_SIZE);
+        assert_eq!(missed.first(), Some(&(first + 2)));
+        assert_eq!(missed.last(), Some(&last));
+    }
+
+    #[test]
+    fn a_seq_from_somewhere_else_gets_a_snapshot() {
+        let hub = Hub::default();
+        let first = publish(&hub, 3);
+
+        // from another server, or from before this one forgot the jam
+        assert_eq!(hub.missed("jam", 5, first + 3), None);
+        assert_eq!(hub.missed("jam", first + 10, first + 3), None);
+        assert_eq!(hub.missed("another jam", first, first + 3), None);
+    }
+}
@@ -1,14 +1,38 @@
+use super::Permission;
+
 pub type JamId = String;
 
 #[derive(Debug, Clone)]
 pub struct Id {
     pub id: IdType,
     pub jam_id: String,
+    /// what the host let this user do, empty for everyone who is not a co-host
+    pub permissions: Vec<Permission>,
 }
 
 impl Id {
     pub fn new(id: IdType, jam_id: String) -> Self {
-        Self { id, jam_id }
+        Self {
+            id,
+            jam_id,
+            permissions: Vec::new(),
+        }
+    }
+
+    pub fn with_permissions(self, permissions: Vec<Permission>) -> Self {
+        Self {
+            permissions,
+            ..self
+        }
+    }
+
+    /// the host can do everything, a co-host only what the host let them
+    pub fn can(&self, permission: Permission) -> bool {
+        self.is_host() || (self.is_user() && self.permissions.contains(&permission))
+    }
+
+    pub fn is_co_host(&self) -> bool {
+        self.is_user() && !self.permissions.is_empty()
     }
 
     pub fn jam_id(&self) -> &str {
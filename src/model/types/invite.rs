@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// A link the host hands out, it lets people into the jam without the passcode
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub struct Invite {
+    pub id: String,
+    /// the signed id of the invite, it goes into the link as `?invite=`
+    pub token: String,
+    /// in milliseconds, none if it never expires
+    pub expires_at: Option<i64>,
+    /// none if it can be used any number of times
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+}
+
+/// What someone joining a jam can prove that they were let in with
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct JoinCredentials {
+    pub passcode: Option<String>,
+    /// the token of an `Invite`
+    pub invite: Option<String>,
+}
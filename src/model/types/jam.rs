@@ -1,3 +1,4 @@
+use super::JamSettings;
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -5,5 +6,5 @@ pub struct Jam {
     pub id: String,
     pub name: String,
     pub max_song_count: u8,
+    pub settings: JamSettings,
 }
-
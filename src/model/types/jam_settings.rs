@@ -0,0 +1,126 @@
+use super::{Error, InvalidReason, QueueStrategy, Song};
+use serde::{Deserialize, Serialize};
+
+pub const MAX_PASSCODE_LENGTH: usize = 12;
+
+/// The settings of a jam, the host can change them while the jam is running
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub struct JamSettings {
+    /// if off, the users can't vote on the songs
+    pub voting: bool,
+    /// if off, the users can only upvote
+    pub downvotes: bool,
+    pub allow_explicit: bool,
+    /// in milliseconds like `Song::duration`, none if there is no limit
+    pub max_song_duration: Option<u32>,
+    /// songs by these artists can't be added, compared case insensitively
+    pub blocked_artists: Vec<String>,
+    /// if not empty, only songs that have at least one genre containing one of these can be added
+    pub allowed_genres: Vec<String>,
+    pub queue_strategy: QueueStrategy,
+    /// users who just had a song played have to wait for the others
+    pub fairness: bool,
+    /// new users have to be let in by the host
+    pub join_approval: bool,
+    /// if some, joining needs this or an invite link, the users never get it
+    pub passcode: Option<String>,
+    /// the songs the users add have to be approved by the host before they are in the queue
+    pub moderated: bool,
+    /// the share of the users that has to vote to skip the current song, between 0 and 1
+    pub skip_threshold: f32,
+    /// the songs that were played are saved as a playlist on the host's spotify account when the jam is deleted
+    pub save_playlist: bool,
+}
+
+impl Default for JamSettings {
+    fn default() -> Self {
+        Self {
+            voting: true,
+            downvotes: true,
+            allow_explicit: true,
+            max_song_duration: None,
+            blocked_artists: Vec::new(),
+            allowed_genres: Vec::new(),
+            queue_strategy: QueueStrategy::default(),
+            fairness: false,
+            join_approval: false,
+            passcode: None,
+            moderated: false,
+            skip_threshold: 0.5,
+            save_playlist: false,
+        }
+    }
+}
+
+impl JamSettings {
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(0.0..=1.0).contains(&self.skip_threshold) {
+            return Err(Error::InvalidRequest(
+                "Skip threshold must be between 0 and 1".into(),
+            ));
+        }
+        if self.max_song_duration == Some(0) {
+            return Err(Error::InvalidRequest(
+                "Max song duration must be more than 0".into(),
+            ));
+        }
+        if let Some(passcode) = &self.passcode {
+            // it is put in the url of the create user page, so it is kept to letters and numbers
+            if passcode.is_empty()
+                || passcode.len() > MAX_PASSCODE_LENGTH
+                || !passcode.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return Err(Error::InvalidRequest(
+                    format!(
+                        "The passcode must be 1 to {} letters and numbers",
+                        MAX_PASSCODE_LENGTH
+                    )
+                    .into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// checks the song against the duration limit, the blocked artists and the allowed genres,
+    /// the genres are only needed if `allowed_genres` is not empty
+    pub fn check_song(&self, song: &Song, genres: &[String]) -> Result<(), InvalidReason> {
+        if let Some(max_duration) = self.max_song_duration {
+            if song.duration > max_duration {
+                return Err(InvalidReason::SongTooLong {
+                    song: song.name.clone(),
+                    duration: song.duration,
+                    max_duration,
+                });
+            }
+        }
+
+        if let Some(artist) = song.artists.iter().find(|artist| {
+            self.blocked_artists
+                .iter()
+                .any(|blocked| blocked.eq_ignore_ascii_case(artist.trim()))
+        }) {
+            return Err(InvalidReason::ArtistBlocked {
+                song: song.name.clone(),
+                artist: artist.clone(),
+            });
+        }
+
+        if !self.allowed_genres.is_empty()
+            && !genres.iter().any(|genre| {
+                let genre = genre.to_lowercase();
+                self.allowed_genres
+                    .iter()
+                    .any(|allowed| genre.contains(&allowed.to_lowercase()))
+            })
+        {
+            return Err(InvalidReason::GenreNotAllowed {
+                song: song.name.clone(),
+                allowed_genres: self.allowed_genres.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
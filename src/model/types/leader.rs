@@ -0,0 +1,131 @@
+use sqlx::{postgres::PgConnectOptions, Connection, PgConnection};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Picks the one server that does the background work of a jam, like moving on to the next song,
+/// so it is done once even if there are more servers running the app.
+/// The server that holds the Postgres advisory lock of the jam leads it, all the locks of this server
+/// are held by one connection, if that is lost the locks are let go and another server takes over
+#[derive(Debug, Clone)]
+pub struct Leader {
+    options: PgConnectOptions,
+    connection: Arc<tokio::sync::Mutex<Option<PgConnection>>>,
+    /// the jams this server holds the lock of
+    led: Arc<Mutex<HashSet<String>>>,
+    /// how many hosts of the jam are connected to this server,
+    /// a jam is in here as long as its background work runs here
+    hosts: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Leader {
+    pub fn new(pool: &sqlx::PgPool) -> Self {
+        Self {
+            options: (*pool.connect_options()).clone(),
+            connection: Arc::new(tokio::sync::Mutex::new(None)),
+            led: Arc::new(Mutex::new(HashSet::new())),
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// returns true if the background work of the jam has to be started on this server
+    pub fn host_joined(&self, jam_id: &str) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        match hosts.get_mut(jam_id) {
+            Some(count) => {
+                *count += 1;
+                false
+            }
+            None => {
+                hosts.insert(jam_id.to_string(), 1);
+                true
+            }
+        }
+    }
+
+    pub fn host_left(&self, jam_id: &str) {
+        if let Some(count) = self.hosts.lock().unwrap().get_mut(jam_id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// false once the last host of the jam left this server, then the background work has to stop,
+    /// the jam is only forgotten here so a host joining in the meantime doesn't start it a second time
+    pub fn has_hosts(&self, jam_id: &str) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        match hosts.get(jam_id) {
+            Some(0) | None => {
+                hosts.remove(jam_id);
+                false
+            }
+            Some(_) => true,
+        }
+    }
+
+    /// true if this server leads the jam, it tries to become the leader if no server is
+    pub async fn lead(&self, jam_id: &str) -> bool {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            match PgConnection::connect_with(&self.options).await {
+                Ok(new_connection) => *connection = Some(new_connection),
+                Err(e) => {
+                    eprintln!("Error connecting for the leader locks: {:?}", e);
+                    return false;
+                }
+            }
+        }
+        let Some(locks) = connection.as_mut() else {
+            return false;
+        };
+
+        let already_led = self.led.lock().unwrap().contains(jam_id);
+        let result = if already_led {
+            // the locks are gone with the connection, so it is checked that it still works
+            locks.ping().await.map(|_| true)
+        } else {
+            sqlx::query!(
+                "SELECT pg_try_advisory_lock(hashtextextended($1, 0)) AS led",
+                jam_id
+            )
+            .fetch_one(&mut *locks)
+            .await
+            .map(|row| row.led.unwrap_or(false))
+        };
+
+        match result {
+            Ok(true) => {
+                if !already_led {
+                    println!("leading jam {}", jam_id);
+                    self.led.lock().unwrap().insert(jam_id.to_string());
+                }
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                eprintln!("Lost the connection of the leader locks: {:?}", e);
+                *connection = None;
+                self.led.lock().unwrap().clear();
+                false
+            }
+        }
+    }
+
+    /// lets another server take over the jam
+    pub async fn resign(&self, jam_id: &str) {
+        if !self.led.lock().unwrap().remove(jam_id) {
+            return;
+        }
+
+        let mut connection = self.connection.lock().await;
+        let Some(locks) = connection.as_mut() else {
+            return;
+        };
+        if let Err(e) = sqlx::query!("SELECT pg_advisory_unlock(hashtextextended($1, 0))", jam_id)
+            .execute(&mut *locks)
+            .await
+        {
+            eprintln!("Error letting go of the lock of jam {}: {:?}", jam_id, e);
+            *connection = None;
+            self.led.lock().unwrap().clear();
+        }
+    }
+}
@@ -11,6 +11,16 @@ mod db;
 #[cfg(feature = "ssr")]
 pub use db::*;
 
+#[cfg(feature = "ssr")]
+mod hub;
+#[cfg(feature = "ssr")]
+pub use hub::*;
+
+#[cfg(feature = "ssr")]
+mod leader;
+#[cfg(feature = "ssr")]
+pub use leader::*;
+
 pub mod real_time;
 
 mod error;
@@ -25,11 +35,34 @@ pub use song::*;
 mod jam;
 pub use jam::*;
 
+mod jam_settings;
+pub use jam_settings::*;
+
 mod user;
 pub use user::*;
 
+mod invite;
+pub use invite::*;
+
+mod session;
+pub use session::*;
+
 mod spotify_credentials;
 pub use spotify_credentials::*;
 
 mod id;
-pub use id::*;
\ No newline at end of file
+pub use id::*;
+
+mod provider_kind;
+pub use provider_kind::*;
+mod queue_strategy;
+pub use queue_strategy::*;
+
+mod permission;
+pub use permission::*;
+
+mod played_song;
+pub use played_song::*;
+
+mod skip_votes;
+pub use skip_votes::*;
@@ -0,0 +1,55 @@
+use super::Error;
+use serde::{Deserialize, Serialize};
+
+/// What the host can let a user do, a user with any of them is a co-host,
+/// stored in the `permissions` column of `users`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub enum Permission {
+    /// kick and ban users, and let people in from the waiting room
+    Kick,
+    /// skip the current song without a vote
+    Skip,
+    /// approve, reject, pin and remove songs of anyone
+    ApproveSongs,
+    ChangeSettings,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::Kick,
+        Permission::Skip,
+        Permission::ApproveSongs,
+        Permission::ChangeSettings,
+    ];
+
+    /// the value stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Kick => "kick",
+            Permission::Skip => "skip",
+            Permission::ApproveSongs => "approve_songs",
+            Permission::ChangeSettings => "change_settings",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Permission::Kick => "Kick and ban users",
+            Permission::Skip => "Skip songs",
+            Permission::ApproveSongs => "Approve songs",
+            Permission::ChangeSettings => "Change settings",
+        }
+    }
+}
+
+impl std::str::FromStr for Permission {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+            .ok_or_else(|| Error::Decode(format!("unknown permission: {}", s)))
+    }
+}
@@ -0,0 +1,14 @@
+use super::Song;
+use serde::{Deserialize, Serialize};
+
+/// a song from the history of the jam
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub struct PlayedSong {
+    /// the id of the song is the id of the history entry, the votes are the votes it had when it started playing
+    pub song: Song,
+    /// the name of the user who added the song, none if it was not added by a user
+    pub user_name: Option<String>,
+    /// unix timestamp in milliseconds
+    pub played_at: i64,
+}
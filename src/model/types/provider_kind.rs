@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// which music provider the server is running with, so the host page knows what player to show
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    Spotify,
+    Fake,
+    Local,
+}
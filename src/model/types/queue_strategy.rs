@@ -0,0 +1,56 @@
+use super::Error;
+use serde::{Deserialize, Serialize};
+
+/// How the next song is picked from the queue of a jam, stored in the `queue_strategy` column of `jams`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub enum QueueStrategy {
+    /// the song with the most votes, ties are picked randomly
+    #[default]
+    MostVotes,
+    /// the users take turns, on their turn their song with the most votes is played
+    RoundRobin,
+    /// the oldest song is played first, but every vote moves a song forward in the queue
+    FifoWithVoteBoost,
+    /// a random song, the more votes a song has the more likely it is to be picked
+    WeightedRandom,
+}
+
+impl QueueStrategy {
+    pub const ALL: [QueueStrategy; 4] = [
+        QueueStrategy::MostVotes,
+        QueueStrategy::RoundRobin,
+        QueueStrategy::FifoWithVoteBoost,
+        QueueStrategy::WeightedRandom,
+    ];
+
+    /// the value stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueStrategy::MostVotes => "most_votes",
+            QueueStrategy::RoundRobin => "round_robin",
+            QueueStrategy::FifoWithVoteBoost => "fifo_with_vote_boost",
+            QueueStrategy::WeightedRandom => "weighted_random",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            QueueStrategy::MostVotes => "Most votes",
+            QueueStrategy::RoundRobin => "Round robin",
+            QueueStrategy::FifoWithVoteBoost => "First in first out",
+            QueueStrategy::WeightedRandom => "Weighted random",
+        }
+    }
+}
+
+impl std::str::FromStr for QueueStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        QueueStrategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.as_str() == s)
+            .ok_or_else(|| Error::Decode(format!("unknown queue strategy: {}", s)))
+    }
+}
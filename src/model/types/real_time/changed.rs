@@ -1,16 +1,28 @@
 use serde::{Serialize, Deserialize};
+use crate::model::types::{Id, Permission};
 
 /// this struct is used to tell the listening server socket what has changed in the database
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
 pub struct Changed {
     pub users: bool,
-    ///this has to be re-fetched on the clients WS thread because every user can vote on songs and thus has a different result of for songs
+    /// the songs are loaded once for the jam, the votes of the user are filled in on the clients WS thread
     pub songs: bool,
-    /// this has to be re-fetched on the clients WS thread because every user can vote on songs and thus has a different result of for votes
+    /// the votes are loaded once for the jam, the votes of the user are filled in on the clients WS thread
     pub votes: bool,
     pub ended: bool,
     pub position: bool,
     pub current_song: bool,
+    pub history: bool,
+    pub skip_votes: bool,
+    pub settings: bool,
+    /// the songs waiting for approval, only sent to the host and co-hosts who can approve songs
+    pub pending: bool,
+    /// the users that can't join the jam again, only sent to the host and co-hosts who can kick
+    pub banned: bool,
+    /// the users waiting to be admitted, only sent to the host and co-hosts who can kick
+    pub join_requests: bool,
+    /// the invite links of the jam, only sent to the host
+    pub invites: bool,
 }
 
 impl Changed {
@@ -23,11 +35,18 @@ impl Changed {
             ended: false,
             position: false,
             current_song: false,
+            history: false,
+            skip_votes: false,
+            settings: false,
+            pending: false,
+            banned: false,
+            join_requests: false,
+            invites: false,
        }
     }
 
     pub fn has_changed(&self) -> bool {
-        self.users || self.songs || self.votes || self.ended || self.position || self.current_song
+        self.users || self.songs || self.votes || self.ended || self.position || self.current_song || self.history || self.skip_votes || self.settings || self.pending || self.banned || self.join_requests || self.invites
     }
 
     /// This function merges the current instance with another instance of the struct
@@ -39,6 +58,13 @@ impl Changed {
             ended: self.ended || other.ended,
             position: self.position || other.position,
             current_song: self.current_song || other.current_song,
+            history: self.history || other.history,
+            skip_votes: self.skip_votes || other.skip_votes,
+            settings: self.settings || other.settings,
+            pending: self.pending || other.pending,
+            banned: self.banned || other.banned,
+            join_requests: self.join_requests || other.join_requests,
+            invites: self.invites || other.invites,
         }
     }
 
@@ -87,6 +113,65 @@ impl Changed {
         }
     }
 
+    pub fn history(self) -> Self {
+        Self {
+            history: true,
+            ..self
+        }
+    }
+
+    pub fn skip_votes(self) -> Self {
+        Self {
+            skip_votes: true,
+            ..self
+        }
+    }
+
+    pub fn settings(self) -> Self {
+        Self {
+            settings: true,
+            ..self
+        }
+    }
+
+    pub fn pending(self) -> Self {
+        Self {
+            pending: true,
+            ..self
+        }
+    }
+
+    pub fn banned(self) -> Self {
+        Self {
+            banned: true,
+            ..self
+        }
+    }
+
+    pub fn join_requests(self) -> Self {
+        Self {
+            join_requests: true,
+            ..self
+        }
+    }
+
+    pub fn invites(self) -> Self {
+        Self {
+            invites: true,
+            ..self
+        }
+    }
+
+    /// leaves out what the id is not allowed to see, so it is not loaded for nothing
+    pub fn visible_to(self, id: &Id) -> Self {
+        Self {
+            pending: self.pending && id.can(Permission::ApproveSongs),
+            banned: self.banned && id.can(Permission::Kick),
+            join_requests: self.join_requests && id.can(Permission::Kick),
+            invites: self.invites && id.is_host(),
+            ..self
+        }
+    }
 
     /// This function sets all the fields to true except for ended
     pub fn all() -> Self {
@@ -97,6 +182,13 @@ impl Changed {
             ended: false,
             position: true,
             current_song: true,
+            history: true,
+            skip_votes: true,
+            settings: true,
+            pending: true,
+            banned: true,
+            join_requests: true,
+            invites: true,
         }
     }
 }
\ No newline at end of file
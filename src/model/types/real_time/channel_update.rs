@@ -1,11 +1,11 @@
-use std::vec::Vec;
-
 use serde::{Serialize, Deserialize};
 use crate::model::types::*;
 
 
+/// what is sent through Postgres NOTIFY, every jam shares one channel,
+/// the errors of a request are only sent back to the socket that sent it
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChannelUpdate{
-    pub errors: Vec<Error>,
+    pub jam_id: String,
     pub changed: real_time::Changed,
 }
\ No newline at end of file
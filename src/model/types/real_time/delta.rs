@@ -0,0 +1,277 @@
+use crate::model::types::{Song, User, Vote, Votes};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One change to the songs, the votes or the users of the jam, so the whole list isn't sent again for every change.
+/// A delta sets the new value instead of saying how it changed, so applying one that is already in a snapshot does nothing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub enum Delta {
+    /// also sent when a song that was already in the queue changed, like when it was pinned, it comes with its votes
+    SongAdded(Song),
+    SongRemoved {
+        song_id: String,
+    },
+    VoteChanged {
+        song_id: String,
+        vote: Vote,
+    },
+    /// also sent when a user that was already in the jam changed, like when they were made a co-host
+    UserJoined(User),
+    UserLeft {
+        user_id: String,
+    },
+}
+
+impl Delta {
+    /// the songs are compared without their votes, a change in those is a `VoteChanged`
+    pub fn songs(old: &[Song], new: &[Song]) -> Vec<Delta> {
+        let without_votes = |song: &Song| Song {
+            votes: Vote::default(),
+            ..song.clone()
+        };
+        let old_songs = old
+            .iter()
+            .filter_map(|song| Some((song.id.as_deref()?, song)))
+            .collect::<HashMap<_, _>>();
+        let new_ids = new
+            .iter()
+            .filter_map(|song| song.id.as_deref())
+            .collect::<Vec<_>>();
+
+        let removed = old_songs
+            .keys()
+            .filter(|song_id| !new_ids.contains(song_id))
+            .map(|song_id| Delta::SongRemoved {
+                song_id: song_id.to_string(),
+            });
+        let added = new
+            .iter()
+            .filter(|song| {
+                let old_song = song
+                    .id
+                    .as_deref()
+                    .and_then(|song_id| old_songs.get(song_id));
+                old_song.is_none_or(|old_song| without_votes(old_song) != without_votes(song))
+            })
+            .map(|song| Delta::SongAdded(song.clone()));
+
+        removed.chain(added).collect()
+    }
+
+    /// only the songs that were there before are compared, the new ones come with their votes in `SongAdded`
+    pub fn votes(old: &Votes, new: &Votes) -> Vec<Delta> {
+        new.iter()
+            .filter(|(song_id, vote)| old.get(*song_id).is_some_and(|old_vote| old_vote != *vote))
+            .map(|(song_id, vote)| Delta::VoteChanged {
+                song_id: song_id.clone(),
+                vote: *vote,
+            })
+            .collect()
+    }
+
+    pub fn users(old: &[User], new: &[User]) -> Vec<Delta> {
+        let left = old
+            .iter()
+            .filter(|user| !new.iter().any(|new_user| new_user.id == user.id))
+            .map(|user| Delta::UserLeft {
+                user_id: user.id.clone(),
+            });
+        let joined = new
+            .iter()
+            .filter(|user| !old.contains(user))
+            .map(|user| Delta::UserJoined(user.clone()));
+
+        left.chain(joined).collect()
+    }
+
+    /// the songs end up sorted the way the server sorts the queue
+    pub fn apply_to_songs(deltas: &[Delta], songs: &mut Vec<Song>) {
+        for delta in deltas {
+            match delta {
+                Delta::SongAdded(song) => match songs.iter_mut().find(|s| s.id == song.id) {
+                    Some(old_song) => *old_song = song.clone(),
+                    None => songs.push(song.clone()),
+                },
+                Delta::SongRemoved { song_id } => {
+                    songs.retain(|song| song.id.as_ref() != Some(song_id));
+                }
+                Delta::VoteChanged { song_id, vote } => {
+                    if let Some(song) = songs.iter_mut().find(|s| s.id.as_ref() == Some(song_id)) {
+                        song.votes = *vote;
+                    }
+                }
+                Delta::UserJoined(_) | Delta::UserLeft { .. } => {}
+            }
+        }
+        songs.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then(b.votes.votes.cmp(&a.votes.votes))
+                .then(b.id.cmp(&a.id))
+        });
+    }
+
+    pub fn apply_to_votes(deltas: &[Delta], votes: &mut Votes) {
+        for delta in deltas {
+            match delta {
+                Delta::SongAdded(song) => {
+                    if let Some(song_id) = &song.id {
+                        votes.insert(song_id.clone(), song.votes);
+                    }
+                }
+                Delta::SongRemoved { song_id } => {
+                    votes.remove(song_id);
+                }
+                Delta::VoteChanged { song_id, vote } => {
+                    votes.insert(song_id.clone(), *vote);
+                }
+                Delta::UserJoined(_) | Delta::UserLeft { .. } => {}
+            }
+        }
+    }
+
+    pub fn apply_to_users(deltas: &[Delta], users: &mut Vec<User>) {
+        for delta in deltas {
+            match delta {
+                Delta::UserJoined(user) => match users.iter_mut().find(|u| u.id == user.id) {
+                    Some(old_user) => *old_user = user.clone(),
+                    None => users.push(user.clone()),
+                },
+                Delta::UserLeft { user_id } => users.retain(|user| &user.id != user_id),
+                Delta::SongAdded(_) | Delta::SongRemoved { .. } | Delta::VoteChanged { .. } => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::types::Permission;
+
+    fn song(id: &str, votes: i64, pinned: bool) -> Song {
+        Song {
+            id: Some(id.to_string()),
+            spotify_id: format!("spotify-{}", id),
+            user_id: None,
+            name: id.to_string(),
+            artists: vec!["artist".to_string()],
+            album: "album".to_string(),
+            duration: 180_000,
+            image_url: String::new(),
+            votes: Vote {
+                votes,
+                have_you_voted: None,
+            },
+            explicit: false,
+            pinned,
+        }
+    }
+
+    fn user(id: &str, permissions: Vec<Permission>) -> User {
+        User {
+            id: id.to_string(),
+            jam_id: "jam".to_string(),
+            name: id.to_string(),
+            permissions,
+        }
+    }
+
+    fn votes_of(songs: &[Song]) -> Votes {
+        songs
+            .iter()
+            .map(|song| (song.id.clone().unwrap(), song.votes))
+            .collect()
+    }
+
+    /// the songs are in the order the server sorts the queue in, so they can be compared after `apply_to_songs`
+    fn old_and_new_songs() -> (Vec<Song>, Vec<Song>) {
+        let old = vec![
+            song("a", 3, false),
+            song("b", 2, false),
+            song("c", 1, false),
+            song("d", 0, false),
+        ];
+        // a is removed, b is voted for, c is pinned and e is added
+        let new = vec![
+            song("c", 1, true),
+            song("b", 4, false),
+            song("e", 2, false),
+            song("d", 0, false),
+        ];
+        (old, new)
+    }
+
+    fn diff(old: &[Song], new: &[Song]) -> Vec<Delta> {
+        let mut deltas = Delta::songs(old, new);
+        deltas.extend(Delta::votes(&votes_of(old), &votes_of(new)));
+        deltas
+    }
+
+    #[test]
+    fn the_diff_of_the_songs_applied_gives_the_new_songs() {
+        let (old, new) = old_and_new_songs();
+        let deltas = diff(&old, &new);
+
+        let mut songs = old.clone();
+        Delta::apply_to_songs(&deltas, &mut songs);
+        assert_eq!(songs, new);
+
+        let mut votes = votes_of(&old);
+        Delta::apply_to_votes(&deltas, &mut votes);
+        assert_eq!(votes, votes_of(&new));
+    }
+
+    #[test]
+    fn only_what_changed_is_in_the_diff() {
+        let (old, new) = old_and_new_songs();
+        let deltas = diff(&old, &new);
+
+        assert_eq!(deltas.len(), 4);
+        assert!(deltas.contains(&Delta::SongRemoved {
+            song_id: "a".to_string()
+        }));
+        assert!(deltas.contains(&Delta::SongAdded(song("c", 1, true))));
+        assert!(deltas.contains(&Delta::SongAdded(song("e", 2, false))));
+        assert!(deltas.contains(&Delta::VoteChanged {
+            song_id: "b".to_string(),
+            vote: song("b", 4, false).votes,
+        }));
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn deltas_already_in_a_snapshot_change_nothing() {
+        let (old, new) = old_and_new_songs();
+        let deltas = diff(&old, &new);
+
+        let mut songs = new.clone();
+        Delta::apply_to_songs(&deltas, &mut songs);
+        assert_eq!(songs, new);
+
+        let mut votes = votes_of(&new);
+        Delta::apply_to_votes(&deltas, &mut votes);
+        assert_eq!(votes, votes_of(&new));
+    }
+
+    #[test]
+    fn the_diff_of_the_users_applied_gives_the_new_users() {
+        let old = vec![user("a", vec![]), user("b", vec![]), user("c", vec![])];
+        // b is made a co-host, c leaves and d joins
+        let new = vec![
+            user("a", vec![]),
+            user("b", vec![Permission::Skip]),
+            user("d", vec![]),
+        ];
+        let deltas = Delta::users(&old, &new);
+        assert_eq!(deltas.len(), 3);
+
+        let mut users = old.clone();
+        Delta::apply_to_users(&deltas, &mut users);
+        assert_eq!(users, new);
+        // the deltas of the songs leave the users alone
+        Delta::apply_to_users(&diff(&old_and_new_songs().0, &[]), &mut users);
+        assert_eq!(users, new);
+    }
+}
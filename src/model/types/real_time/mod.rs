@@ -4,9 +4,21 @@ pub use changed::*;
 mod update;
 pub use update::*;
 
+mod delta;
+pub use delta::*;
+
+mod sequence;
+pub use sequence::*;
+
+mod protocol;
+pub use protocol::*;
+
 mod request;
 pub use request::*;
 
+mod role;
+pub use role::*;
+
 pub mod search;
 pub use search::*;
 
@@ -14,3 +26,8 @@ pub use search::*;
 mod channel_update;
 #[cfg(feature = "ssr")]
 pub use channel_update::*;
+
+#[cfg(feature = "ssr")]
+mod shared_update;
+#[cfg(feature = "ssr")]
+pub use shared_update::*;
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// The version of the messages on `/socket`, it goes up when `Request` or `Update` change in a way older clients can't read
+pub const PROTOCOL_VERSION: u32 = 1;
+/// the oldest version the server still speaks
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// How the messages on the socket are encoded, msgpack is sent in binary messages and json in text messages
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Msgpack,
+    Json,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 2] = [Encoding::Msgpack, Encoding::Json];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Msgpack => "msgpack",
+            Encoding::Json => "json",
+        }
+    }
+}
+
+/// What the client and the server agreed on when the socket was opened, it is sent in the first update
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub struct Protocol {
+    pub version: u32,
+    pub encoding: Encoding,
+}
+
+impl Protocol {
+    /// The client says the newest version it speaks with `?version=`, the newest one both of them speak is used.
+    /// The clients from before there were versions speak the first one
+    pub fn negotiate(version: Option<u32>, encoding: Option<Encoding>) -> Result<Self, String> {
+        let version = version.unwrap_or(1).min(PROTOCOL_VERSION);
+        if version < MIN_PROTOCOL_VERSION {
+            return Err(format!(
+                "protocol version {} is not supported anymore, this server speaks {} to {}",
+                version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ));
+        }
+
+        Ok(Self {
+            version,
+            encoding: encoding.unwrap_or_default(),
+        })
+    }
+
+    /// what the own pages connect with
+    pub fn query() -> String {
+        format!(
+            "version={}&encoding={}",
+            PROTOCOL_VERSION,
+            Encoding::Msgpack.as_str()
+        )
+    }
+}
+
+/// `Option<()>` is sent as a bool, `Some(())` would be null just like `None` otherwise
+pub(super) mod flag {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<()>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(value.is_some())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<()>, D::Error> {
+        Ok(bool::deserialize(deserializer)?.then_some(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_client_without_a_version_speaks_the_first_one() {
+        let protocol = Protocol::negotiate(None, None).unwrap();
+        assert_eq!(protocol.version, 1);
+        assert_eq!(protocol.encoding, Encoding::Msgpack);
+    }
+
+    #[test]
+    fn a_newer_client_gets_the_newest_version_of_the_server() {
+        let protocol =
+            Protocol::negotiate(Some(PROTOCOL_VERSION + 5), Some(Encoding::Json)).unwrap();
+        assert_eq!(protocol.version, PROTOCOL_VERSION);
+        assert_eq!(protocol.encoding, Encoding::Json);
+    }
+
+    #[test]
+    fn a_client_that_is_too_old_is_turned_away() {
+        let error = Protocol::negotiate(Some(MIN_PROTOCOL_VERSION - 1), None).unwrap_err();
+        assert!(error.contains("not supported anymore"));
+    }
+
+    #[test]
+    fn the_own_pages_speak_the_newest_version() {
+        assert_eq!(
+            Protocol::query(),
+            format!("version={}&encoding=msgpack", PROTOCOL_VERSION)
+        );
+    }
+
+    #[cfg(feature = "ssr")]
+    #[test]
+    fn an_unknown_encoding_is_not_read() {
+        for encoding in Encoding::ALL {
+            let json = format!("\"{}\"", encoding.as_str());
+            assert_eq!(serde_json::from_str::<Encoding>(&json).unwrap(), encoding);
+        }
+        assert!(serde_json::from_str::<Encoding>("\"xml\"").is_err());
+    }
+
+    #[cfg(feature = "ssr")]
+    #[test]
+    fn an_update_survives_json() {
+        use super::super::{Delta, Update};
+
+        let update = Update {
+            deltas: vec![Delta::SongRemoved {
+                song_id: "song".to_string(),
+            }],
+            ..Update::new()
+                .position(0.5)
+                .protocol(Protocol::negotiate(None, Some(Encoding::Json)).unwrap())
+                .snapshot(42)
+        };
+
+        let json = serde_json::to_value(&update).unwrap();
+        // the flags are bools, not null for both
+        assert_eq!(json["snapshot"], serde_json::Value::Bool(true));
+        assert_eq!(json["resumed"], serde_json::Value::Bool(false));
+        assert_eq!(json["protocol"]["encoding"], "json");
+
+        let read = serde_json::from_value::<Update>(json.clone()).unwrap();
+        assert_eq!(read.seq, Some(42));
+        assert!(read.snapshot.is_some());
+        assert!(read.resumed.is_none());
+        assert_eq!(read.deltas, update.deltas);
+        assert_eq!(serde_json::to_value(&read).unwrap(), json);
+    }
+}
@@ -1,12 +1,84 @@
+use crate::model::types::{JamSettings, Permission};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub enum Request {
-    KickUser { user_id: String },
-    AddSong { song_id: String },
-    RemoveSong { song_id: String },
-    AddVote { song_id: String },
-    RemoveVote { song_id: String },
-    Search { query: String, id: String },
-    Position { percentage: f32 },
+    KickUser {
+        user_id: String,
+    },
+    AddSong {
+        song_id: String,
+    },
+    RemoveSong {
+        song_id: String,
+    },
+    AddVote {
+        song_id: String,
+    },
+    AddDownvote {
+        song_id: String,
+    },
+    RemoveVote {
+        song_id: String,
+    },
+    Search {
+        query: String,
+        id: String,
+    },
+    Position {
+        percentage: f32,
+    },
+    VoteSkip,
+    UpdateSettings {
+        settings: JamSettings,
+    },
+    ApproveSong {
+        song_id: String,
+    },
+    RejectSong {
+        song_id: String,
+    },
+    /// a pinned song is played next, before the songs with more votes
+    PinSong {
+        song_id: String,
+        pinned: bool,
+    },
+    /// kicks the user and keeps them from joining again
+    BanUser {
+        user_id: String,
+    },
+    /// the ban id is `BannedUser::id`
+    UnbanUser {
+        ban_id: String,
+    },
+    /// lets the user in from the waiting room
+    AdmitUser {
+        user_id: String,
+    },
+    RejectUser {
+        user_id: String,
+    },
+    /// none means no limit, for the hours and for the uses
+    CreateInvite {
+        hours: Option<u32>,
+        uses: Option<u32>,
+    },
+    DeleteInvite {
+        invite_id: String,
+    },
+    /// makes the user a co-host, no permissions makes them a normal user again
+    SetPermissions {
+        user_id: String,
+        permissions: Vec<Permission>,
+    },
+    /// skips the current song without a vote
+    Skip,
+    /// everything the client can see, for when it noticed a gap in `Update::seq`
+    Snapshot,
+    /// the updates after the last `Update::seq` the client got, for when it connected again,
+    /// a snapshot is sent if they are not kept anymore
+    Resume {
+        last_seq: u64,
+    },
 }
@@ -0,0 +1,373 @@
+use super::Request;
+use crate::model::types::{Id, Permission};
+
+/// Who can send a request over the socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Host,
+    /// a user the host gave the permission to
+    CoHost(Permission),
+    User,
+}
+
+impl Role {
+    pub fn has(&self, id: &Id) -> bool {
+        match self {
+            Role::Host => id.is_host(),
+            Role::CoHost(permission) => id.is_user() && id.can(*permission),
+            Role::User => id.is_user(),
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match self {
+            Role::Host => "the host".to_string(),
+            Role::CoHost(permission) => format!(
+                "co-hosts allowed to {}",
+                permission.display_name().to_lowercase()
+            ),
+            Role::User => "users".to_string(),
+        }
+    }
+}
+
+impl Request {
+    /// The permission table of the socket, every request is checked against it before it is handled.
+    /// Checks that depend on what the request is about, like a user only removing their own songs, are done where it is handled
+    pub fn allowed_roles(&self) -> &'static [Role] {
+        use Permission::*;
+        use Role::*;
+
+        match self {
+            // a user can only kick themselves, unless they are a co-host who can kick
+            Request::KickUser { .. } => &[Host, User],
+            Request::AddSong { .. } => &[Host, User],
+            // a user can only remove their own songs, unless they are a co-host who can approve songs
+            Request::RemoveSong { .. } => &[Host, User],
+            Request::AddVote { .. } => &[User],
+            Request::AddDownvote { .. } => &[User],
+            Request::RemoveVote { .. } => &[User],
+            Request::Search { .. } => &[Host, User],
+            // the player runs on the host page
+            Request::Position { .. } => &[Host],
+            Request::VoteSkip => &[User],
+            Request::UpdateSettings { .. } => &[Host, CoHost(ChangeSettings)],
+            Request::ApproveSong { .. } => &[Host, CoHost(ApproveSongs)],
+            Request::RejectSong { .. } => &[Host, CoHost(ApproveSongs)],
+            Request::PinSong { .. } => &[Host, CoHost(ApproveSongs)],
+            Request::BanUser { .. } => &[Host, CoHost(Kick)],
+            Request::UnbanUser { .. } => &[Host, CoHost(Kick)],
+            Request::AdmitUser { .. } => &[Host, CoHost(Kick)],
+            Request::RejectUser { .. } => &[Host, CoHost(Kick)],
+            Request::CreateInvite { .. } => &[Host],
+            Request::DeleteInvite { .. } => &[Host],
+            Request::SetPermissions { .. } => &[Host],
+            Request::Skip => &[Host, CoHost(Skip)],
+            Request::Snapshot => &[Host, User],
+            Request::Resume { .. } => &[Host, User],
+        }
+    }
+
+    pub fn is_allowed(&self, id: &Id) -> bool {
+        self.allowed_roles().iter().any(|role| role.has(id))
+    }
+
+    /// the error sent back when the request is not allowed, the connection is kept open
+    pub fn forbidden_error(&self) -> String {
+        let roles = self
+            .allowed_roles()
+            .iter()
+            .map(Role::display_name)
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("You are not allowed to do this, only {} can", roles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::types::{IdType, JamSettings};
+
+    fn host() -> Id {
+        Id::new(IdType::Host("host".to_string()), "jam".to_string())
+    }
+
+    fn user() -> Id {
+        Id::new(IdType::User("user".to_string()), "jam".to_string())
+    }
+
+    fn co_host(permissions: Vec<Permission>) -> Id {
+        user().with_permissions(permissions)
+    }
+
+    fn general() -> Id {
+        Id::new(IdType::General, "jam".to_string())
+    }
+
+    /// every kind of id that can send a request, with a name for the assert messages
+    fn ids() -> Vec<(String, Id)> {
+        let mut ids = vec![
+            ("host".to_string(), host()),
+            ("user".to_string(), user()),
+            ("general".to_string(), general()),
+            ("co-host".to_string(), co_host(Permission::ALL.to_vec())),
+        ];
+        for permission in Permission::ALL {
+            ids.push((
+                format!(
+                    "co-host who can {}",
+                    permission.display_name().to_lowercase()
+                ),
+                co_host(vec![permission]),
+            ));
+        }
+        ids
+    }
+
+    /// one request of every kind, `every_kind_of_request_is_tested` checks that none is missing
+    fn one_of_each() -> Vec<Request> {
+        let song_id = || "song".to_string();
+        let user_id = || "user".to_string();
+        vec![
+            Request::KickUser { user_id: user_id() },
+            Request::AddSong { song_id: song_id() },
+            Request::RemoveSong { song_id: song_id() },
+            Request::AddVote { song_id: song_id() },
+            Request::AddDownvote { song_id: song_id() },
+            Request::RemoveVote { song_id: song_id() },
+            Request::Search {
+                query: "query".to_string(),
+                id: "search".to_string(),
+            },
+            Request::Position { percentage: 0.5 },
+            Request::VoteSkip,
+            Request::UpdateSettings {
+                settings: JamSettings::default(),
+            },
+            Request::ApproveSong { song_id: song_id() },
+            Request::RejectSong { song_id: song_id() },
+            Request::PinSong {
+                song_id: song_id(),
+                pinned: true,
+            },
+            Request::BanUser { user_id: user_id() },
+            Request::UnbanUser {
+                ban_id: "ban".to_string(),
+            },
+            Request::AdmitUser { user_id: user_id() },
+            Request::RejectUser { user_id: user_id() },
+            Request::CreateInvite {
+                hours: None,
+                uses: None,
+            },
+            Request::DeleteInvite {
+                invite_id: "invite".to_string(),
+            },
+            Request::SetPermissions {
+                user_id: user_id(),
+                permissions: Permission::ALL.to_vec(),
+            },
+            Request::Skip,
+            Request::Snapshot,
+            Request::Resume { last_seq: 1 },
+        ]
+    }
+
+    /// the names of the requests are taken from the JSON schema, so a new request can't be left out
+    #[cfg(feature = "ssr")]
+    #[test]
+    fn every_kind_of_request_is_tested() {
+        use std::collections::BTreeSet;
+
+        let tested = one_of_each()
+            .iter()
+            .map(|request| match serde_json::to_value(request).unwrap() {
+                serde_json::Value::String(name) => name,
+                serde_json::Value::Object(request) => request.keys().next().unwrap().clone(),
+                request => panic!("a request is sent as a string or an object: {}", request),
+            })
+            .collect::<BTreeSet<String>>();
+
+        let schema = serde_json::to_value(schemars::schema_for!(Request)).unwrap();
+        let mut all = BTreeSet::new();
+        for variant in schema["oneOf"].as_array().unwrap() {
+            // the requests without fields are strings, the others objects with the name as the only key
+            let names = variant["enum"]
+                .as_array()
+                .or_else(|| variant["required"].as_array())
+                .unwrap();
+            all.extend(names.iter().map(|name| name.as_str().unwrap().to_string()));
+        }
+
+        assert_eq!(tested, all);
+    }
+
+    #[test]
+    fn the_roles_match_the_ids() {
+        for permission in Permission::ALL {
+            let role = Role::CoHost(permission);
+            assert!(role.has(&co_host(vec![permission])));
+            assert!(role.has(&co_host(Permission::ALL.to_vec())));
+            assert!(!role.has(&user()));
+            assert!(!role.has(&host()));
+            assert!(!role.has(&general()));
+            let others = Permission::ALL.into_iter().filter(|p| *p != permission);
+            assert!(!role.has(&co_host(others.collect())));
+        }
+
+        assert!(Role::Host.has(&host()));
+        assert!(!Role::Host.has(&co_host(Permission::ALL.to_vec())));
+        assert!(!Role::Host.has(&general()));
+
+        assert!(Role::User.has(&user()));
+        assert!(Role::User.has(&co_host(Permission::ALL.to_vec())));
+        assert!(!Role::User.has(&host()));
+        assert!(!Role::User.has(&general()));
+    }
+
+    /// who may send each request, written out instead of taken from `allowed_roles`,
+    /// the columns are the host, a user, then a co-host with only one of `Permission::ALL` each
+    #[test]
+    fn the_permission_table() {
+        use std::mem::discriminant;
+        const T: bool = true;
+        const F: bool = false;
+
+        let song_id = || "song".to_string();
+        let user_id = || "user".to_string();
+        #[rustfmt::skip]
+        let table = [
+            //                                                            host user kick skip approve settings
+            (Request::KickUser { user_id: user_id() },                     [T,   T,   T,   T,   T,      T]),
+            (Request::AddSong { song_id: song_id() },                      [T,   T,   T,   T,   T,      T]),
+            (Request::RemoveSong { song_id: song_id() },                   [T,   T,   T,   T,   T,      T]),
+            (Request::AddVote { song_id: song_id() },                      [F,   T,   T,   T,   T,      T]),
+            (Request::AddDownvote { song_id: song_id() },                  [F,   T,   T,   T,   T,      T]),
+            (Request::RemoveVote { song_id: song_id() },                   [F,   T,   T,   T,   T,      T]),
+            (Request::Search { query: "query".to_string(), id: "search".to_string() },
+                                                                           [T,   T,   T,   T,   T,      T]),
+            (Request::Position { percentage: 0.5 },                        [T,   F,   F,   F,   F,      F]),
+            (Request::VoteSkip,                                            [F,   T,   T,   T,   T,      T]),
+            (Request::UpdateSettings { settings: JamSettings::default() }, [T,   F,   F,   F,   F,      T]),
+            (Request::ApproveSong { song_id: song_id() },                  [T,   F,   F,   F,   T,      F]),
+            (Request::RejectSong { song_id: song_id() },                   [T,   F,   F,   F,   T,      F]),
+            (Request::PinSong { song_id: song_id(), pinned: true },        [T,   F,   F,   F,   T,      F]),
+            (Request::BanUser { user_id: user_id() },                      [T,   F,   T,   F,   F,      F]),
+            (Request::UnbanUser { ban_id: "ban".to_string() },             [T,   F,   T,   F,   F,      F]),
+            (Request::AdmitUser { user_id: user_id() },                    [T,   F,   T,   F,   F,      F]),
+            (Request::RejectUser { user_id: user_id() },                   [T,   F,   T,   F,   F,      F]),
+            (Request::CreateInvite { hours: None, uses: None },            [T,   F,   F,   F,   F,      F]),
+            (Request::DeleteInvite { invite_id: "invite".to_string() },    [T,   F,   F,   F,   F,      F]),
+            (Request::SetPermissions { user_id: user_id(), permissions: vec![] },
+                                                                           [T,   F,   F,   F,   F,      F]),
+            (Request::Skip,                                                [T,   F,   F,   T,   F,      F]),
+            (Request::Snapshot,                                            [T,   T,   T,   T,   T,      T]),
+            (Request::Resume { last_seq: 1 },                              [T,   T,   T,   T,   T,      T]),
+        ];
+
+        let mut columns = vec![("host".to_string(), host()), ("user".to_string(), user())];
+        for permission in Permission::ALL {
+            columns.push((
+                format!(
+                    "co-host who can {}",
+                    permission.display_name().to_lowercase()
+                ),
+                co_host(vec![permission]),
+            ));
+        }
+
+        // a row for every kind of request
+        for request in one_of_each() {
+            assert!(
+                table
+                    .iter()
+                    .any(|(row, _)| discriminant(row) == discriminant(&request)),
+                "{:?} is missing from the table",
+                request
+            );
+        }
+        for (request, allowed) in &table {
+            for ((name, id), expected) in columns.iter().zip(allowed) {
+                assert_eq!(
+                    request.is_allowed(id),
+                    *expected,
+                    "{:?} sent by the {}",
+                    request,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_request_can_be_sent_by_someone() {
+        for request in one_of_each() {
+            assert!(
+                ids().iter().any(|(_, id)| request.is_allowed(id)),
+                "{:?}",
+                request
+            );
+        }
+    }
+
+    #[test]
+    fn the_general_id_is_never_allowed() {
+        for request in one_of_each() {
+            assert!(!request.is_allowed(&general()), "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn only_the_host_runs_the_jam() {
+        let requests = [
+            Request::Position { percentage: 0.5 },
+            Request::CreateInvite {
+                hours: None,
+                uses: None,
+            },
+            Request::DeleteInvite {
+                invite_id: "invite".to_string(),
+            },
+            Request::SetPermissions {
+                user_id: "user".to_string(),
+                permissions: vec![],
+            },
+        ];
+        for request in requests {
+            assert!(request.is_allowed(&host()), "{:?}", request);
+            assert!(
+                !request.is_allowed(&co_host(Permission::ALL.to_vec())),
+                "{:?}",
+                request
+            );
+        }
+    }
+
+    #[test]
+    fn only_users_vote() {
+        let requests = [
+            Request::AddVote {
+                song_id: "song".to_string(),
+            },
+            Request::AddDownvote {
+                song_id: "song".to_string(),
+            },
+            Request::RemoveVote {
+                song_id: "song".to_string(),
+            },
+            Request::VoteSkip,
+        ];
+        for request in requests {
+            assert!(request.is_allowed(&user()), "{:?}", request);
+            assert!(!request.is_allowed(&host()), "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn the_error_names_who_is_allowed() {
+        let message = Request::Skip.forbidden_error();
+        assert!(message.contains("the host"));
+        assert!(message.contains("co-hosts allowed to skip songs"));
+    }
+}
@@ -3,6 +3,7 @@ use super::super::Song;
 use std::vec::Vec;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub struct SearchResult {
     pub songs: Vec<Song>,
     pub search_id: String,
@@ -0,0 +1,117 @@
+use super::{Request, Update};
+
+/// Keeps track of the `Update::seq` the client got, to notice when it missed an update
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sequence {
+    last: Option<u64>,
+    /// a snapshot or the missed updates were asked for and haven't come yet, so they aren't asked for again
+    waiting_for_snapshot: bool,
+}
+
+impl Sequence {
+    /// returns true if an update was missed, then the client has to send `Request::Snapshot`
+    pub fn missed(&mut self, update: &Update) -> bool {
+        let Some(seq) = update.seq else {
+            return false;
+        };
+        if update.snapshot.is_some() || update.resumed.is_some() {
+            self.last = Some(seq);
+            self.waiting_for_snapshot = false;
+            return false;
+        }
+
+        let missed = self.last.is_some_and(|last| seq > last + 1);
+        self.last = Some(self.last.map_or(seq, |last| last.max(seq)));
+        if missed && !self.waiting_for_snapshot {
+            self.waiting_for_snapshot = true;
+            return true;
+        }
+        false
+    }
+
+    /// what to ask for once the socket is connected, the updates missed while it wasn't,
+    /// or everything if it is the first time
+    pub fn connected(&mut self) -> Request {
+        self.waiting_for_snapshot = true;
+        match self.last {
+            Some(last_seq) => Request::Resume { last_seq },
+            None => Request::Snapshot,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(seq: u64) -> Update {
+        Update {
+            seq: Some(seq),
+            ..Update::new()
+        }
+    }
+
+    #[test]
+    fn nothing_is_missed_in_order() {
+        let mut sequence = Sequence::default();
+        for seq in 7..12 {
+            assert!(!sequence.missed(&update(seq)), "seq {}", seq);
+        }
+    }
+
+    #[test]
+    fn an_update_without_a_seq_is_not_counted() {
+        let mut sequence = Sequence::default();
+        assert!(!sequence.missed(&update(1)));
+        assert!(!sequence.missed(&Update::new()));
+        assert!(!sequence.missed(&update(2)));
+    }
+
+    #[test]
+    fn an_update_that_came_again_is_not_a_gap() {
+        let mut sequence = Sequence::default();
+        for seq in [1, 2, 2, 1, 3] {
+            assert!(!sequence.missed(&update(seq)), "seq {}", seq);
+        }
+    }
+
+    #[test]
+    fn a_gap_asks_for_one_snapshot() {
+        let mut sequence = Sequence::default();
+        assert!(!sequence.missed(&update(1)));
+        assert!(sequence.missed(&update(3)));
+        // the snapshot was asked for already
+        assert!(!sequence.missed(&update(5)));
+
+        assert!(!sequence.missed(&Update::new().snapshot(5)));
+        assert!(!sequence.missed(&update(6)));
+        assert!(sequence.missed(&update(8)));
+    }
+
+    #[test]
+    fn resumed_updates_fill_the_gap() {
+        let mut sequence = Sequence::default();
+        assert!(!sequence.missed(&update(1)));
+        assert!(sequence.missed(&update(3)));
+        assert!(!sequence.missed(&Update::resumed(Vec::new(), 4).unwrap()));
+        assert!(!sequence.missed(&update(5)));
+        assert!(sequence.missed(&update(7)));
+    }
+
+    #[test]
+    fn a_new_socket_asks_for_a_snapshot_and_a_reconnected_one_resumes() {
+        let mut sequence = Sequence::default();
+        assert!(matches!(sequence.connected(), Request::Snapshot));
+        assert!(!sequence.missed(&Update::new().snapshot(10)));
+        assert!(!sequence.missed(&update(11)));
+
+        assert!(matches!(
+            sequence.connected(),
+            Request::Resume { last_seq: 11 }
+        ));
+        // the missed updates were asked for already
+        assert!(!sequence.missed(&update(14)));
+        assert!(!sequence.missed(&Update::resumed(Vec::new(), 14).unwrap()));
+        assert!(sequence.missed(&update(16)));
+    }
+}
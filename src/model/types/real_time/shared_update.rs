@@ -0,0 +1,333 @@
+use super::{Delta, Update};
+use crate::model::functions;
+use crate::model::types::*;
+use std::collections::HashMap;
+
+/// What changed in a jam, loaded once for every socket of the jam.
+/// The songs and votes are loaded without anyone's own votes, and everything the host can see is in it,
+/// `SharedUpdate::for_id` fills in the own votes and leaves out what the id is not allowed to see.
+/// The `Hub` turns the songs, votes and users into deltas before it is sent
+#[derive(Debug, Clone, Default)]
+pub struct SharedUpdate {
+    pub update: Update,
+    /// the votes of every user by song id, by user id, loaded with the songs or the votes
+    pub votes_of_users: Option<HashMap<String, HashMap<String, YourVote>>>,
+    /// the users who voted to skip, loaded with the skip votes
+    pub skip_voters: Option<Vec<String>>,
+}
+
+impl SharedUpdate {
+    pub async fn from_changed<'e>(
+        changed: real_time::Changed,
+        jam_id: &str,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Self {
+        use tokio::sync::Mutex;
+
+        let id = &Id::new(IdType::General, jam_id.to_string());
+        let update = Update::new();
+        let transaction = Mutex::new(transaction);
+
+        let users_future = async {
+            if changed.users {
+                let mut transaction = transaction.lock().await;
+                update.clone().users_from_jam(id, &mut ***transaction).await
+            } else {
+                update.clone()
+            }
+        };
+
+        let songs_future = async {
+            if changed.songs {
+                let mut transaction = transaction.lock().await;
+                update.clone().songs_from_jam(id, *transaction).await
+            } else {
+                update.clone()
+            }
+        };
+
+        let votes_future = async {
+            if changed.votes {
+                let mut transaction = transaction.lock().await;
+                update.clone().votes_from_jam(id, &mut transaction).await
+            } else {
+                update.clone()
+            }
+        };
+
+        let ended_future = async {
+            if changed.ended {
+                update.clone().ended()
+            } else {
+                update.clone()
+            }
+        };
+
+        let position_future = async {
+            if changed.position {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .position_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let current_song_future = async {
+            if changed.current_song {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .current_song_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let history_future = async {
+            if changed.history {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .history_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        // the amount of votes needed depends on how many users there are
+        let skip_votes_future = async {
+            if changed.skip_votes || changed.users {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .skip_votes_from_jam(id, &mut transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let settings_future = async {
+            if changed.settings {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .settings_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let pending_future = async {
+            if changed.pending {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .pending_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let banned_future = async {
+            if changed.banned {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .banned_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let join_requests_future = async {
+            if changed.join_requests {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .join_requests_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let invites_future = async {
+            if changed.invites {
+                let mut transaction = transaction.lock().await;
+                update
+                    .clone()
+                    .invites_from_jam(id.jam_id(), &mut ***transaction)
+                    .await
+            } else {
+                update.clone()
+            }
+        };
+
+        let (
+            users_update,
+            songs_update,
+            votes_update,
+            ended_update,
+            position_update,
+            current_song_update,
+            history_update,
+            skip_votes_update,
+            settings_update,
+            pending_update,
+            banned_update,
+            join_requests_update,
+            invites_update,
+        ) = tokio::join!(
+            users_future,
+            songs_future,
+            votes_future,
+            ended_future,
+            position_future,
+            current_song_future,
+            history_future,
+            skip_votes_future,
+            settings_future,
+            pending_future,
+            banned_future,
+            join_requests_future,
+            invites_future
+        );
+
+        let mut update = update
+            .merge_with_other(users_update, false)
+            .merge_with_other(songs_update, false)
+            .merge_with_other(votes_update, false)
+            .merge_with_other(ended_update, false)
+            .merge_with_other(position_update, false)
+            .merge_with_other(current_song_update, false)
+            .merge_with_other(history_update, false)
+            .merge_with_other(skip_votes_update, false)
+            .merge_with_other(settings_update, false)
+            .merge_with_other(pending_update, false)
+            .merge_with_other(banned_update, false)
+            .merge_with_other(join_requests_update, false)
+            .merge_with_other(invites_update, false);
+
+        let transaction = transaction.into_inner();
+
+        let votes_of_users = if update.songs.is_some() || update.votes.is_some() {
+            match functions::get_votes_of_users(jam_id, &mut **transaction).await {
+                Ok(votes_of_users) => Some(votes_of_users),
+                Err(e) => {
+                    update = update.error(e.into());
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let skip_voters = if update.skip_votes.is_some() {
+            match functions::get_skip_voters(jam_id, &mut **transaction).await {
+                Ok(skip_voters) => Some(skip_voters),
+                Err(e) => {
+                    update = update.error(e.into());
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Self {
+            update,
+            votes_of_users,
+            skip_voters,
+        }
+    }
+
+    /// the update as the id would have loaded it by itself
+    pub fn for_id(&self, id: &Id) -> Update {
+        let mut update = self.update.clone();
+
+        let your_votes = match &id.id {
+            IdType::User(user_id) => Some(
+                self.votes_of_users
+                    .as_ref()
+                    .and_then(|votes_of_users| votes_of_users.get(user_id))
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            IdType::Host(_) | IdType::General => None,
+        };
+        let your_vote = |song_id: &str| {
+            your_votes
+                .as_ref()
+                .map(|votes| votes.get(song_id).copied().unwrap_or_default())
+        };
+
+        let fill_in = |song: &mut Song| {
+            // a user only gets to know which songs are their own
+            song.user_id = match &id.id {
+                IdType::User(user_id) => song.user_id.take().filter(|owner| owner == user_id),
+                IdType::Host(_) => None,
+                IdType::General => song.user_id.take(),
+            };
+            if let Some(song_id) = &song.id {
+                song.votes.have_you_voted = your_vote(song_id);
+            }
+        };
+
+        if let Some(songs) = update.songs.as_mut() {
+            songs.iter_mut().for_each(&fill_in);
+        }
+
+        if let Some(votes) = update.votes.as_mut() {
+            for (song_id, vote) in votes.iter_mut() {
+                vote.have_you_voted = your_vote(song_id);
+            }
+        }
+
+        for delta in update.deltas.iter_mut() {
+            match delta {
+                Delta::SongAdded(song) => fill_in(song),
+                Delta::VoteChanged { song_id, vote } => vote.have_you_voted = your_vote(song_id),
+                Delta::SongRemoved { .. } | Delta::UserJoined(_) | Delta::UserLeft { .. } => {}
+            }
+        }
+
+        if let Some(skip_votes) = update.skip_votes.as_mut() {
+            skip_votes.have_you_voted = match &id.id {
+                IdType::User(user_id) => Some(
+                    self.skip_voters
+                        .as_ref()
+                        .is_some_and(|voters| voters.contains(user_id)),
+                ),
+                IdType::Host(_) | IdType::General => None,
+            };
+        }
+
+        // only the ones who can change the settings get to see the passcode
+        if !id.can(Permission::ChangeSettings) {
+            if let Some(settings) = update.settings.as_mut() {
+                settings.passcode = None;
+            }
+        }
+        if !id.can(Permission::ApproveSongs) {
+            update.pending = None;
+        }
+        if !id.can(Permission::Kick) {
+            update.banned = None;
+            update.join_requests = None;
+        }
+        if !id.is_host() {
+            update.invites = None;
+        }
+
+        update
+    }
+}
@@ -1,4 +1,5 @@
-use super::SearchResult;
+use super::protocol::flag;
+use super::{Delta, Protocol, SearchResult};
 #[cfg(feature = "ssr")]
 use crate::model::functions;
 use crate::model::types::*;
@@ -8,17 +9,51 @@ use serde::{Deserialize, Serialize};
 /// if the field is some then it was updated
 /// if the field is none then it was not updated
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub struct Update {
     pub users: Option<Vec<User>>,
     pub songs: Option<Vec<Song>>,
     pub errors: Vec<Error>,
     pub votes: Option<Votes>,
     pub search: Option<SearchResult>,
+    #[serde(with = "flag")]
+    #[cfg_attr(feature = "ssr", schemars(with = "bool"))]
     pub ended: Option<()>,
     /// the percentage of the current song
     pub position: Option<f32>,
     /// the current song may be null, so there is an option inside an option
     pub current_song: Option<Option<Song>>,
+    /// the songs that were played, the most recent first
+    pub history: Option<Vec<PlayedSong>>,
+    pub skip_votes: Option<SkipVotes>,
+    pub settings: Option<JamSettings>,
+    /// the songs waiting for the host to approve them, only sent to the host and co-hosts who can approve songs
+    pub pending: Option<Vec<Song>>,
+    /// the users that were banned from the jam, only sent to the host and co-hosts who can kick
+    pub banned: Option<Vec<BannedUser>>,
+    /// the users in the waiting room, only sent to the host and co-hosts who can kick
+    pub join_requests: Option<Vec<User>>,
+    /// the user is in the waiting room, nothing else is sent to them until the host admits them
+    #[serde(with = "flag")]
+    #[cfg_attr(feature = "ssr", schemars(with = "bool"))]
+    pub waiting: Option<()>,
+    /// the invite links of the jam, only sent to the host
+    pub invites: Option<Vec<Invite>>,
+    /// what changed in the songs, votes and users the client already has, applied after the whole lists if those are sent too
+    pub deltas: Vec<Delta>,
+    /// the number of the update in the jam, one more than the last one, if there is a gap the client missed an update
+    /// and asks for a snapshot with `Request::Snapshot`, after reconnecting it asks for what it missed with `Request::Resume`
+    pub seq: Option<u64>,
+    /// everything the client can see, answering `Request::Snapshot`, the updates up to `seq` are in it
+    #[serde(with = "flag")]
+    #[cfg_attr(feature = "ssr", schemars(with = "bool"))]
+    pub snapshot: Option<()>,
+    /// the updates the client missed, answering `Request::Resume`, it goes on from `seq`
+    #[serde(with = "flag")]
+    #[cfg_attr(feature = "ssr", schemars(with = "bool"))]
+    pub resumed: Option<()>,
+    /// the version and encoding the socket speaks, only in the first update
+    pub protocol: Option<Protocol>,
 }
 
 impl Update {
@@ -103,6 +138,13 @@ impl Update {
         }
     }
 
+    pub fn waiting(self) -> Self {
+        Self {
+            waiting: Some(()),
+            ..self
+        }
+    }
+
     pub fn position(self, percentage: f32) -> Self {
         Self {
             position: Some(percentage),
@@ -141,6 +183,199 @@ impl Update {
         }
     }
 
+    pub fn history(self, history: Vec<PlayedSong>) -> Self {
+        Self {
+            history: Some(history),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn history_from_jam<'e>(
+        self,
+        jam_id: &str,
+        executor: impl sqlx::PgExecutor<'e>,
+    ) -> Self {
+        match functions::get_history(jam_id, Some(functions::RECENTLY_PLAYED), executor).await {
+            Ok(history) => self.history(history),
+            Err(e) => self.error(e.into()),
+        }
+    }
+
+    pub fn skip_votes(self, skip_votes: SkipVotes) -> Self {
+        Self {
+            skip_votes: Some(skip_votes),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn skip_votes_from_jam<'e>(
+        self,
+        id: &Id,
+        transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
+    ) -> Self {
+        match functions::get_skip_votes(transaction, id).await {
+            Ok(skip_votes) => self.skip_votes(skip_votes),
+            Err(e) => self.error(e),
+        }
+    }
+
+    pub fn settings(self, settings: JamSettings) -> Self {
+        Self {
+            settings: Some(settings),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn settings_from_jam<'e>(
+        self,
+        jam_id: &str,
+        executor: impl sqlx::PgExecutor<'e>,
+    ) -> Self {
+        match functions::get_settings(jam_id, executor).await {
+            Ok(settings) => self.settings(settings),
+            Err(e) => self.error(e),
+        }
+    }
+
+    pub fn pending(self, pending: Vec<Song>) -> Self {
+        Self {
+            pending: Some(pending),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn pending_from_jam<'e>(
+        self,
+        jam_id: &str,
+        executor: impl sqlx::PgExecutor<'e>,
+    ) -> Self {
+        match functions::get_pending_songs(jam_id, executor).await {
+            Ok(pending) => self.pending(pending),
+            Err(e) => self.error(e.into()),
+        }
+    }
+
+    pub fn banned(self, banned: Vec<BannedUser>) -> Self {
+        Self {
+            banned: Some(banned),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn banned_from_jam<'e>(
+        self,
+        jam_id: &str,
+        executor: impl sqlx::PgExecutor<'e>,
+    ) -> Self {
+        match functions::get_banned_users(jam_id, executor).await {
+            Ok(banned) => self.banned(banned),
+            Err(e) => self.error(e.into()),
+        }
+    }
+
+    pub fn join_requests(self, join_requests: Vec<User>) -> Self {
+        Self {
+            join_requests: Some(join_requests),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn join_requests_from_jam<'e>(
+        self,
+        jam_id: &str,
+        executor: impl sqlx::PgExecutor<'e>,
+    ) -> Self {
+        match functions::get_join_requests(jam_id, executor).await {
+            Ok(join_requests) => self.join_requests(join_requests),
+            Err(e) => self.error(e.into()),
+        }
+    }
+
+    pub fn invites(self, invites: Vec<Invite>) -> Self {
+        Self {
+            invites: Some(invites),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub async fn invites_from_jam<'e>(
+        self,
+        jam_id: &str,
+        executor: impl sqlx::PgExecutor<'e>,
+    ) -> Self {
+        match functions::get_invites(jam_id, executor).await {
+            Ok(invites) => self.invites(invites),
+            Err(e) => self.error(e.into()),
+        }
+    }
+
+    pub fn snapshot(self, seq: u64) -> Self {
+        Self {
+            seq: Some(seq),
+            snapshot: Some(()),
+            ..self
+        }
+    }
+
+    pub fn protocol(self, protocol: Protocol) -> Self {
+        Self {
+            protocol: Some(protocol),
+            ..self
+        }
+    }
+
+    /// The missed updates as one, so the client applies all of them at once.
+    /// None if one of them has a whole list in it, the deltas before it would be applied after it, then a snapshot is sent instead
+    pub fn resumed(missed: impl IntoIterator<Item = Update>, seq: u64) -> Option<Self> {
+        let mut resumed = Update::new();
+        for mut update in missed {
+            if update.songs.is_some() || update.votes.is_some() || update.users.is_some() {
+                return None;
+            }
+            // the errors were about what someone did back then
+            update.errors.clear();
+            resumed = resumed.merge_with_other(update, true);
+        }
+        Some(Self {
+            seq: Some(seq),
+            resumed: Some(()),
+            ..resumed
+        })
+    }
+
+    /// the user as they are in this update, if it has them
+    pub fn find_user(&self, user_id: &str) -> Option<&User> {
+        let joined = self.deltas.iter().rev().find_map(|delta| match delta {
+            Delta::UserJoined(user) if user.id == user_id => Some(user),
+            _ => None,
+        });
+        joined.or_else(|| {
+            self.users
+                .as_ref()
+                .and_then(|users| users.iter().find(|user| user.id == user_id))
+        })
+    }
+
+    /// true if the user was kicked or left, by the whole list of users or a delta
+    pub fn has_left(&self, user_id: &str) -> bool {
+        let left = self
+            .deltas
+            .iter()
+            .any(|delta| matches!(delta, Delta::UserLeft { user_id: id } if id == user_id));
+        let not_in_users = self
+            .users
+            .as_ref()
+            .is_some_and(|users| !users.iter().any(|user| user.id == user_id));
+        left || not_in_users
+    }
+
     pub fn merge_with_other(self, other: Self, prioritize_other: bool) -> Self {
         if prioritize_other {
             Self {
@@ -152,6 +387,19 @@ impl Update {
                 ended: other.ended.or(self.ended),
                 position: other.position.or(self.position),
                 current_song: other.current_song.or(self.current_song),
+                history: other.history.or(self.history),
+                skip_votes: other.skip_votes.or(self.skip_votes),
+                settings: other.settings.or(self.settings),
+                pending: other.pending.or(self.pending),
+                banned: other.banned.or(self.banned),
+                join_requests: other.join_requests.or(self.join_requests),
+                invites: other.invites.or(self.invites),
+                waiting: other.waiting.or(self.waiting),
+                deltas: self.deltas.into_iter().chain(other.deltas).collect(),
+                seq: other.seq.or(self.seq),
+                snapshot: other.snapshot.or(self.snapshot),
+                resumed: other.resumed.or(self.resumed),
+                protocol: other.protocol.or(self.protocol),
             }
         } else {
             Self {
@@ -163,103 +411,41 @@ impl Update {
                 ended: self.ended.or(other.ended),
                 position: self.position.or(other.position),
                 current_song: self.current_song.or(other.current_song),
+                history: self.history.or(other.history),
+                skip_votes: self.skip_votes.or(other.skip_votes),
+                settings: self.settings.or(other.settings),
+                pending: self.pending.or(other.pending),
+                banned: self.banned.or(other.banned),
+                join_requests: self.join_requests.or(other.join_requests),
+                invites: self.invites.or(other.invites),
+                waiting: self.waiting.or(other.waiting),
+                deltas: self.deltas.into_iter().chain(other.deltas).collect(),
+                seq: self.seq.or(other.seq),
+                snapshot: self.snapshot.or(other.snapshot),
+                resumed: self.resumed.or(other.resumed),
+                protocol: self.protocol.or(other.protocol),
             }
         }
     }
 
+    /// loads what changed for the id, the sockets get the same from the `Hub` without loading it each
     #[cfg(feature = "ssr")]
     pub async fn from_changed<'e>(
         changed: real_time::Changed,
         id: &Id,
         transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
     ) -> Self {
-        use tokio::sync::Mutex;
-
-        let update = Update::new();
-        let transaction = Mutex::new(transaction);
-
-        let users_future = async {
-            if changed.users {
-                let mut transaction = transaction.lock().await;
-                update.clone().users_from_jam(id, &mut ***transaction).await
-            } else {
-                update.clone()
-            }
-        };
-
-        let songs_future = async {
-            if changed.songs {
-                let mut transaction = transaction.lock().await;
-                update.clone().songs_from_jam(id, *transaction).await
-            } else {
-                update.clone()
+        if let IdType::User(user_id) = &id.id {
+            match functions::is_user_waiting(user_id, &mut **transaction).await {
+                Ok(true) => return Update::new().waiting(),
+                Ok(false) => {}
+                Err(e) => return Update::new().error(e.into()),
             }
-        };
-
-        let votes_future = async {
-            if changed.votes {
-                let mut transaction = transaction.lock().await;
-                update.clone().votes_from_jam(id, &mut transaction).await
-            } else {
-                update.clone()
-            }
-        };
+        }
 
-        let ended_future = async {
-            if changed.ended {
-                update.clone().ended()
-            } else {
-                update.clone()
-            }
-        };
-
-        let position_future = async {
-            if changed.position {
-                let mut transaction = transaction.lock().await;
-                update
-                    .clone()
-                    .position_from_jam(id.jam_id(), &mut ***transaction)
-                    .await
-            } else {
-                update.clone()
-            }
-        };
-
-        let current_song_future = async {
-            if changed.current_song {
-                let mut transaction = transaction.lock().await;
-                update
-                    .clone()
-                    .current_song_from_jam(id.jam_id(), &mut ***transaction)
-                    .await
-            } else {
-                update.clone()
-            }
-        };
-
-        let (
-            users_update,
-            songs_update,
-            votes_update,
-            ended_update,
-            position_update,
-            current_song_update,
-        ) = tokio::join!(
-            users_future,
-            songs_future,
-            votes_future,
-            ended_future,
-            position_future,
-            current_song_future
-        );
-
-        update
-            .merge_with_other(users_update, false)
-            .merge_with_other(songs_update, false)
-            .merge_with_other(votes_update, false)
-            .merge_with_other(ended_update, false)
-            .merge_with_other(position_update, false)
-            .merge_with_other(current_song_update, false)
+        real_time::SharedUpdate::from_changed(changed.visible_to(id), id.jam_id(), transaction)
+            .await
+            .for_id(id)
     }
 }
 
@@ -296,3 +482,65 @@ impl<T: Into<Update>> From<Result<T, sqlx::Error>> for Update {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(song_id: &str) -> Delta {
+        Delta::SongRemoved {
+            song_id: song_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn the_missed_updates_are_resumed_as_one() {
+        let missed = vec![
+            Update {
+                deltas: vec![removed("a")],
+                ..Update::new().position(0.1)
+            },
+            Update::new().error(Error::Forbidden("back then".to_string())),
+            Update {
+                deltas: vec![removed("b")],
+                ..Update::new().position(0.2)
+            },
+        ];
+
+        let resumed = Update::resumed(missed, 42).unwrap();
+        assert_eq!(resumed.seq, Some(42));
+        assert!(resumed.resumed.is_some());
+        assert!(resumed.snapshot.is_none());
+        // the deltas in the order they happened, the latest of the rest
+        assert_eq!(resumed.deltas, vec![removed("a"), removed("b")]);
+        assert_eq!(resumed.position, Some(0.2));
+        assert!(resumed.errors.is_empty());
+    }
+
+    #[test]
+    fn nothing_missed_is_resumed_too() {
+        let resumed = Update::resumed(Vec::new(), 7).unwrap();
+        assert_eq!(resumed.seq, Some(7));
+        assert!(resumed.resumed.is_some());
+        assert!(resumed.deltas.is_empty());
+    }
+
+    #[test]
+    fn a_whole_list_in_the_missed_updates_needs_a_snapshot() {
+        let with_list = [
+            Update::new().songs(Vec::new()),
+            Update::new().votes(Votes::new()),
+            Update::new().users(Vec::new()),
+        ];
+        for list in with_list {
+            let missed = vec![
+                Update {
+                    deltas: vec![removed("a")],
+                    ..Update::new()
+                },
+                list,
+            ];
+            assert!(Update::resumed(missed, 42).is_none());
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// What the client keeps after it joins a jam or connects as a host, the id is only to know who it is,
+/// the token that proves it to the server is in an HttpOnly cookie, so the scripts of the page can't read it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    /// the id of the user or the host
+    pub id: String,
+    /// in milliseconds, the token is not accepted after this
+    pub expires_at: i64,
+}
+
+impl Session {
+    /// the time is in milliseconds, it is passed in because the client and the server get it differently
+    pub fn has_expired(&self, now: i64) -> bool {
+        self.expires_at < now
+    }
+}
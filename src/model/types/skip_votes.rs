@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// the tally of the votes to skip the current song
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub struct SkipVotes {
+    pub votes: u32,
+    /// how many votes are needed to skip the song
+    pub needed: u32,
+    ///none if requested by the host, or a unknown person
+    pub have_you_voted: Option<bool>,
+}
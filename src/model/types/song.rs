@@ -2,7 +2,8 @@ use crate::model::types::*;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub struct Song {
     /// is some if it is in the jam, is none if for example it came from search
     pub id: Option<String>,
@@ -12,9 +13,13 @@ pub struct Song {
     pub name: String,
     pub artists: Vec<String>,
     pub album: String,
+    /// in milliseconds
     pub duration: u32,
     pub image_url: String,
     pub votes: Vote,
+    pub explicit: bool,
+    /// pinned by the host to be played next, before the songs with more votes
+    pub pinned: bool,
 }
 
 
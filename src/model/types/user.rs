@@ -1,8 +1,22 @@
+use super::Permission;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub struct User {
     pub id: String,
     pub jam_id: String,
     pub name: String,
+    /// what the host let the user do, a user with any permissions is a co-host
+    pub permissions: Vec<Permission>,
+}
+
+/// Someone the host banned, they are recognized by the device they joined from
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub struct BannedUser {
+    pub id: String,
+    pub name: String,
+    /// in milliseconds
+    pub banned_at: i64,
 }
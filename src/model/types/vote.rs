@@ -2,10 +2,32 @@ use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
 pub struct Vote {
-    pub votes: u64,
+    /// the net score, upvotes minus downvotes
+    pub votes: i64,
     ///none if requested by the host, or a unknown person
-    pub have_you_voted: Option<bool>,
+    pub have_you_voted: Option<YourVote>,
 }
 
-pub type Votes = HashMap<String, Vote>;
\ No newline at end of file
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ssr", derive(schemars::JsonSchema))]
+pub enum YourVote {
+    #[default]
+    NotVoted,
+    Up,
+    Down,
+}
+
+impl YourVote {
+    /// the value of a vote in the `value` column of `votes`
+    pub fn from_value(value: i16) -> Self {
+        match value {
+            v if v > 0 => YourVote::Up,
+            v if v < 0 => YourVote::Down,
+            _ => YourVote::NotVoted,
+        }
+    }
+}
+
+pub type Votes = HashMap<String, Vote>;
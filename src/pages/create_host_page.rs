@@ -1,12 +1,36 @@
+use crate::model::types::Session;
 use leptos::{logging::*, prelude::*};
 use leptos_router::{hooks::*, *};
 
+/// the state is the host id signed by `oauth_state` in `redirect_to_spotify_oauth`, the host gets a session for it
 #[server]
-async fn create_host(code: String, host_id: String) -> Result<(), ServerFnError> {
+async fn create_host(code: String, state: String) -> Result<Session, ServerFnError> {
     use crate::model::functions;
     use crate::model::AppState;
     let app_state = expect_context::<AppState>();
 
+    let host_id = match functions::verify_oauth_state(&state, &app_state.secret_key) {
+        Ok(host_id) => host_id,
+        Err(e) => return Err(ServerFnError::Request(e.to_string())),
+    };
+    let session = functions::create_session(&host_id);
+
+    if !app_state.music_provider.is_spotify() {
+        let host_exists = sqlx::query!("SELECT EXISTS(SELECT 1 FROM hosts WHERE id = $1)", host_id)
+            .fetch_one(&app_state.db.pool)
+            .await?
+            .exists
+            .unwrap_or(false);
+        if !host_exists {
+            return Err(ServerFnError::Request(format!(
+                "host with id {} does not exist",
+                host_id
+            )));
+        }
+        set_session_cookie(&session, &app_state.secret_key);
+        return Ok(session);
+    }
+
     if let Err(e) = functions::create_host(
         code,
         host_id,
@@ -20,37 +44,49 @@ async fn create_host(code: String, host_id: String) -> Result<(), ServerFnError>
         return Err(ServerFnError::ServerError(format!("{:#?}", e)));
     }
 
-    Ok(())
+    set_session_cookie(&session, &app_state.secret_key);
+    Ok(session)
+}
+
+/// the token of the host session is only sent as a cookie, see `session_cookie`
+#[cfg(feature = "ssr")]
+fn set_session_cookie(session: &Session, secret_key: &str) {
+    use http::{header, HeaderValue};
+    use leptos_axum::ResponseOptions;
+
+    let cookie = crate::model::functions::session_cookie(session, None, secret_key);
+    if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+        expect_context::<ResponseOptions>().append_header(header::SET_COOKIE, cookie);
+    }
 }
 
 #[component]
 pub fn CreateHostPage() -> impl IntoView {
     let queries = use_query_map();
     let code = move || queries.with(|queries| queries.get("code"));
-    let host_id = move || queries.with(|queries| queries.get("state"));
+    let state = move || queries.with(|queries| queries.get("state"));
 
     let create_host_action = Action::new(|input: &(String, String)| {
         use gloo::storage::{LocalStorage, Storage};
 
         let input = input.clone();
         async move {
-            let res = create_host(input.0.clone(), input.1.clone()).await;
-            if res.is_ok() {
-                LocalStorage::set("host_id", input.1)?;
-            }
-            res
+            let session = create_host(input.0.clone(), input.1.clone()).await?;
+            LocalStorage::set("host_session", session)?;
+            Ok::<_, ServerFnError>(())
         }
     });
 
     let (feedback, set_feedback) = signal(String::from("Creating host..."));
 
     Effect::new(move |_| {
-        if let (Some(code), Some(state)) = (code(), host_id()) {
+        // without spotify there is no oauth code, the server checks if it's needed
+        if let (code, Some(state)) = (code().unwrap_or_default(), state()) {
             log!("Creating host with code: {} and state: {}", code, state);
             create_host_action.dispatch((code, state));
             create_host_action.pending();
         } else {
-            set_feedback("Error creating host: missing code or state".to_string());
+            set_feedback("Error creating host: missing state".to_string());
         }
     });
 
@@ -1,4 +1,5 @@
 use crate::components::user::CreateUser;
+use crate::model::types::JoinCredentials;
 use leptos::{either::*, prelude::*, logging::*};
 
 use leptos_router::{hooks::*, params::*, *};
@@ -25,12 +26,31 @@ pub fn CreateUserPage() -> impl IntoView {
     };
     let jam_id = Signal::derive(jam_id);
 
+    /// the passcode from the join island or the invite from a shared link
+    #[derive(PartialEq, Params)]
+    struct Credentials {
+        passcode: Option<String>,
+        invite: Option<String>,
+    }
+    let credentials = use_query::<Credentials>();
+    let credentials = move || {
+        credentials.with(|credentials| {
+            credentials
+                .as_ref()
+                .map(|credentials| JoinCredentials {
+                    passcode: credentials.passcode.clone(),
+                    invite: credentials.invite.clone(),
+                })
+                .unwrap_or_default()
+        })
+    };
+
     view! {
         <div class="create-user-page">
 
             {move || {
                 if let Some(jam_id) = jam_id.get() {
-                    Either::Left(view! { <CreateUser jam_id=jam_id/> })
+                    Either::Left(view! { <CreateUser jam_id=jam_id credentials=credentials()/> })
                 } else {
                     Either::Right(view! { <div class="loading">"Loading..."</div> })
                 }
@@ -1,43 +1,54 @@
-use crate::components::{host::Player, Modal, Share, SongList, SongListAction, UsersBar};
+use crate::components::{
+    general,
+    host::{is_invite_usable, Invites, JoinRequests, LocalPlayer, PendingSongs, Player, Settings},
+    user::Search,
+    History, Modal, Share, SongList, SongListAction, UsersBar,
+};
 use crate::model::types::*;
 use codee::binary::MsgpackSerdeCodec;
 use gloo::storage::{LocalStorage, Storage};
-use leptos::{logging::*, prelude::*};
+use leptos::{
+    either::{Either, EitherOf3},
+    logging::*,
+    prelude::*,
+};
 use leptos_meta::Title;
 use leptos_router::{
     hooks::{use_navigate, use_params_map},
     NavigateOptions,
 };
-use leptos_use::{use_websocket, UseWebSocketReturn};
+use leptos_use::{core::ConnectionReadyState, use_websocket, UseWebSocketReturn};
 
 #[component]
 pub fn HostPage() -> impl IntoView {
     let (error_message, set_error_message) = signal(String::new());
 
-    let (host_id, set_host_id) = signal(String::new());
-    let host_id = Memo::new(move |_| {
-        if host_id.with(String::is_empty) {
-            None
-        } else {
-            Some(host_id.get_untracked())
-        }
-    });
-    Effect::new(move |_| {
-        let host_id: String = LocalStorage::get("host_id").unwrap_or_default();
-        if host_id.is_empty() {
-            let navigator = use_navigate();
-            navigator("/", NavigateOptions::default());
-        }
-        set_host_id(host_id);
-    });
+    // the host session, its token is sent as a cookie instead of the host id
+    let (session, set_session) = signal(None::<Session>);
+    Effect::new(
+        move |_| match LocalStorage::get::<Session>("host_session") {
+            Ok(session) if !session.has_expired(js_sys::Date::now() as i64) => {
+                set_session(Some(session))
+            }
+            _ => {
+                let navigator = use_navigate();
+                navigator("/", NavigateOptions::default());
+            }
+        },
+    );
 
-    let initial_update = Resource::new(host_id, move |host_id| async move {
-        if let Some(host_id) = host_id {
-            get_initial_update(host_id).await
-        } else {
-            Err(ServerFnError::Request("host_id is empty".to_string()))
-        }
-    });
+    let initial_update = Resource::new(
+        move || session.with(Option::is_some),
+        move |has_session| async move {
+            if has_session {
+                get_initial_update(None).await
+            } else {
+                Err(ServerFnError::Request(
+                    "there is no host session".to_string(),
+                ))
+            }
+        },
+    );
 
     let jam_id = move || use_params_map().with(|params| params.get("id"));
     let jam_id = Signal::derive(jam_id);
@@ -64,6 +75,19 @@ pub fn HostPage() -> impl IntoView {
     let (users, set_users) = signal(None);
     let (songs, set_songs) = signal(None::<Vec<Song>>);
     let (votes, set_votes) = signal(Votes::new());
+    let (current_song, set_current_song) = signal(None::<Song>);
+    let (history, set_history) = signal(None::<Vec<PlayedSong>>);
+    let (skip_votes, set_skip_votes) = signal(SkipVotes::default());
+    let (settings, set_settings) = signal(JamSettings::default());
+    let (show_settings, set_show_settings) = signal(false);
+    let (pending, set_pending) = signal(None::<Vec<Song>>);
+    let (banned, set_banned) = signal(Vec::<BannedUser>::new());
+    let (join_requests, set_join_requests) = signal(Vec::<User>::new());
+    let (invites, set_invites) = signal(Vec::<Invite>::new());
+    let (search_result, set_search_result) = signal(None);
+    let (ready_state, set_ready_state) = signal(ConnectionReadyState::Connecting);
+
+    let provider_kind = Resource::new(|| (), |_| get_provider_kind());
 
     let (send_request, set_send_request) = signal(Callback::new(|_: real_time::Request| {
         warn!("wanted to send a message to ws, but the ws is not ready yet");
@@ -78,25 +102,104 @@ pub fn HostPage() -> impl IntoView {
     };
     let remove_song = Callback::new(remove_song);
 
+    let pin_song = move |(song_id, pinned)| {
+        let request = real_time::Request::PinSong { song_id, pinned };
+        send_request.get_untracked().run(request);
+    };
+    let pin_song = Callback::new(pin_song);
+
+    let search = move |(query, id)| {
+        let request = real_time::Request::Search { query, id };
+        send_request.get_untracked().run(request);
+    };
+    let search = Callback::new(search);
+
+    // the songs of the host don't count towards the max song count
+    let add_song = move |song_id| {
+        let request = real_time::Request::AddSong { song_id };
+        send_request.get_untracked().run(request);
+    };
+    let add_song = Callback::new(add_song);
+
     let kick_user = move |id| {
         let request = real_time::Request::KickUser { user_id: id };
         send_request.get_untracked().run(request);
     };
     let kick_user = Callback::new(kick_user);
 
+    let ban_user = move |user_id| {
+        let request = real_time::Request::BanUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let ban_user = Callback::new(ban_user);
+
+    let unban_user = move |ban_id| {
+        let request = real_time::Request::UnbanUser { ban_id };
+        send_request.get_untracked().run(request);
+    };
+    let unban_user = Callback::new(unban_user);
+
+    let set_permissions = move |(user_id, permissions)| {
+        let request = real_time::Request::SetPermissions {
+            user_id,
+            permissions,
+        };
+        send_request.get_untracked().run(request);
+    };
+    let set_permissions = Callback::new(set_permissions);
+
+    let admit_user = move |user_id| {
+        let request = real_time::Request::AdmitUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let admit_user = Callback::new(admit_user);
+
+    let reject_user = move |user_id| {
+        let request = real_time::Request::RejectUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let reject_user = Callback::new(reject_user);
+
+    let create_invite = move |(hours, uses)| {
+        let request = real_time::Request::CreateInvite { hours, uses };
+        send_request.get_untracked().run(request);
+    };
+    let create_invite = Callback::new(create_invite);
+
+    let delete_invite = move |invite_id| {
+        let request = real_time::Request::DeleteInvite { invite_id };
+        send_request.get_untracked().run(request);
+    };
+    let delete_invite = Callback::new(delete_invite);
+
     let set_song_position = move |percentage| {
         let request = real_time::Request::Position { percentage };
         send_request.get_untracked().run(request);
     };
     let set_song_position = Callback::new(set_song_position);
 
-    Effect::new(move |_| log!("host_id:{:?}", host_id()));
+    let update_settings = move |settings| {
+        let request = real_time::Request::UpdateSettings { settings };
+        send_request.get_untracked().run(request);
+    };
+    let update_settings = Callback::new(update_settings);
+
+    let approve_song = move |song_id| {
+        let request = real_time::Request::ApproveSong { song_id };
+        send_request.get_untracked().run(request);
+    };
+    let approve_song = Callback::new(approve_song);
+
+    let reject_song = move |song_id| {
+        let request = real_time::Request::RejectSong { song_id };
+        send_request.get_untracked().run(request);
+    };
+    let reject_song = Callback::new(reject_song);
 
     Effect::new(move |_| {
-        let host_id = match host_id() {
-            Some(host_id) => host_id,
-            None => return,
-        };
+        if session.with(Option::is_none) {
+            return;
+        }
 
         let UseWebSocketReturn {
             ready_state,
@@ -105,21 +208,27 @@ pub fn HostPage() -> impl IntoView {
             send,
             ..
         } = use_websocket::<real_time::Request, real_time::Update, MsgpackSerdeCodec>(&format!(
-            "/socket?id={}",
-            host_id
+            "/socket?{}",
+            real_time::Protocol::query()
         ));
 
-        Effect::new(move |_| {
-            log!("ready_state: {:?}", ready_state.get_untracked());
-        });
-
         let send_request = Callback::new(move |request| send(&request));
         set_send_request(send_request);
+        let sequence = StoredValue::new(real_time::Sequence::default());
 
-        let delete_jam = Action::new(move |_: &()| {
-            let host_id = host_id.clone();
-            async move { delete_jam(host_id).await }
+        Effect::new(move |_| {
+            log!("ready_state: {:?}", ready_state.get());
+            set_ready_state(ready_state.get());
+            // the deltas only make sense on top of everything that was sent before them,
+            // after a reconnect only what was missed is sent
+            if ready_state.get() == ConnectionReadyState::Open {
+                if let Some(request) = sequence.try_update_value(real_time::Sequence::connected) {
+                    send_request.run(request);
+                }
+            }
         });
+
+        let delete_jam = Action::new(move |_: &()| delete_jam());
         let close = Callback::new(move |_: ()| {
             delete_jam.dispatch(());
         });
@@ -134,6 +243,12 @@ pub fn HostPage() -> impl IntoView {
                 }
                 None => None,
             }) {
+                if sequence
+                    .try_update_value(|sequence| sequence.missed(&update))
+                    .unwrap_or(false)
+                {
+                    send_request.run(real_time::Request::Snapshot);
+                }
                 if let Some(users) = update.users {
                     set_users(Some(users));
                 }
@@ -143,6 +258,20 @@ pub fn HostPage() -> impl IntoView {
                 if let Some(votes) = update.votes {
                     set_votes(votes);
                 }
+                if !update.deltas.is_empty() {
+                    set_users.update(|users| {
+                        if let Some(users) = users {
+                            real_time::Delta::apply_to_users(&update.deltas, users);
+                        }
+                    });
+                    set_songs.update(|songs| {
+                        if let Some(songs) = songs {
+                            real_time::Delta::apply_to_songs(&update.deltas, songs);
+                        }
+                    });
+                    set_votes
+                        .update(|votes| real_time::Delta::apply_to_votes(&update.deltas, votes));
+                }
                 if !update.errors.is_empty() {
                     set_error_message(format!("Errors: {:#?}", update.errors));
                 }
@@ -151,14 +280,35 @@ pub fn HostPage() -> impl IntoView {
                     let navigator = use_navigate();
                     navigator("/", NavigateOptions::default());
                 }
-                if update.search.is_some() {
-                    warn!("Unexpected search update");
+                if let Some(result) = update.search {
+                    set_search_result(Some(result));
                 }
                 if update.position.is_some() {
                     warn!("Unexpected position update");
                 }
-                if update.current_song.is_some() {
-                    warn!("Unexpected current song update");
+                if let Some(song) = update.current_song {
+                    set_current_song(song);
+                }
+                if let Some(history) = update.history {
+                    set_history(Some(history));
+                }
+                if let Some(skip_votes) = update.skip_votes {
+                    set_skip_votes(skip_votes);
+                }
+                if let Some(settings) = update.settings {
+                    set_settings(settings);
+                }
+                if let Some(pending) = update.pending {
+                    set_pending(Some(pending));
+                }
+                if let Some(banned) = update.banned {
+                    set_banned(banned);
+                }
+                if let Some(join_requests) = update.join_requests {
+                    set_join_requests(join_requests);
+                }
+                if let Some(invites) = update.invites {
+                    set_invites(invites);
                 }
             }
         });
@@ -167,15 +317,60 @@ pub fn HostPage() -> impl IntoView {
     let close = Callback::new(move |_| {
         close.get_untracked().run(());
     });
+
+    let (reconnect, set_reconnect) = signal(false);
+    let save_playlist = Action::new(move |_: &()| async move {
+        if session.with_untracked(Option::is_none) {
+            return;
+        }
+        match export_playlist().await {
+            Ok(SavedPlaylist::Saved { url }) => set_error_message(format!(
+                "The songs that were played were saved to your Spotify account: {}",
+                url
+            )),
+            Ok(SavedPlaylist::Reconnect) => {
+                set_reconnect(true);
+                set_error_message(
+                    "Saving playlists needs a new permission, connect Spotify again to allow it"
+                        .to_string(),
+                );
+            }
+            Err(e) => set_error_message(format!("Error saving playlist: {}", e)),
+        }
+    });
+    let reconnect_spotify = Action::new(move |_: &()| async move {
+        if session.with_untracked(Option::is_none) {
+            return;
+        }
+        if let Err(e) = reconnect_spotify().await {
+            set_reconnect(false);
+            set_error_message(format!("Error connecting Spotify: {}", e));
+        }
+    });
+    let spotify =
+        Signal::derive(move || matches!(provider_kind.get(), Some(Ok(ProviderKind::Spotify))));
     view! {
         <Modal visible=Signal::derive(move || {
             error_message.with(|e| !e.is_empty())
         })>
             {error_message}
+            <Show when=move || reconnect.get()>
+                <button on:click=move |_| {
+                    reconnect_spotify.dispatch(());
+                }>"Connect Spotify again"</button>
+            </Show>
             <button on:click=move |_| {
+                set_reconnect(false);
                 set_error_message(String::new());
             }>"Close"</button>
         </Modal>
+        <Settings
+            visible=show_settings
+            settings
+            update_settings
+            close=move |_| set_show_settings(false)
+            spotify
+        />
         <Title text=move || {
             jam.get()
                 .map(|jam| jam.map(|jam| jam.name.clone()))
@@ -183,13 +378,106 @@ pub fn HostPage() -> impl IntoView {
                 .unwrap_or_default()
         } />
         <div class="host-page">
-            <UsersBar close=close users kick_user />
+            <UsersBar close=close users kick_user ban_user banned unban_user set_permissions />
+            <button class="button open-settings" on:click=move |_| set_show_settings(true)>
+                "Settings"
+            </button>
+            {move || {
+                if spotify.get() {
+                    Either::Left(
+                        view! {
+                            <button
+                                class="button save-playlist"
+                                on:click=move |_| {
+                                    save_playlist.dispatch(());
+                                }
+                            >
+                                "Save as Spotify playlist"
+                            </button>
+                        },
+                    )
+                } else {
+                    Either::Right(())
+                }
+            }}
+            <div class="export">
+                "Download the songs as "
+                {["m3u", "csv", "json"]
+                    .into_iter()
+                    .map(|format| {
+                        view! {
+                            <a
+                                href=format!("/export?format={}", format)
+                                download
+                                rel="external"
+                            >
+                                {format}
+                            </a>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+            <div class="skip-votes">
+                {move || {
+                    skip_votes
+                        .with(|s| format!("Votes to skip: {} / {}", s.votes, s.needed))
+                }}
+            </div>
+            <div class="queue-strategy">
+                "Queue order: "
+                {move || {
+                    settings
+                        .with(|settings| {
+                            if settings.fairness {
+                                format!(
+                                    "{}, everyone gets a turn",
+                                    settings.queue_strategy.display_name(),
+                                )
+                            } else {
+                                settings.queue_strategy.display_name().to_string()
+                            }
+                        })
+                }}
+            </div>
             <div class="center">
-                <Player host_id set_song_position />
+                <Suspense>
+                    {move || {
+                        provider_kind
+                            .get()
+                            .map(|kind| match kind {
+                                Ok(ProviderKind::Spotify) => {
+                                    EitherOf3::A(view! { <Player session set_song_position /> })
+                                }
+                                Ok(ProviderKind::Local) => {
+                                    EitherOf3::B(
+                                        view! { <LocalPlayer current_song set_song_position /> },
+                                    )
+                                }
+                                // the fake songs have no audio, only the song is shown
+                                _ => {
+                                    EitherOf3::C(
+                                        view! { <general::Player current_song position=0.0_f32 /> },
+                                    )
+                                }
+                            })
+                    }}
+                </Suspense>
+                <JoinRequests join_requests admit=admit_user reject=reject_user />
+                <Invites invites create=create_invite delete=delete_invite />
+                <PendingSongs pending approve=approve_song reject=reject_song />
+                <Search
+                    search_result
+                    search
+                    add_song
+                    loaded=Signal::derive(move || ready_state.get() == ConnectionReadyState::Open)
+                />
                 <SongList
                     songs
                     votes
-                    song_list_action=SongListAction::Remove(remove_song)
+                    song_list_action=SongListAction::Remove {
+                        remove: remove_song,
+                        pin: pin_song,
+                    }
                     max_song_count=Signal::derive(move || {
                         jam.get()
                             .map(|jam| jam.map(|jam| jam.max_song_count))
@@ -198,30 +486,58 @@ pub fn HostPage() -> impl IntoView {
                     })
                 />
 
-                <Share jam_id=Signal::derive(move || {
-                    jam.get()
-                        .map(|jam| jam.map(|jam| jam.id))
-                        .unwrap_or(Ok("".to_string()))
-                        .unwrap_or_default()
-                }) />
+                <Share
+                    jam_id=Signal::derive(move || {
+                        jam.get()
+                            .map(|jam| jam.map(|jam| jam.id))
+                            .unwrap_or(Ok("".to_string()))
+                            .unwrap_or_default()
+                    })
+                    invite=Signal::derive(move || {
+                        invites
+                            .with(|invites| {
+                                invites.iter().find(|invite| is_invite_usable(invite)).cloned()
+                            })
+                            .map(|invite| invite.token)
+                    })
+                />
             </div>
+            <History history />
         </div>
     }
 }
 
 #[server]
-async fn delete_jam(host_id: String) -> Result<(), ServerFnError> {
-    use crate::model::{self, check_id_type, notify, AppState};
+async fn delete_jam() -> Result<(), ServerFnError> {
+    use crate::model::{self, check_request_session, notify, AppState};
     let app_state = expect_context::<AppState>();
+    let headers: http::HeaderMap = leptos_axum::extract().await?;
     let mut transaction = app_state.db.pool.begin().await?;
-    let id = check_id_type(&host_id, &mut transaction).await?;
+    let id = check_request_session(&headers, None, &app_state.secret_key, &mut transaction).await?;
     if !id.is_host() {
         return Err(ServerFnError::Request("id is not a host id".to_string()));
     }
+    let settings = model::get_jam(&id.jam_id, &mut *transaction)
+        .await?
+        .settings;
+    if settings.save_playlist && app_state.music_provider.is_spotify() {
+        // the history and the host's token are gone after the delete, the jam is deleted even if saving fails
+        let mut export_transaction = app_state.db.pool.begin().await?;
+        match model::export_history_to_playlist(
+            &id.jam_id,
+            &mut export_transaction,
+            app_state.spotify_credentials.clone(),
+        )
+        .await
+        {
+            Ok(_) => export_transaction.commit().await?,
+            Err(e) => eprintln!("error saving the playlist of jam {}: {}", id.jam_id, e),
+        }
+    }
     model::delete_jam(&id.jam_id, &mut *transaction).await?;
     leptos_axum::redirect("/");
     use crate::model::real_time::Changed;
-    notify(Changed::new().ended(), vec![], &id.jam_id, &mut transaction).await?;
+    notify(Changed::new().ended(), &id.jam_id, &mut transaction).await?;
     transaction.commit().await?;
     Ok(())
 }
@@ -233,19 +549,99 @@ pub async fn get_jam(jam_id: String) -> Result<Jam, ServerFnError> {
     let app_state = expect_context::<AppState>();
     let pool = &app_state.db.pool;
     match get_jam_fn(&jam_id, pool).await {
-        Ok(jam) => Ok(jam),
+        // anyone can call this, the host gets the passcode with the settings over the web socket
+        Ok(jam) => Ok(Jam {
+            settings: JamSettings {
+                passcode: None,
+                ..jam.settings
+            },
+            ..jam
+        }),
         Err(e) => Err(ServerFnError::Request(e.to_string())),
     }
 }
 
+/// the jam id is the one of the user session, the host session is used without it
 #[server]
-pub async fn get_initial_update(id: String) -> Result<real_time::Update, ServerFnError> {
-    use crate::model::{check_id_type, AppState};
+pub async fn get_initial_update(
+    user_of_jam: Option<String>,
+) -> Result<real_time::Update, ServerFnError> {
+    use crate::model::{check_request_session, AppState};
     let app_state = expect_context::<AppState>();
+    let headers: http::HeaderMap = leptos_axum::extract().await?;
     let mut transaction = app_state.db.pool.begin().await?;
-    let id = check_id_type(&id, &mut transaction).await?;
+    let id = check_request_session(
+        &headers,
+        user_of_jam.as_deref(),
+        &app_state.secret_key,
+        &mut transaction,
+    )
+    .await?;
     let update =
         real_time::Update::from_changed(real_time::Changed::all(), &id, &mut transaction).await;
     transaction.commit().await?;
     Ok(update)
 }
+
+#[server]
+pub async fn get_provider_kind() -> Result<ProviderKind, ServerFnError> {
+    use crate::model::AppState;
+    let app_state = expect_context::<AppState>();
+    Ok(app_state.music_provider.kind())
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum SavedPlaylist {
+    Saved {
+        url: String,
+    },
+    /// the host connected spotify before saving playlists was allowed
+    Reconnect,
+}
+
+#[server]
+async fn export_playlist() -> Result<SavedPlaylist, ServerFnError> {
+    use crate::model::{check_request_session, export_history_to_playlist, AppState, Error};
+    let app_state = expect_context::<AppState>();
+    let headers: http::HeaderMap = leptos_axum::extract().await?;
+    let mut transaction = app_state.db.pool.begin().await?;
+    let id = check_request_session(&headers, None, &app_state.secret_key, &mut transaction).await?;
+    if !id.is_host() {
+        return Err(ServerFnError::Request("id is not a host id".to_string()));
+    }
+    let url = match export_history_to_playlist(
+        &id.jam_id,
+        &mut transaction,
+        app_state.spotify_credentials,
+    )
+    .await
+    {
+        Ok(url) => url,
+        Err(Error::MissingSpotifyScope(_)) => return Ok(SavedPlaylist::Reconnect),
+        Err(e) => return Err(e.into()),
+    };
+    transaction.commit().await?;
+    Ok(SavedPlaylist::Saved { url })
+}
+
+/// sends the host to spotify to allow the scopes they are missing, they come back to the create host page
+#[server]
+async fn reconnect_spotify() -> Result<(), ServerFnError> {
+    use crate::model::{check_request_session, oauth_state, spotify_authorize_url, AppState};
+    let app_state = expect_context::<AppState>();
+    let headers: http::HeaderMap = leptos_axum::extract().await?;
+    let mut transaction = app_state.db.pool.begin().await?;
+    let id = check_request_session(&headers, None, &app_state.secret_key, &mut transaction).await?;
+    let host_id = match id.id_type() {
+        IdType::Host(host_id) => host_id,
+        _ => return Err(ServerFnError::Request("id is not a host id".to_string())),
+    };
+    let state = oauth_state(&host_id, &app_state.secret_key);
+    leptos_axum::redirect(&spotify_authorize_url(
+        &app_state.spotify_credentials,
+        &app_state.site_url,
+        &state,
+    ));
+    transaction.commit().await?;
+    Ok(())
+}
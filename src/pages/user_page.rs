@@ -1,13 +1,17 @@
 use std::ops::Deref;
 
 use super::host_page::get_jam;
-use crate::components::{user::Search, Player, SongList, SongListAction, UsersBar};
+use crate::components::{
+    host::{JoinRequests, PendingSongs, Settings},
+    user::Search,
+    History, Modal, Player, SongList, SongListAction, UsersBar,
+};
 use crate::model::{self, *};
 use crate::pages::host_page::get_initial_update;
 use codee::binary::MsgpackSerdeCodec;
 use gloo::storage::{LocalStorage, Storage};
 use itertools::Itertools;
-use leptos::{logging::*, prelude::*};
+use leptos::{either::Either, logging::*, prelude::*};
 use leptos_meta::Title;
 use leptos_router::{hooks::*, *};
 use leptos_use::{core::ConnectionReadyState, use_websocket, UseWebSocketReturn};
@@ -40,6 +44,7 @@ pub fn UserPage() -> impl IntoView {
         }
     });
 
+    // the id of the user session, its token is sent as a cookie instead of the user id
     let (user_id, set_user_id) = signal(String::new());
     Effect::new(move |_| {
         let navigator = use_navigate();
@@ -47,17 +52,21 @@ pub fn UserPage() -> impl IntoView {
             navigator("/", NavigateOptions::default());
             return;
         }
-        let user_id: String = LocalStorage::get(jam_id()).unwrap_or_default();
-        if user_id.is_empty() {
-            navigator("/", NavigateOptions::default());
-            return;
-        }
-        set_user_id(user_id);
+        let session = match LocalStorage::get::<Session>(jam_id()) {
+            Ok(session) if !session.has_expired(js_sys::Date::now() as i64) => session,
+            _ => {
+                navigator("/", NavigateOptions::default());
+                return;
+            }
+        };
+        set_user_id(session.id);
     });
 
     let initial_update = LocalResource::new(move || {
-        let user_id = user_id.get();
-        async move { get_initial_update(user_id).await }
+        // loaded again once the session is found
+        user_id.track();
+        let jam_id = jam_id.get();
+        async move { get_initial_update(Some(jam_id)).await }
     });
 
     let (search_result, set_search_result) = signal(None);
@@ -66,6 +75,16 @@ pub fn UserPage() -> impl IntoView {
     let (users, set_users) = signal(None);
     let (position, set_position) = signal(0.0);
     let (current_song, set_current_song) = signal(None);
+    let (history, set_history) = signal(None);
+    let (skip_votes, set_skip_votes) = signal(SkipVotes::default());
+    let (settings, set_settings) = signal(JamSettings::default());
+    let (waiting, set_waiting) = signal(false);
+    // only sent if the host made the user a co-host who is allowed to see them
+    let (pending, set_pending) = signal(None::<Vec<Song>>);
+    let (banned, set_banned) = signal(Vec::<BannedUser>::new());
+    let (join_requests, set_join_requests) = signal(Vec::<User>::new());
+    let (show_settings, set_show_settings) = signal(false);
+    let (error_message, set_error_message) = signal(String::new());
     let (ready_state, set_ready_state) = signal(ConnectionReadyState::Connecting);
 
     let (send_request, set_send_request) = signal(Callback::new(|_: real_time::Request| {
@@ -75,6 +94,19 @@ pub fn UserPage() -> impl IntoView {
         warn!("wanted to close ws, but the ws is not ready yet");
     }));
 
+    // what the host let the user do, empty if they are not a co-host
+    let permissions = Memo::new(move |_| {
+        users.with(|users: &Option<Vec<User>>| {
+            users
+                .iter()
+                .flatten()
+                .find(|user| user_id.with(|user_id| user.id == *user_id))
+                .map(|user| user.permissions.clone())
+                .unwrap_or_default()
+        })
+    });
+    let can = move |permission: Permission| permissions.with(|p| p.contains(&permission));
+
     let search = move |query_id: (String, String)| {
         let request = real_time::Request::Search {
             query: query_id.0,
@@ -124,6 +156,13 @@ pub fn UserPage() -> impl IntoView {
     };
     let add_vote = Callback::new(add_vote);
 
+    let add_downvote = move |song_id: String| {
+        log!("Adding downvote for song: {}", song_id);
+        let request = real_time::Request::AddDownvote { song_id };
+        send_request.get_untracked().run(request);
+    };
+    let add_downvote = Callback::new(add_downvote);
+
     let remove_vote = move |song_id: String| {
         log!("Removing vote for song: {}", song_id);
         let request = real_time::Request::RemoveVote { song_id };
@@ -131,12 +170,70 @@ pub fn UserPage() -> impl IntoView {
     };
     let remove_vote = Callback::new(remove_vote);
 
+    let vote_skip = move || {
+        send_request
+            .get_untracked()
+            .run(real_time::Request::VoteSkip);
+    };
+
     let remove_song = move |song_id: String| {
         let request = real_time::Request::RemoveSong { song_id };
         send_request.get_untracked().run(request);
     };
     let remove_song = Callback::new(remove_song);
 
+    let skip = move || {
+        send_request.get_untracked().run(real_time::Request::Skip);
+    };
+
+    let kick_user = move |user_id| {
+        let request = real_time::Request::KickUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let kick_user = Callback::new(kick_user);
+
+    let ban_user = move |user_id| {
+        let request = real_time::Request::BanUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let ban_user = Callback::new(ban_user);
+
+    let unban_user = move |ban_id| {
+        let request = real_time::Request::UnbanUser { ban_id };
+        send_request.get_untracked().run(request);
+    };
+    let unban_user = Callback::new(unban_user);
+
+    let admit_user = move |user_id| {
+        let request = real_time::Request::AdmitUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let admit_user = Callback::new(admit_user);
+
+    let reject_user = move |user_id| {
+        let request = real_time::Request::RejectUser { user_id };
+        send_request.get_untracked().run(request);
+    };
+    let reject_user = Callback::new(reject_user);
+
+    let approve_song = move |song_id| {
+        let request = real_time::Request::ApproveSong { song_id };
+        send_request.get_untracked().run(request);
+    };
+    let approve_song = Callback::new(approve_song);
+
+    let reject_song = move |song_id| {
+        let request = real_time::Request::RejectSong { song_id };
+        send_request.get_untracked().run(request);
+    };
+    let reject_song = Callback::new(reject_song);
+
+    let update_settings = move |settings| {
+        let request = real_time::Request::UpdateSettings { settings };
+        send_request.get_untracked().run(request);
+    };
+    let update_settings = Callback::new(update_settings);
+
     let leave = move || {
         let request = real_time::Request::KickUser {
             user_id: user_id.get_untracked(),
@@ -161,20 +258,29 @@ pub fn UserPage() -> impl IntoView {
             send,
             ..
         } = use_websocket::<real_time::Request, real_time::Update, MsgpackSerdeCodec>(&format!(
-            "/socket?id={}",
-            user_id.get_untracked()
+            "/socket?jam_id={}&{}",
+            jam_id.get_untracked(),
+            real_time::Protocol::query()
         ));
 
-        Effect::new(move |_| {
-            log!("ready_state: {:?}", ready_state.get());
-            set_ready_state(ready_state.get());
-        });
-
         let send_request = move |request: real_time::Request| {
             send(&request);
         };
         let send_request = Callback::new(send_request);
         set_send_request(send_request);
+        let sequence = StoredValue::new(real_time::Sequence::default());
+
+        Effect::new(move |_| {
+            log!("ready_state: {:?}", ready_state.get());
+            set_ready_state(ready_state.get());
+            // the deltas only make sense on top of everything that was sent before them,
+            // after a reconnect only what was missed is sent
+            if ready_state.get() == ConnectionReadyState::Open {
+                if let Some(request) = sequence.try_update_value(real_time::Sequence::connected) {
+                    send_request.run(request);
+                }
+            }
+        });
 
         let close_ws = Callback::new(move |_: ()| close_ws());
 
@@ -189,6 +295,20 @@ pub fn UserPage() -> impl IntoView {
                     None => None,
                 }
             }) {
+                if sequence
+                    .try_update_value(|sequence| sequence.missed(&update))
+                    .unwrap_or(false)
+                {
+                    send_request.run(real_time::Request::Snapshot);
+                }
+                // kicked, banned or left from another tab
+                let has_left = update.has_left(&user_id.get_untracked());
+                // the first update after the host admits the user has everything in it
+                if update.waiting.is_some() {
+                    set_waiting(true);
+                } else if update.users.is_some() {
+                    set_waiting(false);
+                }
                 if let Some(result) = update.search {
                     //log!("Got search result: {:#?}", result);
                     set_search_result(Some(result));
@@ -199,22 +319,34 @@ pub fn UserPage() -> impl IntoView {
                 if let Some(votes) = update.votes {
                     set_votes(votes);
                 }
-                if let Some(users) = update.users {
-                    if !users
-                        .iter()
-                        .map(|user| &user.id)
-                        .contains(&user_id.get_untracked())
-                    {
-                        close_ws.run(());
-                        jam_id.with_untracked(|jam_id| {
-                            if LocalStorage::set(jam_id, "kicked").is_err() {
-                                error!("Failed to set local storage to kicked");
+                if !update.deltas.is_empty() {
+                    set_songs.update(|songs| {
+                        if let Some(songs) = songs {
+                            real_time::Delta::apply_to_songs(&update.deltas, songs);
+                        }
+                    });
+                    set_votes
+                        .update(|votes| real_time::Delta::apply_to_votes(&update.deltas, votes));
+                }
+                if has_left {
+                    close_ws.run(());
+                    jam_id.with_untracked(|jam_id| {
+                        if LocalStorage::set(jam_id, "kicked").is_err() {
+                            error!("Failed to set local storage to kicked");
+                        }
+                    });
+                    let navigator = use_navigate();
+                    navigator("/", NavigateOptions::default());
+                } else {
+                    if let Some(users) = update.users {
+                        set_users(Some(users));
+                    }
+                    if !update.deltas.is_empty() {
+                        set_users.update(|users| {
+                            if let Some(users) = users {
+                                real_time::Delta::apply_to_users(&update.deltas, users);
                             }
                         });
-                        let navigator = use_navigate();
-                        navigator("/", NavigateOptions::default());
-                    } else {
-                        set_users(Some(users));
                     }
                 }
                 if let Some(percentage) = update.position {
@@ -223,6 +355,24 @@ pub fn UserPage() -> impl IntoView {
                 if let Some(song) = update.current_song {
                     set_current_song(song);
                 }
+                if let Some(history) = update.history {
+                    set_history(Some(history));
+                }
+                if let Some(skip_votes) = update.skip_votes {
+                    set_skip_votes(skip_votes);
+                }
+                if let Some(settings) = update.settings {
+                    set_settings(settings);
+                }
+                if let Some(pending) = update.pending {
+                    set_pending(Some(pending));
+                }
+                if let Some(banned) = update.banned {
+                    set_banned(banned);
+                }
+                if let Some(join_requests) = update.join_requests {
+                    set_join_requests(join_requests);
+                }
                 if update.ended.is_some() {
                     close_ws.run(());
                     delete_user_id_from_local_storage.run(());
@@ -231,6 +381,16 @@ pub fn UserPage() -> impl IntoView {
                 }
                 if !update.errors.is_empty() {
                     error!("Errors: {:#?}", update.errors);
+                    // the errors caused by what the user did are explained to them, like a song breaking the rules of the jam
+                    let explanation = update
+                        .errors
+                        .into_iter()
+                        .filter(|e| e.to_code() == 4400)
+                        .map(String::from)
+                        .join("\n");
+                    if !explanation.is_empty() {
+                        set_error_message(explanation);
+                    }
                 }
             }
         });
@@ -251,6 +411,22 @@ pub fn UserPage() -> impl IntoView {
     });
 
     view! {
+        <Modal visible=Signal::derive(move || {
+            error_message.with(|e| !e.is_empty())
+        })>
+            {error_message}
+            <button on:click=move |_| {
+                set_error_message(String::new());
+            }>"Close"</button>
+        </Modal>
+        <Show when=move || can(Permission::ChangeSettings)>
+            <Settings
+                visible=show_settings
+                settings
+                update_settings
+                close=move |_| set_show_settings(false)
+            />
+        </Show>
         <Title text=move || {
             jam.value()
                 .get()
@@ -259,8 +435,37 @@ pub fn UserPage() -> impl IntoView {
                 .unwrap_or_default()
         }/>
         <div class="user-page">
-            <UsersBar users close/>
-            <div class="center">
+            {move || {
+                if can(Permission::Kick) {
+                    Either::Left(
+                        view! { <UsersBar users close kick_user ban_user banned unban_user /> },
+                    )
+                } else {
+                    Either::Right(view! { <UsersBar users close /> })
+                }
+            }}
+            <Show when=move || can(Permission::ChangeSettings)>
+                <button class="button open-settings" on:click=move |_| set_show_settings(true)>
+                    "Settings"
+                </button>
+            </Show>
+            <Show when=waiting>
+                <div class="waiting-room">
+                    "Waiting for the host to let you in..."
+                </div>
+            </Show>
+            <div class="center" class:hidden=waiting>
+                <Show when=move || can(Permission::Kick)>
+                    <JoinRequests join_requests admit=admit_user reject=reject_user />
+                </Show>
+                <Show when=move || can(Permission::ApproveSongs)>
+                    <PendingSongs pending approve=approve_song reject=reject_song />
+                </Show>
+                <Show when=move || settings.with(|s| s.moderated)>
+                    <div class="moderated-notice">
+                        "The host approves the songs before they are added to the queue"
+                    </div>
+                </Show>
                 <Search
                     search_result
                     search
@@ -272,8 +477,11 @@ pub fn UserPage() -> impl IntoView {
                     votes
                     song_list_action=SongListAction::Vote {
                         add_vote,
+                        add_downvote,
                         remove_vote,
                         remove_song,
+                        voting: Signal::derive(move || settings.with(|s| s.voting)),
+                        downvotes: Signal::derive(move || settings.with(|s| s.downvotes)),
                     }
 
                     max_song_count=Signal::derive(move || {
@@ -285,8 +493,23 @@ pub fn UserPage() -> impl IntoView {
                     })
                 />
 
-                <Player position current_song/>
+                <Player position current_song>
+                    <button
+                        class="skip"
+                        on:click=move |_| vote_skip()
+                        disabled=move || skip_votes.with(|s| s.have_you_voted.unwrap_or(false))
+                        title="vote to skip this song"
+                    >
+                        {move || skip_votes.with(|s| format!("Skip {}/{}", s.votes, s.needed))}
+                    </button>
+                    <Show when=move || can(Permission::Skip)>
+                        <button class="skip" on:click=move |_| skip() title="skip this song now">
+                            "Skip now"
+                        </button>
+                    </Show>
+                </Player>
             </div>
+            <History history/>
         </div>
     }
 }
@@ -0,0 +1,93 @@
+use crate::model::{functions::*, AppState};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ExportQuery {
+    /// the jam the user session is for, the host session is used without it
+    pub jam_id: Option<String>,
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    M3u,
+    Csv,
+    Json,
+}
+
+/// downloads the played and queued songs of the jam
+pub async fn export(
+    headers: HeaderMap,
+    Query(query): Query<ExportQuery>,
+    State(state): State<AppState>,
+) -> Response {
+    let mut transaction = match state.db.pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let user_of_jam = query.jam_id.as_deref();
+    let id = match check_request_session(&headers, user_of_jam, &state.secret_key, &mut transaction)
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => return (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+    };
+    let jam_id = id.jam_id().to_string();
+
+    let set_list = match get_set_list(&jam_id, &mut transaction).await {
+        Ok(set_list) => set_list,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let (body, content_type, extension) = match query.format {
+        ExportFormat::M3u => {
+            let provider = &state.music_provider;
+            let site_url = &state.site_url;
+            let m3u = set_list.to_m3u(|song| match provider {
+                Provider::Spotify(_) => format!("spotify:track:{}", song.spotify_id),
+                Provider::Local(_) => format!("{}/library/{}/audio", site_url, song.spotify_id),
+                Provider::Fake(_) => song.spotify_id.clone(),
+            });
+            (m3u, "audio/x-mpegurl", "m3u")
+        }
+        ExportFormat::Csv => {
+            let user_names = match get_users(&mut *transaction, &id).await {
+                Ok(users) => users
+                    .into_iter()
+                    .map(|user| (user.id, user.name))
+                    .collect::<HashMap<String, String>>(),
+                Err(e) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+                }
+            };
+            (set_list.to_csv(&user_names), "text/csv", "csv")
+        }
+        ExportFormat::Json => match serde_json::to_string_pretty(&set_list) {
+            Ok(json) => (json, "application/json", "json"),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+    };
+
+    if let Err(e) = transaction.commit().await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", jam_id, extension),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
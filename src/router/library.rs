@@ -0,0 +1,46 @@
+use crate::model::{functions::Provider, AppState};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+/// serves the audio file of a song in the local library, with range requests so the player can seek
+pub async fn audio(
+    Path(song_id): Path<String>,
+    State(state): State<AppState>,
+    request: Request<Body>,
+) -> Response {
+    let library = match &state.music_provider {
+        Provider::Local(library) => library,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let path = match library.path_of(&song_id) {
+        Ok(path) => path,
+        Err(e) => return (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    };
+
+    match ServeFile::new(path).oneshot(request).await {
+        Ok(res) => res.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// serves the cover art embedded in a song of the local library
+pub async fn cover(Path(song_id): Path<String>, State(state): State<AppState>) -> Response {
+    let library = match &state.music_provider {
+        Provider::Local(library) => library,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    match library.cover_of(&song_id) {
+        Ok(Some((bytes, mime_type))) => {
+            ([(header::CONTENT_TYPE, mime_type)], bytes).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
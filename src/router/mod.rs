@@ -4,6 +4,10 @@ use axum::{routing::get, Router};
 use leptos::prelude::*;
 use leptos_axum::*;
 
+mod export;
+mod library;
+mod protocol;
+
 pub fn new(
     leptos_routes: Vec<AxumRouteListing>,
     app_state: AppState,
@@ -24,5 +28,9 @@ pub fn new(
         )
         .fallback(leptos_axum::file_and_error_handler::<AppState, _>(shell))
         .route("/socket", get(crate::socket::socket))
+        .route("/socket/protocol", get(protocol::protocol))
+        .route("/export", get(export::export))
+        .route("/library/:song_id/audio", get(library::audio))
+        .route("/library/:song_id/cover", get(library::cover))
         .with_state(app_state.clone())
 }
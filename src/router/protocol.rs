@@ -0,0 +1,26 @@
+use crate::model::types::real_time::{self, Encoding, Request, Update};
+use axum::Json;
+use schemars::{schema::RootSchema, schema_for};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct ProtocolDescription {
+    pub version: u32,
+    pub min_version: u32,
+    pub encodings: [Encoding; 2],
+    /// what the client sends on `/socket`
+    pub request: RootSchema,
+    /// what the server sends on `/socket`
+    pub update: RootSchema,
+}
+
+/// the versions and the JSON schemas of the messages on `/socket`, for clients that are not the pages of the app
+pub async fn protocol() -> Json<ProtocolDescription> {
+    Json(ProtocolDescription {
+        version: real_time::PROTOCOL_VERSION,
+        min_version: real_time::MIN_PROTOCOL_VERSION,
+        encodings: Encoding::ALL,
+        request: schema_for!(Request),
+        update: schema_for!(Update),
+    })
+}
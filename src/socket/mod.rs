@@ -4,7 +4,8 @@ use axum::{
         ws::{self, WebSocket, WebSocketUpgrade},
         Query, State,
     },
-    response::Response,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use futures_util::{
     sink::SinkExt,
@@ -15,191 +16,165 @@ use tokio::sync::mpsc;
 mod read;
 mod write;
 
+/// the session and the protocol are checked before upgrading, so a client with an invalid one gets a 401
+/// and one that is too old a 400 instead of a socket
 pub async fn socket(
     ws: WebSocketUpgrade,
-    Query(id): Query<QueryId>,
+    headers: HeaderMap,
+    Query(query): Query<SocketQuery>,
     State(state): State<AppState>,
 ) -> Response {
-    println!("ws: {:?}", id);
-    ws.on_upgrade(|socket| handle_socket(socket, state, id.id))
-}
-
-#[derive(Debug, serde::Deserialize)]
-pub struct QueryId {
-    pub id: String,
-}
-
-async fn handle_socket(socket: WebSocket, app_state: AppState, id: String) {
-    let (sender, receiver) = socket.split();
-    let (mpsc_sender, mpsc_receiver) = mpsc::channel(3);
-
-    let pool = app_state.db.pool.clone();
-    let spotify_credentials = app_state.spotify_credentials.clone();
+    let protocol = match real_time::Protocol::negotiate(query.version, query.encoding) {
+        Ok(protocol) => protocol,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
 
-    let mut transaction = match pool.begin().await {
+    let mut transaction = match state.db.pool.begin().await {
         Ok(transaction) => transaction,
-        Err(e) => {
-            eprintln!(
-                "Terminating socket, Error starting transaction while handling socket: {:?}",
-                e
-            );
-            return;
-        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     };
 
-    let id = match check_id_type(&id, &mut transaction).await {
+    let user_of_jam = query.jam_id.as_deref();
+    let id = match check_request_session(&headers, user_of_jam, &state.secret_key, &mut transaction)
+        .await
+    {
         Ok(id) => id,
         Err(e) => {
-            eprintln!("Error checking id type: {:?}", e);
-            return;
+            eprintln!("Error checking session of socket: {:?}", e);
+            return (StatusCode::UNAUTHORIZED, e.to_string()).into_response();
         }
     };
-    transaction.commit().await.unwrap_or_else(|e| {
+    if let Err(e) = transaction.commit().await {
         eprintln!(
-            "Error committing transaction while handling socket: {:?}",
+            "Error committing transaction while checking session: {:?}",
             e
         );
-    });
+    }
+
+    println!("ws: {:?}", id);
+    ws.on_upgrade(move |socket| handle_socket(socket, state, id, protocol))
+}
+
+/// what the client asked for besides the updates, the write task sends it so it stays in order with them
+#[derive(Debug, Clone, Copy)]
+enum Catchup {
+    Snapshot,
+    Resume { last_seq: u64 },
+}
 
-    let bridge_task = tokio::spawn(send(mpsc_receiver, sender));
+/// what the tasks hand to the bridge, it encodes the updates the way the client asked for
+#[derive(Debug)]
+enum Outgoing {
+    Update(real_time::Update),
+    Close(ws::CloseFrame<'static>),
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SocketQuery {
+    /// the jam the user session is for, the host session is used without it
+    pub jam_id: Option<String>,
+    /// the newest protocol version the client speaks, see `real_time::Protocol::negotiate`
+    pub version: Option<u32>,
+    /// msgpack if it is not given
+    pub encoding: Option<real_time::Encoding>,
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    app_state: AppState,
+    id: Id,
+    protocol: real_time::Protocol,
+) {
+    let (sender, receiver) = socket.split();
+    let (mpsc_sender, mpsc_receiver) = mpsc::channel(3);
+
+    // the client learns what was agreed on before anything else
+    if let Err(e) = mpsc_sender
+        .send(Outgoing::Update(
+            real_time::Update::new().protocol(protocol),
+        ))
+        .await
+    {
+        eprintln!("Error sending the protocol: {:?}", e);
+    }
+    let bridge_task = tokio::spawn(send(mpsc_receiver, sender, protocol.encoding));
+    let (catchup_sender, catchup_receiver) = mpsc::channel(3);
     let recv_task = tokio::spawn(read::read(
         receiver,
         mpsc_sender.clone(),
         id.clone(),
         app_state.clone(),
+        catchup_sender,
+        protocol.encoding,
     ));
 
     let send_task = tokio::spawn(write::write(
         mpsc_sender.clone(),
         id.clone(),
         app_state.clone(),
+        catchup_receiver,
     ));
 
-    let checkup = if id.is_host() {
-        let handle = tokio::spawn(occasional_notify(
-            pool.clone(),
-            id.jam_id.clone(),
-            spotify_credentials,
-        ));
-        Some(handle)
-    } else {
-        None
-    };
+    if id.is_host() {
+        watch_jam(
+            id.jam_id(),
+            &app_state.db.pool,
+            &app_state.music_provider,
+            &app_state.leader,
+        );
+    }
 
     if let Err(e) = bridge_task.await {
         eprintln!("Error in bridge task: {:?}", e);
     };
     send_task.abort();
     recv_task.abort();
-    if let Some(handle) = checkup {
-        handle.abort();
+    if id.is_host() {
+        app_state.leader.host_left(id.jam_id());
     }
 }
 
-async fn occasional_notify<'e>(
-    pool: sqlx::PgPool,
-    jam_id: String,
-    spotify_credentials: SpotifyCredentials,
-) -> Result<(), Error> {
-    use std::time::Duration;
-    while dose_jam_exist(&jam_id, &pool).await.unwrap_or(true) {
-        println!("Occasional notify");
-
-        let mut transaction = match pool.begin().await {
-            Ok(transaction) => transaction,
-            Err(e) => {
-                eprintln!("Error starting transaction in occasional notify: {:?}", e);
-                tokio::time::sleep(Duration::from_secs(10)).await;
-                continue;
-            }
-        };
-
-        if let Err(e) = notify(real_time::Changed::all(), vec![], &jam_id, &mut transaction).await {
-            eprintln!("Error notifying all, in occasional notify: {:?}", e);
-        };
-
-        tokio::spawn({
-            let spotify_credentials = spotify_credentials.clone();
-            let jam_id = jam_id.clone();
-            async move {
-                play_the_current_song_if_player_is_not_playing_it(
-                    jam_id,
-                    &mut transaction,
-                    spotify_credentials,
-                )
-                .await
-                .unwrap_or_else(|e| {
-                    eprintln!("Error playing current song in occasional notify: {:?}", e);
-                });
-                if let Err(e) = transaction.commit().await {
-                    eprintln!("Error committing transaction in occasional notify: {:?}", e);
-                }
-            }
-        });
-
-        tokio::time::sleep(Duration::from_secs(10)).await;
-    }
-
-    Ok(())
-}
+async fn handle_error(error: Error, close: bool, sender: &mpsc::Sender<Outgoing>) {
+    eprintln!("Error: {:?}", error);
 
-async fn play_the_current_song_if_player_is_not_playing_it<'e>(
-    jam_id: String,
-    transaction: &mut sqlx::Transaction<'e, sqlx::Postgres>,
-    credentials: SpotifyCredentials,
-) -> Result<(), Error> {
-    use crate::model::functions::{get_current_song_from_player, play_song};
-    let jam_id = &jam_id;
-    let current_song = get_current_song(jam_id, &mut **transaction).await?;
-    let song = match current_song {
-        Some(song) => song,
-        None => return Ok(()),
+    let message = if close {
+        Outgoing::Close(error.to_close_frame())
+    } else {
+        Outgoing::Update(real_time::Update::new().error(error))
     };
-    let player_current_song =
-        get_current_song_from_player(jam_id, transaction, credentials.clone()).await?;
-    if player_current_song
-        .as_ref()
-        .map(|s| s.spotify_id != song.spotify_id)
-        .unwrap_or(true)
-    {
-        println!(
-            "playing song: {:?}, set away from: {}",
-            song.name,
-            player_current_song.map(|s| s.name).unwrap_or_default()
-        );
-        play_song(&song.spotify_id, jam_id, transaction, credentials).await?;
+    if let Err(e) = sender.send(message).await {
+        eprintln!("Error sending error: {:?}", e);
     }
-    Ok(())
 }
 
-async fn handle_error(error: Error, close: bool, sender: &mpsc::Sender<ws::Message>) {
-    eprintln!("Error: {:?}", error);
-
-    if close {
-        let close_frame = error.to_close_frame();
-        if let Err(e) = sender.send(ws::Message::Close(Some(close_frame))).await {
-            eprintln!("Error sending close frame: {:?}", e);
-        }
-    } else {
-        let update = real_time::Update::new().error(error);
-        let bin = match rmp_serde::to_vec(&update) {
-            Ok(bin) => bin,
-            Err(e) => {
-                eprintln!("Error encoding error update: {:?}", e);
-                return;
-            }
-        };
-        if let Err(e) = sender.send(ws::Message::Binary(bin)).await {
-            eprintln!("Error sending error update: {:?}", e);
-        }
+/// msgpack is sent as binary and json as text
+fn encode(update: &real_time::Update, encoding: real_time::Encoding) -> Result<ws::Message, Error> {
+    match encoding {
+        real_time::Encoding::Msgpack => rmp_serde::to_vec(update)
+            .map(ws::Message::Binary)
+            .map_err(|e| Error::Encode(format!("Error encoding message sent in ws: {:?}", e))),
+        real_time::Encoding::Json => serde_json::to_string(update)
+            .map(ws::Message::Text)
+            .map_err(|e| Error::Encode(format!("Error encoding message sent in ws: {:?}", e))),
     }
 }
 
 async fn send(
-    mut receiver: mpsc::Receiver<ws::Message>,
+    mut receiver: mpsc::Receiver<Outgoing>,
     mut sender: SplitSink<WebSocket, ws::Message>,
+    encoding: real_time::Encoding,
 ) {
-    while let Some(msg) = receiver.recv().await {
+    while let Some(outgoing) = receiver.recv().await {
+        let msg = match outgoing {
+            Outgoing::Update(update) => match encode(&update, encoding) {
+                Ok(msg) => msg,
+                Err(error) => {
+                    eprintln!("Error: {:?}", error);
+                    ws::Message::Close(Some(error.to_close_frame()))
+                }
+            },
+            Outgoing::Close(close_frame) => ws::Message::Close(Some(close_frame)),
+        };
         let close_connection = matches!(msg, ws::Message::Close(_));
 
         match sender.send(msg).await {
@@ -1,4 +1,4 @@
-use super::handle_error;
+use super::{handle_error, Catchup, Outgoing};
 use crate::model::*;
 use axum::extract::ws::{self, WebSocket};
 use futures_util::{stream::SplitStream, StreamExt};
@@ -7,12 +7,15 @@ use tokio::sync::mpsc;
 
 pub async fn read(
     mut receiver: SplitStream<WebSocket>,
-    sender: mpsc::Sender<ws::Message>,
+    sender: mpsc::Sender<Outgoing>,
     id: Id,
     app_state: AppState,
+    catchup: mpsc::Sender<Catchup>,
+    encoding: real_time::Encoding,
 ) {
     let pool = &app_state.db.pool.clone();
-    let credentials = app_state.spotify_credentials;
+    let provider = app_state.music_provider;
+    let secret_key = app_state.secret_key;
 
     while let Some(message) = receiver.next().await {
         let message = match message {
@@ -23,22 +26,51 @@ pub async fn read(
             }
         };
 
+        let message = match decode(message, encoding) {
+            Ok(message) => message,
+            Err(error) => {
+                handle_error(error, true, &sender).await;
+                break;
+            }
+        };
+
         tokio::spawn(handle_message(
             message,
             sender.clone(),
             id.clone(),
             pool.clone(),
-            credentials.clone(),
+            provider.clone(),
+            secret_key.clone(),
+            catchup.clone(),
         ));
     }
 }
 
-async fn handle_message(
+/// the requests come in the encoding that was agreed on, msgpack or json
+fn decode(
     message: ws::Message,
-    sender: mpsc::Sender<ws::Message>,
+    encoding: real_time::Encoding,
+) -> Result<real_time::Request, Error> {
+    let message = message.into_data();
+    let request = match encoding {
+        real_time::Encoding::Msgpack => {
+            rmp_serde::from_slice(&message).map_err(|e| format!("{:#?}", e))
+        }
+        real_time::Encoding::Json => {
+            serde_json::from_slice(&message).map_err(|e| format!("{:#?}", e))
+        }
+    };
+    request.map_err(|e| Error::Decode(format!("Error decoding message sent in ws: {}", e)))
+}
+
+async fn handle_message(
+    message: real_time::Request,
+    sender: mpsc::Sender<Outgoing>,
     id: Id,
     pool: sqlx::PgPool,
-    credentials: SpotifyCredentials,
+    provider: Provider,
+    secret_key: String,
+    catchup: mpsc::Sender<Catchup>,
 ) {
     let mut transaction = match pool.begin().await {
         Ok(t) => t,
@@ -48,91 +80,114 @@ async fn handle_message(
             return;
         }
     };
-    let message: types::real_time::Request = match rmp_serde::from_slice(&message.into_data()) {
-        Ok(m) => m,
+    let mut changed = real_time::Changed::new();
+
+    // in the waiting room a user can only leave, or ask for a snapshot, which tells them they are waiting
+    if let IdType::User(user_id) = &id.id {
+        // the socket of a kicked user may still be open
+        if let Err(e) = check_user_exists(user_id, &mut *transaction).await {
+            handle_error(e, true, &sender).await;
+            return;
+        }
+        let is_allowed_to_wait = matches!(
+            message,
+            real_time::Request::KickUser { .. }
+                | real_time::Request::Snapshot
+                | real_time::Request::Resume { .. }
+        );
+        match is_user_waiting(user_id, &mut *transaction).await {
+            Ok(true) if !is_allowed_to_wait => {
+                let error = Error::Forbidden(
+                    "You are in the waiting room, the host has not admitted you yet".to_string(),
+                );
+                handle_error(error, false, &sender).await;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                handle_error(e.into(), false, &sender).await;
+                return;
+            }
+        }
+    }
+
+    let id = match refresh_permissions(id, &mut *transaction).await {
+        Ok(id) => id,
         Err(e) => {
-            use Error;
-            let error = Error::Decode(format!("Error decoding message sent in ws: {:#?}", e));
-            handle_error(error, true, &sender).await;
+            handle_error(e, false, &sender).await;
             return;
         }
     };
-    let mut changed = real_time::Changed::new();
-    let mut errors: Vec<Error> = Vec::new();
+
+    // who can send what is declared in `Request::allowed_roles`
+    if !message.is_allowed(&id) {
+        let error = Error::Forbidden(message.forbidden_error());
+        handle_error(error, false, &sender).await;
+        return;
+    }
 
     match message {
         real_time::Request::KickUser { user_id } => {
+            // the permission table keeps out the general id already, this arm only gives the match its id
             let your_id = match &id.id {
                 IdType::User(id) | IdType::Host(id) => id,
                 IdType::General => {
                     let error = Error::Forbidden(
-                        "Only users and hosts can kick users (users themselves), this is a bug, terminating socket connection"
-                            .to_string(),
+                        "Only users and hosts can kick users (users themselves)".to_string(),
                     );
-                    handle_error(error, true, &sender).await;
+                    handle_error(error, false, &sender).await;
                     return;
                 }
             };
-            if !(&user_id == your_id || user_id.is_empty()) && id.is_user() {
+            if !(&user_id == your_id || user_id.is_empty()) && !id.can(Permission::Kick) {
                 let error = Error::Forbidden(
-                    "A user only can kick themselves, this is a bug, terminating socket connection"
+                    "A user only can kick themselves, unless they are a co-host who is allowed to kick"
                         .to_string(),
                 );
-                handle_error(error, true, &sender).await;
+                handle_error(error, false, &sender).await;
                 return;
             }
             if user_id.is_empty() && id.is_host() {
-                let error = Error::InvalidRequest(
-                    "the user id is empty and you are not a user".to_string(),
-                );
+                let error =
+                    Error::InvalidRequest("the user id is empty and you are not a user".into());
                 handle_error(error, false, &sender).await;
                 return;
             }
-            let mut user_id = &user_id;
-            if user_id.is_empty() {
-                if id.is_user() {
-                    user_id = your_id;
-                } else {
-                    errors.push(Error::InvalidRequest("No user id provided".to_string()));
+            let user_id = if user_id.is_empty() {
+                your_id
+            } else {
+                &user_id
+            };
+            match kick_user(user_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
                 }
-            }
-            if errors.is_empty() {
-                match kick_user(user_id, &mut *transaction).await {
-                    Ok(changed_new) => {
-                        changed = changed.merge_with_other(changed_new);
-                    }
-                    Err(e) => {
-                        errors.push(e);
-                    }
-                };
-            }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
         }
         real_time::Request::AddSong { song_id } => {
-            let your_id = match only_user(
-                &id,
-                "Only users can add songs, this is a bug, terminating socket connection",
-                &sender,
-            )
-            .await
-            {
-                Ok(id) => id,
-                Err(_) => return,
+            // the songs of the host are added with the jam id, so they are not limited,
+            // the general id is kept out by the permission table already
+            let your_id = match &id.id {
+                IdType::User(user_id) => user_id.as_str(),
+                IdType::Host(_) => id.jam_id(),
+                IdType::General => {
+                    let error = Error::Forbidden("Only users and hosts can add songs".to_string());
+                    handle_error(error, false, &sender).await;
+                    return;
+                }
             };
 
-            match add_song(
-                &song_id,
-                your_id,
-                id.jam_id(),
-                &mut transaction,
-                credentials.clone(),
-            )
-            .await
-            {
+            match add_song(&song_id, your_id, id.jam_id(), &mut transaction, &provider).await {
                 Ok(changed_new) => {
                     changed = changed.merge_with_other(changed_new);
                 }
                 Err(e) => {
-                    errors.push(e);
+                    handle_error(e, false, &sender).await;
+                    return;
                 }
             };
         }
@@ -142,40 +197,45 @@ async fn handle_message(
                     changed = changed.merge_with_other(changed_new);
                 }
                 Err(e) => {
-                    errors.push(e);
+                    handle_error(e, false, &sender).await;
+                    return;
                 }
             };
         }
         real_time::Request::AddVote { song_id } => {
-            let id = match only_user(
-                &id,
-                "Only users can vote, this is a bug, terminating socket connection",
-                &sender,
-            )
-            .await
-            {
+            let your_id = match only_user(&id, "Only users can vote", &sender).await {
+                Ok(id) => id,
+                Err(_) => return,
+            };
+
+            match add_vote(&song_id, your_id, id.jam_id(), &mut transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::AddDownvote { song_id } => {
+            let your_id = match only_user(&id, "Only users can vote", &sender).await {
                 Ok(id) => id,
                 Err(_) => return,
             };
 
-            match add_vote(&song_id, id, &mut *transaction).await {
+            match add_downvote(&song_id, your_id, id.jam_id(), &mut transaction).await {
                 Ok(changed_new) => {
                     changed = changed.merge_with_other(changed_new);
                 }
                 Err(e) => {
-                    eprintln!("Error adding vote: {:?}", e);
-                    errors.push(e);
+                    handle_error(e, false, &sender).await;
+                    return;
                 }
             };
         }
         real_time::Request::RemoveVote { song_id } => {
-            let your_id = match only_user(
-                &id,
-                "Only users can remove votes, this is a bug, terminating socket connection",
-                &sender,
-            )
-            .await
-            {
+            let your_id = match only_user(&id, "Only users can remove votes", &sender).await {
                 Ok(id) => id,
                 Err(_) => return,
             };
@@ -185,8 +245,8 @@ async fn handle_message(
                     changed = changed.merge_with_other(changed_new);
                 }
                 Err(e) => {
-                    eprintln!("Error removing vote: {:?}", e);
-                    errors.push(e);
+                    handle_error(e, false, &sender).await;
+                    return;
                 }
             };
         }
@@ -194,71 +254,197 @@ async fn handle_message(
             query,
             id: search_id,
         } => {
-            if only_user(
-                &id,
-                "Only users can search, this is a bug, terminating socket connection",
-                &sender,
-            )
-            .await
-            .is_err()
-            {
-                return;
-            }
-
-            let songs =
-                match search(&query, &mut transaction, id.jam_id(), credentials.clone()).await {
-                    Ok(songs) => songs,
-                    Err(e) => {
-                        handle_error(e, false, &sender).await;
-                        return;
-                    }
-                };
-
-            let update = real_time::Update::new().search(SearchResult { songs, search_id });
-            let message = match rmp_serde::to_vec(&update) {
-                Ok(m) => m,
+            let songs = match search(&query, &mut transaction, id.jam_id(), &provider).await {
+                Ok(songs) => songs,
                 Err(e) => {
-                    let error = Error::Decode(format!("Error encoding search result: {:#?}", e));
-                    handle_error(error, true, &sender).await;
+                    handle_error(e, false, &sender).await;
                     return;
                 }
             };
-            let message = ws::Message::Binary(message);
+
+            let update = real_time::Update::new().search(SearchResult { songs, search_id });
+            let message = Outgoing::Update(update);
             if let Err(e) = sender.send(message).await {
                 eprintln!("Error sending ws message: {:?}", e);
                 return;
             }
         }
         real_time::Request::Position { percentage } => {
-            if only_host(
-                &id,
-                "Only a host can update the current position of a song, this is a bug, terminating socket connection",
-                &sender,
-            )
-            .await
-            .is_err() {
-                return;
-            }
+            match set_current_song_position(id.jam_id(), percentage, &mut transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::VoteSkip => {
+            let your_id = match only_user(&id, "Only users can vote to skip", &sender).await {
+                Ok(id) => id,
+                Err(_) => return,
+            };
 
-            match set_current_song_position(
-                id.jam_id(),
-                percentage,
-                credentials.clone(),
-                &mut transaction,
-            )
-            .await
-            {
+            match vote_skip(your_id, id.jam_id(), &mut transaction, &provider).await {
                 Ok(changed_new) => {
                     changed = changed.merge_with_other(changed_new);
                 }
                 Err(e) => {
-                    errors.push(e);
+                    handle_error(e, false, &sender).await;
+                    return;
                 }
             };
         }
+        real_time::Request::UpdateSettings { settings } => {
+            match update_settings(id.jam_id(), &settings, &mut transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::ApproveSong { song_id } => {
+            match approve_song(&song_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::PinSong { song_id, pinned } => {
+            match pin_song(&song_id, id.jam_id(), pinned, &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::BanUser { user_id } => {
+            match ban_user(&user_id, id.jam_id(), &mut transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::UnbanUser { ban_id } => {
+            match unban_user(&ban_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::AdmitUser { user_id } => {
+            match admit_user(&user_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::RejectUser { user_id } => {
+            match reject_user(&user_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::CreateInvite { hours, uses } => {
+            match create_invite(id.jam_id(), hours, uses, &secret_key, &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::DeleteInvite { invite_id } => {
+            match delete_invite(&invite_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::RejectSong { song_id } => {
+            match reject_song(&song_id, id.jam_id(), &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::SetPermissions {
+            user_id,
+            permissions,
+        } => {
+            match set_permissions(&user_id, id.jam_id(), &permissions, &mut *transaction).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::Skip => {
+            match skip_song(id.jam_id(), &mut transaction, &provider).await {
+                Ok(changed_new) => {
+                    changed = changed.merge_with_other(changed_new);
+                }
+                Err(e) => {
+                    handle_error(e, false, &sender).await;
+                    return;
+                }
+            };
+        }
+        real_time::Request::Snapshot => {
+            if let Err(e) = catchup.send(Catchup::Snapshot).await {
+                eprintln!("Error asking for a snapshot: {:?}", e);
+            }
+        }
+        real_time::Request::Resume { last_seq } => {
+            if let Err(e) = catchup.send(Catchup::Resume { last_seq }).await {
+                eprintln!("Error asking for the missed updates: {:?}", e);
+            }
+        }
     }
 
-    if let Err(e) = notify(changed, errors, id.jam_id(), &mut transaction).await {
+    // the errors were sent back on this socket above, the other clients of the jam only get what changed
+    if let Err(e) = notify(changed, id.jam_id(), &mut transaction).await {
         handle_error(e.into(), false, &sender).await;
     }
 
@@ -270,36 +456,98 @@ async fn handle_message(
 
 use super::Id;
 
-///returns id of host, if the id is not a host, it returns an error
-async fn only_host<'a>(
+/// returns the user id if the id is a user, otherwise sends an error message and returns an error.
+/// The requests it is used for are only allowed for users in the permission table, so the error is not sent,
+/// it stays because the handlers need the user id, which the table can't hand them
+async fn only_user<'a>(
     id: &'a Id,
     message: &str,
-    sender: &mpsc::Sender<ws::Message>,
+    sender: &mpsc::Sender<Outgoing>,
 ) -> Result<&'a String, ()> {
     match &id.id {
-        IdType::Host(id) => Ok(id),
+        IdType::User(id) => Ok(id),
         _ => {
             let error = Error::Forbidden(message.to_string());
 
-            handle_error(error, true, sender).await;
+            handle_error(error, false, sender).await;
             Err(())
         }
     }
 }
 
-/// returns the user id if the id is a user, otherwise sends an error message and returns an error
-async fn only_user<'a>(
-    id: &'a Id,
-    message: &str,
-    sender: &mpsc::Sender<ws::Message>,
-) -> Result<&'a String, ()> {
-    match &id.id {
-        IdType::User(id) => Ok(id),
-        _ => {
-            let error = Error::Forbidden(message.to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            handle_error(error, true, sender).await;
-            Err(())
+    #[sqlx::test(migrations = "db/migrations")]
+    async fn a_forbidden_request_keeps_the_socket_open(pool: sqlx::PgPool) {
+        let provider = Provider::Fake(FakeProvider::default());
+        let host_id = cuid2::create_id();
+        sqlx::query("INSERT INTO hosts (id) VALUES ($1)")
+            .bind(&host_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut transaction = pool.begin().await.unwrap();
+        let jam_id = create_jam(
+            "forbidden",
+            &host_id,
+            10,
+            &JamSettings::default(),
+            &mut transaction,
+            &provider,
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+        let user_id = cuid2::create_id();
+        sqlx::query("INSERT INTO users (id, jam_id, name) VALUES ($1, $2, $3)")
+            .bind(&user_id)
+            .bind(&jam_id)
+            .bind("someone")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let id = Id::new(IdType::User(user_id), jam_id);
+        let (catchup, mut catchup_receiver) = mpsc::channel(3);
+
+        // only the host sends the position of the song
+        let (sender, mut receiver) = mpsc::channel(3);
+        let request = real_time::Request::Position { percentage: 0.5 };
+        handle_message(
+            request,
+            sender,
+            id.clone(),
+            pool.clone(),
+            provider.clone(),
+            "secret".to_string(),
+            catchup.clone(),
+        )
+        .await;
+        match receiver.recv().await {
+            Some(Outgoing::Update(update)) => {
+                assert!(matches!(update.errors.as_slice(), [Error::Forbidden(_)]))
+            }
+            outgoing => panic!("expected an update with the error, got {:?}", outgoing),
         }
+        // the socket is not closed
+        assert!(receiver.recv().await.is_none());
+
+        // and the next request of the user is handled
+        let (sender, _receiver) = mpsc::channel(3);
+        handle_message(
+            real_time::Request::Snapshot,
+            sender,
+            id,
+            pool.clone(),
+            provider,
+            "secret".to_string(),
+            catchup,
+        )
+        .await;
+        assert!(matches!(
+            catchup_receiver.recv().await,
+            Some(Catchup::Snapshot)
+        ));
     }
 }
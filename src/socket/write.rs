@@ -1,97 +1,147 @@
-use super::{handle_error, Id};
+use super::{handle_error, Catchup, Id, Outgoing};
 use crate::model::*;
-use axum::extract::ws;
-use sqlx::postgres::PgListener;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 
-pub async fn write(sender: mpsc::Sender<ws::Message>, id: Id, app_state: AppState) {
+pub async fn write(
+    sender: mpsc::Sender<Outgoing>,
+    mut id: Id,
+    app_state: AppState,
+    mut catchup: mpsc::Receiver<Catchup>,
+) {
     let pool = app_state.db.pool;
-    let mut listener = match create_listener(&pool, &id).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            handle_error(e, false, &sender).await;
-            return;
-        }
+    // the spotify player on the host page knows the current song by itself, other players need to be told
+    let host_needs_current_song = !app_state.music_provider.is_spotify();
+    let (mut seq, mut receiver) = app_state.hub.subscribe(id.jam_id());
+    // once a user is let out of the waiting room they need everything they missed
+    let mut waiting = match &id.id {
+        IdType::User(user_id) => match is_user_waiting(user_id, &pool).await {
+            Ok(waiting) => waiting,
+            Err(e) => {
+                handle_error(e.into(), false, &sender).await;
+                false
+            }
+        },
+        _ => false,
     };
 
-    while let Ok(m) = listener.try_recv().await {
-        match m {
-            None => {
-                let error =
-                    Error::Database("pool disconnected on listener, reconnecting...".to_string());
-                handle_error(error, false, &sender).await;
+    loop {
+        let shared = tokio::select! {
+            received = receiver.recv() => match received {
+                Ok(shared) => shared,
+                // the socket fell behind, the client sees the gap in the seq and asks for a snapshot
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Some(catchup) = catchup.recv() => {
+                let message = catch_up(catchup, waiting, seq, &id, &app_state.hub, &pool).await;
+                if !send(message, &id, host_needs_current_song, &sender).await {
+                    break;
+                }
                 continue;
             }
-            Some(message) => {
-                let update = message.payload();
-                let update: real_time::ChannelUpdate = match serde_json::from_str(update) {
-                    Ok(update) => update,
-                    Err(e) => {
-                        let error = Error::Decode(format!(
-                            "Error decoding message sent in listen/notify: {:#?}",
-                            e
-                        ));
-                        handle_error(error, true, &sender).await;
-                        break;
-                    }
-                };
-
-                let mut changed = update.changed;
-                let errors = update.errors;
-                if id.is_host() {
-                    changed.position = false;
-                    changed.current_song = false;
-                }
+        };
+        seq = shared.update.seq.unwrap_or(seq);
 
-                let mut transaction = match pool.begin().await {
-                    Ok(transaction) => transaction,
-                    Err(e) => {
-                        let error = Error::Database(format!("Error starting transaction: {:?}", e));
-                        handle_error(error, true, &sender).await;
-                        continue;
-                    }
-                };
-                let message = real_time::Update::from_changed(changed, &id, &mut transaction)
-                    .await
-                    .error_vec(errors);
-                if let Err(e) = transaction.commit().await {
-                    let error = Error::Database(format!("Error committing transaction: {:?}", e));
-                    handle_error(error, true, &sender).await;
-                    continue;
+        let was_waiting = waiting;
+        let mut permissions_changed = false;
+        if let IdType::User(user_id) = &id.id {
+            if let Some(join_requests) = &shared.update.join_requests {
+                waiting = join_requests.iter().any(|user| &user.id == user_id);
+            }
+            if let Some(you) = shared.update.find_user(user_id) {
+                waiting = false;
+                // the host may have changed what a co-host can do, then they need everything they can see now
+                if you.permissions != id.permissions {
+                    id = id.clone().with_permissions(you.permissions.clone());
+                    permissions_changed = true;
                 }
+            }
+        }
 
-                let bin = match rmp_serde::to_vec(&message) {
-                    Ok(bin) => bin,
-                    Err(e) => {
-                        let error =
-                            Error::Decode(format!("Error encoding message sent in ws: {:?}", e));
-                        handle_error(error, true, &sender).await;
-                        break;
-                    }
-                };
-
-                match sender.send(ws::Message::Binary(bin)).await {
-                    Ok(_) => (),
-                    Err(e) => {
-                        eprintln!("Error sending ws send message: {:?}", e);
-                        break;
-                    }
+        let message = if waiting {
+            real_time::Update::new().waiting()
+        } else if was_waiting || permissions_changed {
+            // a user turned away from the waiting room is gone, they get nothing of the jam
+            if let IdType::User(user_id) = &id.id {
+                if let Err(e) = check_user_exists(user_id, &pool).await {
+                    handle_error(e, true, &sender).await;
+                    break;
                 }
             }
+            load_everything(&pool, &id)
+                .await
+                .merge_with_other(shared.for_id(&id), false)
+                .snapshot(seq)
+        } else {
+            shared.for_id(&id)
+        };
+
+        if !send(message, &id, host_needs_current_song, &sender).await {
+            break;
         }
     }
 }
 
-async fn create_listener<'e>(pool: &sqlx::PgPool, id: &Id) -> Result<PgListener, Error> {
-    let mut listener = match PgListener::connect_with(pool).await {
-        Ok(listener) => listener,
+/// the updates up to seq were sent already, so they are in the snapshot, or are sent again with the missed ones
+async fn catch_up(
+    catchup: Catchup,
+    waiting: bool,
+    seq: u64,
+    id: &Id,
+    hub: &Hub,
+    pool: &sqlx::PgPool,
+) -> real_time::Update {
+    let missed = match catchup {
+        // a user in the waiting room is only told that they are waiting
+        Catchup::Resume { last_seq } if !waiting => hub.missed(id.jam_id(), last_seq, seq),
+        _ => None,
+    };
+    let resumed = missed.and_then(|missed| {
+        real_time::Update::resumed(missed.iter().map(|shared| shared.for_id(id)), seq)
+    });
+
+    match resumed {
+        Some(resumed) => resumed,
+        None => load_everything(pool, id).await.snapshot(seq),
+    }
+}
+
+async fn load_everything(pool: &sqlx::PgPool, id: &Id) -> real_time::Update {
+    let mut transaction = match pool.begin().await {
+        Ok(transaction) => transaction,
         Err(e) => {
-            return Err(Error::Database(e.to_string()));
+            let error = Error::Database(format!("Error starting transaction: {:?}", e));
+            return real_time::Update::new().error(error);
         }
     };
+    let message =
+        real_time::Update::from_changed(real_time::Changed::all(), id, &mut transaction).await;
+    if let Err(e) = transaction.commit().await {
+        let error = Error::Database(format!("Error committing transaction: {:?}", e));
+        return message.error(error);
+    }
+    message
+}
 
-    match listener.listen(id.jam_id()).await {
-        Ok(_) => Ok(listener),
-        Err(e) => Err(Error::Database(e.to_string())),
+/// returns false if the socket is gone
+async fn send(
+    mut message: real_time::Update,
+    id: &Id,
+    host_needs_current_song: bool,
+    sender: &mpsc::Sender<Outgoing>,
+) -> bool {
+    if id.is_host() {
+        message.position = None;
+        if !host_needs_current_song {
+            message.current_song = None;
+        }
+    }
+
+    match sender.send(Outgoing::Update(message)).await {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("Error sending ws send message: {:?}", e);
+            false
+        }
     }
 }
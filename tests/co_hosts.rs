@@ -0,0 +1,123 @@
+//! What a co-host can do, only ever in the jam they are a user of.
+//! They need a Postgres server set with `DATABASE_URL`, every test gets a database of its own that is dropped after:
+//! `DATABASE_URL=postgres://... cargo test --features ssr --test co_hosts`
+#![cfg(feature = "ssr")]
+
+use music_jam::model::*;
+use sqlx::PgPool;
+
+async fn start_jam(pool: &PgPool, provider: &Provider) -> String {
+    let host_id = cuid2::create_id();
+    sqlx::query("INSERT INTO hosts (id) VALUES ($1)")
+        .bind(&host_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let mut transaction = pool.begin().await.unwrap();
+    let jam_id = create_jam(
+        "co-hosts",
+        &host_id,
+        10,
+        &JamSettings::default(),
+        &mut transaction,
+        provider,
+    )
+    .await
+    .unwrap();
+    transaction.commit().await.unwrap();
+    jam_id
+}
+
+async fn join(pool: &PgPool, jam_id: &str, name: &str) -> String {
+    let user_id = cuid2::create_id();
+    sqlx::query("INSERT INTO users (id, jam_id, name) VALUES ($1, $2, $3)")
+        .bind(&user_id)
+        .bind(jam_id)
+        .bind(name)
+        .execute(pool)
+        .await
+        .unwrap();
+    user_id
+}
+
+async fn user_exists(pool: &PgPool, user_id: &str) -> bool {
+    sqlx::query("SELECT id FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+        .is_some()
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn a_co_host_kicks_only_users_of_their_jam(pool: PgPool) {
+    let provider = Provider::Fake(FakeProvider::default());
+    let jam_id = start_jam(&pool, &provider).await;
+    let other_jam_id = start_jam(&pool, &provider).await;
+    let user_id = join(&pool, &jam_id, "user").await;
+    let stranger_id = join(&pool, &other_jam_id, "stranger").await;
+
+    // a co-host of the first jam who can kick
+    let mut transaction = pool.begin().await.unwrap();
+    assert!(kick_user(&stranger_id, &jam_id, &mut *transaction)
+        .await
+        .is_err());
+    assert!(kick_user(&jam_id, &jam_id, &mut *transaction)
+        .await
+        .is_err());
+    assert!(kick_user(&other_jam_id, &jam_id, &mut *transaction)
+        .await
+        .is_err());
+    kick_user(&user_id, &jam_id, &mut *transaction)
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    assert!(user_exists(&pool, &stranger_id).await);
+    assert!(user_exists(&pool, &jam_id).await);
+    assert!(user_exists(&pool, &other_jam_id).await);
+    assert!(!user_exists(&pool, &user_id).await);
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn a_co_host_removes_only_queued_songs_of_their_jam(pool: PgPool) {
+    let provider = Provider::Fake(FakeProvider::default());
+    let jam_id = start_jam(&pool, &provider).await;
+    let other_jam_id = start_jam(&pool, &provider).await;
+    let co_host_id = join(&pool, &jam_id, "co-host").await;
+    let co_host = Id::new(IdType::User(co_host_id), jam_id.clone())
+        .with_permissions(vec![Permission::ApproveSongs]);
+
+    let mut transaction = pool.begin().await.unwrap();
+    add_song(
+        "fake-7",
+        &other_jam_id,
+        &other_jam_id,
+        &mut transaction,
+        &provider,
+    )
+    .await
+    .unwrap();
+    let other_song_id = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM songs WHERE user_id = $1 AND status <> 'playing'",
+    )
+    .bind(&other_jam_id)
+    .fetch_one(&mut *transaction)
+    .await
+    .unwrap();
+    let playing_song_id = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM songs WHERE user_id = $1 AND status = 'playing'",
+    )
+    .bind(&jam_id)
+    .fetch_one(&mut *transaction)
+    .await
+    .unwrap();
+
+    assert!(remove_song(&other_song_id, &co_host, &mut transaction)
+        .await
+        .is_err());
+    assert!(remove_song(&playing_song_id, &co_host, &mut transaction)
+        .await
+        .is_err());
+}
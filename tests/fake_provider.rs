@@ -0,0 +1,215 @@
+//! The jam functions with the in memory `FakeProvider`, so they run without spotify.
+//! They need a Postgres server set with `DATABASE_URL`, every test gets a database of its own that is dropped after:
+//! `DATABASE_URL=postgres://... cargo test --features ssr --test fake_provider`
+#![cfg(feature = "ssr")]
+
+use music_jam::model::*;
+use sqlx::PgPool;
+
+async fn start_jam(pool: &PgPool, provider: &Provider, max_song_count: i16) -> String {
+    let host_id = cuid2::create_id();
+    sqlx::query("INSERT INTO hosts (id) VALUES ($1)")
+        .bind(&host_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let mut transaction = pool.begin().await.unwrap();
+    let jam_id = create_jam(
+        "fake",
+        &host_id,
+        max_song_count,
+        &JamSettings::default(),
+        &mut transaction,
+        provider,
+    )
+    .await
+    .unwrap();
+    transaction.commit().await.unwrap();
+    jam_id
+}
+
+async fn join(pool: &PgPool, jam_id: &str, name: &str) -> String {
+    let user_id = cuid2::create_id();
+    sqlx::query("INSERT INTO users (id, jam_id, name) VALUES ($1, $2, $3)")
+        .bind(&user_id)
+        .bind(jam_id)
+        .bind(name)
+        .execute(pool)
+        .await
+        .unwrap();
+    user_id
+}
+
+async fn queue(pool: &PgPool, jam_id: &str) -> Vec<String> {
+    let mut transaction = pool.begin().await.unwrap();
+    let id = Id::new(IdType::General, jam_id.to_string());
+    get_songs(&mut transaction, &id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|song| song.spotify_id)
+        .collect()
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn create_jam_plays_a_song_from_the_library(pool: PgPool) {
+    let fake = FakeProvider::default();
+    let provider = Provider::Fake(fake.clone());
+    let jam_id = start_jam(&pool, &provider, 10).await;
+
+    let current_song = get_current_song(&jam_id, &pool).await.unwrap().unwrap();
+    assert!(fake
+        .library()
+        .iter()
+        .any(|song| song.spotify_id == current_song.spotify_id));
+
+    let mut transaction = pool.begin().await.unwrap();
+    let playing = fake
+        .get_current_song_from_player(&jam_id, &mut transaction)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(playing.spotify_id, current_song.spotify_id);
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn search_leaves_out_the_songs_in_the_jam(pool: PgPool) {
+    let fake = FakeProvider::default();
+    let provider = Provider::Fake(fake.clone());
+    let jam_id = start_jam(&pool, &provider, 10).await;
+    // the song the jam started with is in it already
+    let current_song = get_current_song(&jam_id, &pool).await.unwrap().unwrap();
+    let song = fake
+        .library()
+        .iter()
+        .find(|song| song.spotify_id != current_song.spotify_id)
+        .unwrap();
+    let found_song = |found: &[Song]| found.iter().any(|s| s.spotify_id == song.spotify_id);
+
+    let mut transaction = pool.begin().await.unwrap();
+    let found = search(&song.name, &mut transaction, &jam_id, &provider)
+        .await
+        .unwrap();
+    assert!(found_song(&found));
+    assert!(!found
+        .iter()
+        .any(|s| s.spotify_id == current_song.spotify_id));
+
+    add_song(
+        &song.spotify_id,
+        &jam_id,
+        &jam_id,
+        &mut transaction,
+        &provider,
+    )
+    .await
+    .unwrap();
+    let found = search(&song.name, &mut transaction, &jam_id, &provider)
+        .await
+        .unwrap();
+    assert!(!found_song(&found));
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn add_song_keeps_to_the_limit(pool: PgPool) {
+    let provider = Provider::Fake(FakeProvider::default());
+    let jam_id = start_jam(&pool, &provider, 1).await;
+    let user_id = join(&pool, &jam_id, "someone").await;
+
+    let mut transaction = pool.begin().await.unwrap();
+    add_song("fake-7", &user_id, &jam_id, &mut transaction, &provider)
+        .await
+        .unwrap();
+    let error = add_song("fake-7", &jam_id, &jam_id, &mut transaction, &provider)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::SongAlreadyInJam));
+    let error = add_song("fake-8", &user_id, &jam_id, &mut transaction, &provider)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::UserHasTooTheMaxSongAmount));
+    // the songs of the host are not limited
+    add_song("fake-8", &jam_id, &jam_id, &mut transaction, &provider)
+        .await
+        .unwrap();
+    let error = add_song(
+        "not-in-library",
+        &jam_id,
+        &jam_id,
+        &mut transaction,
+        &provider,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(error, Error::DoesNotExist(_)));
+    transaction.commit().await.unwrap();
+
+    assert_eq!(queue(&pool, &jam_id).await.len(), 2);
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn go_to_next_song_plays_the_top_song(pool: PgPool) {
+    let fake = FakeProvider::default();
+    let provider = Provider::Fake(fake.clone());
+    let jam_id = start_jam(&pool, &provider, 10).await;
+    let user_id = join(&pool, &jam_id, "someone").await;
+
+    let mut transaction = pool.begin().await.unwrap();
+    add_song("fake-7", &user_id, &jam_id, &mut transaction, &provider)
+        .await
+        .unwrap();
+    go_to_next_song(&jam_id, &mut transaction, &provider)
+        .await
+        .unwrap();
+    let playing = fake
+        .get_current_song_from_player(&jam_id, &mut transaction)
+        .await
+        .unwrap()
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    assert_eq!(playing.spotify_id, "fake-7");
+    let current_song = get_current_song(&jam_id, &pool).await.unwrap().unwrap();
+    assert_eq!(current_song.spotify_id, "fake-7");
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn a_user_votes_only_for_the_songs_of_their_jam(pool: PgPool) {
+    let provider = Provider::Fake(FakeProvider::default());
+    let jam_id = start_jam(&pool, &provider, 10).await;
+    let other_jam_id = start_jam(&pool, &provider, 10).await;
+    let user_id = join(&pool, &jam_id, "someone").await;
+    let stranger_id = join(&pool, &other_jam_id, "stranger").await;
+
+    let mut transaction = pool.begin().await.unwrap();
+    add_song(
+        "fake-7",
+        &stranger_id,
+        &other_jam_id,
+        &mut transaction,
+        &provider,
+    )
+    .await
+    .unwrap();
+    let song_id = sqlx::query_scalar::<_, String>("SELECT id FROM songs WHERE user_id = $1")
+        .bind(&stranger_id)
+        .fetch_one(&mut *transaction)
+        .await
+        .unwrap();
+
+    let error = add_vote(&song_id, &user_id, &jam_id, &mut transaction)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::DoesNotExist(_)));
+    let error = add_downvote(&song_id, &user_id, &jam_id, &mut transaction)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::DoesNotExist(_)));
+    transaction.commit().await.unwrap();
+
+    assert_eq!(
+        queue(&pool, &other_jam_id).await,
+        vec!["fake-7".to_string()]
+    );
+}
@@ -0,0 +1,205 @@
+//! The background work of a jam with two servers running against one database, like replicas behind a load balancer.
+//! A server here is not a whole `AppState` with a hub and a listener, only what decides who runs the background work:
+//! a `Leader` with its own connection for the advisory locks, and a provider of its own. They share the pool of the test.
+//! They need a Postgres server set with `DATABASE_URL`, every test gets a database of its own that is dropped after:
+//! `DATABASE_URL=postgres://... cargo test --features ssr --test multi_instance`
+#![cfg(feature = "ssr")]
+
+use music_jam::model::*;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+/// longer than a few ticks of the background work of a jam
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Server {
+    pool: PgPool,
+    /// shares its players with `provider`, it tells what the background work on this server did
+    fake: FakeProvider,
+    provider: Provider,
+    leader: Leader,
+    /// the jams a host is connected to on this server
+    watched: Vec<String>,
+}
+
+impl Server {
+    fn start(pool: &PgPool) -> Self {
+        let fake = FakeProvider::default();
+        Self {
+            pool: pool.clone(),
+            provider: Provider::Fake(fake.clone()),
+            fake,
+            leader: Leader::new(pool),
+            watched: Vec::new(),
+        }
+    }
+
+    /// a host of the jam connects to this server
+    fn watch(&mut self, jam_id: &str) {
+        watch_jam(jam_id, &self.pool, &self.provider, &self.leader);
+        self.watched.push(jam_id.to_string());
+    }
+}
+
+/// the background work stops even if an assert failed
+impl Drop for Server {
+    fn drop(&mut self) {
+        for jam_id in &self.watched {
+            self.leader.host_left(jam_id);
+        }
+    }
+}
+
+/// polls until the condition is true, fails the test if it takes longer than `TIMEOUT`
+async fn eventually<F: Future<Output = bool>>(what: &str, mut condition: impl FnMut() -> F) {
+    let started_at = tokio::time::Instant::now();
+    while !condition().await {
+        assert!(
+            started_at.elapsed() < TIMEOUT,
+            "timed out waiting for {}",
+            what
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// the lock connection of the server leading the jam is cut, like when the server goes away
+async fn drop_lock_connection(pool: &PgPool) {
+    sqlx::query(
+        "SELECT pg_terminate_backend(pid) FROM pg_locks
+        WHERE locktype = 'advisory' AND granted
+        AND database = (SELECT oid FROM pg_database WHERE datname = current_database())",
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// the jam is made with a provider of no server, so the providers of the servers only count their background work
+async fn start_jam(pool: &PgPool) -> String {
+    let provider = &Provider::Fake(FakeProvider::default());
+    let host_id = cuid2::create_id();
+    sqlx::query("INSERT INTO hosts (id) VALUES ($1)")
+        .bind(&host_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let mut transaction = pool.begin().await.unwrap();
+    let jam_id = create_jam(
+        "two servers",
+        &host_id,
+        10,
+        &JamSettings::default(),
+        &mut transaction,
+        provider,
+    )
+    .await
+    .unwrap();
+    // the host adds songs as the jam user, others than the one playing
+    let playing = get_current_song(&jam_id, &mut *transaction)
+        .await
+        .unwrap()
+        .unwrap();
+    let song_ids = ["fake-6", "fake-7", "fake-8"]
+        .into_iter()
+        .filter(|song_id| *song_id != playing.spotify_id)
+        .take(2);
+    for song_id in song_ids {
+        add_song(song_id, &jam_id, &jam_id, &mut transaction, provider)
+            .await
+            .unwrap();
+    }
+    transaction.commit().await.unwrap();
+    jam_id
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn one_server_leads_and_the_other_takes_over(pool: PgPool) {
+    let first = Server::start(&pool);
+    let second = Server::start(&pool);
+    let jam_id = start_jam(&pool).await;
+
+    assert!(first.leader.lead(&jam_id).await);
+    assert!(!second.leader.lead(&jam_id).await);
+    // the leader stays the leader
+    assert!(first.leader.lead(&jam_id).await);
+    assert!(!second.leader.lead(&jam_id).await);
+
+    first.leader.resign(&jam_id).await;
+    assert!(second.leader.lead(&jam_id).await);
+    assert!(!first.leader.lead(&jam_id).await);
+}
+
+#[sqlx::test(migrations = "db/migrations")]
+async fn song_advances_once_with_two_servers(pool: PgPool) {
+    let mut first = Server::start(&pool);
+    let mut second = Server::start(&pool);
+    let jam_id = start_jam(&pool).await;
+    let (pool, jam_id) = (&pool, jam_id.as_str());
+
+    // a host is connected to both servers
+    first.watch(jam_id);
+    second.watch(jam_id);
+    let servers = [&first, &second];
+    // asking a server that doesn't lead can't take the lock from the one that does
+    eventually("a server to lead the jam", move || async move {
+        servers[0].leader.lead(jam_id).await || servers[1].leader.lead(jam_id).await
+    })
+    .await;
+    let mut leaders = Vec::new();
+    for server in servers {
+        leaders.push(server.leader.lead(jam_id).await);
+    }
+    assert_eq!(leaders.iter().filter(|led| **led).count(), 1);
+    let (leader, follower) = if leaders[0] {
+        (&first, &second)
+    } else {
+        (&second, &first)
+    };
+
+    let song_before = get_current_song(jam_id, pool).await.unwrap().unwrap();
+    let played_before = get_history(jam_id, None, pool).await.unwrap().len();
+    // the player reached the end of the song
+    let mut transaction = pool.begin().await.unwrap();
+    set_current_song_position(jam_id, 1.0, &mut transaction)
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    eventually("the next song", move || async move {
+        get_history(jam_id, None, pool).await.unwrap().len() > played_before
+    })
+    .await;
+    // a few more ticks, a second server advancing too would have done it by now
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let played_after = get_history(jam_id, None, pool).await.unwrap().len();
+    assert_eq!(played_after, played_before + 1);
+    assert_eq!(get_current_song_position(jam_id, pool).await.unwrap(), 0.0);
+
+    // only the leading server asked the player and moved it on,
+    // it may have played the song it found the player without first
+    let current_song = get_current_song(jam_id, pool).await.unwrap().unwrap();
+    assert_ne!(current_song.spotify_id, song_before.spotify_id);
+    let played = leader.fake.played(jam_id);
+    assert!(leader.fake.polls(jam_id) > 0);
+    assert_eq!(played.last(), Some(&current_song.spotify_id));
+    let advanced_to = played
+        .iter()
+        .filter(|song_id| **song_id != song_before.spotify_id)
+        .collect::<HashSet<_>>();
+    assert_eq!(advanced_to.len(), 1);
+    assert_eq!(follower.fake.polls(jam_id), 0);
+    assert!(follower.fake.played(jam_id).is_empty());
+
+    // the leading server goes away without letting go of the lock, it is gone with its connection
+    leader.leader.host_left(jam_id);
+    drop_lock_connection(pool).await;
+    eventually("the other server to take over", move || async move {
+        follower.fake.polls(jam_id) > 0
+    })
+    .await;
+    assert!(follower.leader.lead(jam_id).await);
+}